@@ -0,0 +1,46 @@
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+use twintaillauncher_lib::{lint_repository, manifest_schemas};
+
+const USAGE: &str = "Usage: manifest-lint <repository directory>\n       manifest-lint --schema <output directory>";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+
+    match args.as_slice() {
+        [flag, out] if flag == "--schema" => {
+            let out = Path::new(out);
+            fs::create_dir_all(out).unwrap();
+
+            for (name, schema) in manifest_schemas() {
+                fs::write(out.join(name), serde_json::to_string_pretty(&schema).unwrap()).unwrap();
+                println!("Wrote {}", out.join(name).display());
+            }
+            ExitCode::SUCCESS
+        }
+        [path] if !path.starts_with("--") => {
+            let report = lint_repository(Path::new(path));
+            let errors = report.errors;
+
+            for w in &report.warnings {
+                eprintln!("warning: {}: {}: {}", w.file, w.path, w.reason);
+            }
+            for e in &errors {
+                eprintln!("{}: {}: {}", e.file, e.path, e.reason);
+            }
+
+            if errors.is_empty() {
+                println!("No problems found in {}", path);
+                ExitCode::SUCCESS
+            } else {
+                eprintln!("Found {} problem(s) in {}", errors.len(), path);
+                ExitCode::FAILURE
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -6,67 +6,79 @@ use tauri_plugin_notification::NotificationExt;
 use std::path::Path;
 use std::sync::Arc;
 use fischl::compat::Compat;
-use fischl::download::{Compatibility, Extras};
-use fischl::utils::{extract_archive, prettify_bytes};
+use fischl::utils::prettify_bytes;
 use fischl::utils::free_space::available;
-use tauri::{AppHandle, Emitter};
-use crate::utils::db_manager::{create_installation, delete_installation_by_id, get_install_info_by_id, get_installs, get_installs_by_manifest_id, get_manifest_info_by_filename, get_manifest_info_by_id, get_settings, update_install_dxvk_location_by_id, update_install_dxvk_version_by_id, update_install_env_vars_by_id, update_install_fps_value_by_id, update_install_game_location_by_id, update_install_ignore_updates_by_id, update_install_launch_args_by_id, update_install_launch_cmd_by_id, update_install_pre_launch_cmd_by_id, update_install_prefix_location_by_id, update_install_runner_location_by_id, update_install_runner_version_by_id, update_install_skip_hash_check_by_id, update_install_use_fps_unlock_by_id, update_install_use_jadeite_by_id, update_install_use_xxmi_by_id};
+use tauri::{AppHandle, Emitter, Error};
+use std::io::ErrorKind;
+use crate::utils::db_manager::{create_installation, create_launch_profile, delete_installation_by_id, delete_launch_profile_by_id, get_default_launch_profile_by_install_id, get_install_info_by_id, get_launch_profile_by_id, get_launch_profiles_by_install_id, set_default_launch_profile_by_id, update_launch_profile_by_id, get_installs, get_installs_by_manifest_id, get_manifest_info_by_id, get_settings, set_install_env_var_by_id, delete_install_env_var_by_id, update_install_dxvk_location_by_id, update_install_dxvk_version_by_id, update_install_env_var_enabled_by_id, update_install_fps_value_by_id, update_install_game_location_by_id, update_install_ignore_updates_by_id, update_install_launch_args_by_id, update_install_launch_cmd_by_id, update_install_pre_launch_cmd_by_id, update_install_prefix_location_by_id, update_install_runner_location_by_id, update_install_runner_version_by_id, update_install_skip_hash_check_by_id, update_install_use_fps_unlock_by_id, update_install_use_jadeite_by_id, update_install_use_xxmi_by_id, DbError};
+use crate::utils::extras_manager::{install_extra_async, spawn_extra_install, ExtraError, FPS_UNLOCK_EXTRA, JADEITE_EXTRA, XXMI_EXTRA};
 use crate::utils::game_launch_manager::launch;
-use crate::utils::{copy_dir_all, generate_cuid, runner_from_runner_version, AddInstallRsp, DownloadSizesRsp};
-use crate::utils::repo_manager::{get_compatibility, get_manifest, GameVersion};
-
-#[cfg(target_os = "linux")]
-use std::os::unix::fs::symlink;
+use crate::utils::{copy_dir_all, generate_cuid, install_compatibility, parse_legacy_env_vars, parse_legacy_launch_args, AddInstallRsp, DownloadSizesRsp};
+use crate::utils::repo_manager::{get_compatibility_by_version, get_manifest, manifest_key, GameVersion, LaunchProfile, RunnerManifest, RunnerVersion};
+use crate::utils::system_tray::refresh_tray_menu;
 
 #[tauri::command]
-pub async fn list_installs(app: AppHandle) -> Option<String> {
-    let installs = get_installs(&app);
+pub async fn list_installs(app: AppHandle) -> Result<Option<String>, DbError> {
+    let installs = get_installs(&app)?;
 
-    if installs.is_some() {
-        let install = installs.unwrap();
-        let stringified = serde_json::to_string(&install).unwrap();
-        Some(stringified)
+    if installs.is_empty() {
+        Ok(None)
     } else {
-        None
+        let stringified = serde_json::to_string(&installs).unwrap();
+        Ok(Some(stringified))
     }
 }
 
 #[tauri::command]
-pub fn list_installs_by_manifest_id(app: AppHandle, manifest_id: String) -> Option<String> {
-    let installs = get_installs_by_manifest_id(&app, manifest_id);
+pub fn list_installs_by_manifest_id(app: AppHandle, manifest_id: String) -> Result<Option<String>, DbError> {
+    let installs = get_installs_by_manifest_id(&app, manifest_id)?;
 
-    if installs.is_some() {
-        let install = installs.unwrap();
-        let stringified = serde_json::to_string(&install).unwrap();
-        Some(stringified)
+    if installs.is_empty() {
+        Ok(None)
     } else {
-        None
+        let stringified = serde_json::to_string(&installs).unwrap();
+        Ok(Some(stringified))
     }
 }
 
 #[tauri::command]
-pub fn get_install_by_id(app: AppHandle, id: String) -> Option<String> {
-    let inst = get_install_info_by_id(&app, id);
+pub fn get_install_by_id(app: AppHandle, id: String) -> Result<Option<String>, DbError> {
+    let inst = get_install_info_by_id(&app, id)?;
 
     if inst.is_some() {
         let install = inst.unwrap();
         let stringified = serde_json::to_string(&install).unwrap();
-        Some(stringified)
+        Ok(Some(stringified))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn add_install(app: AppHandle, manifest_id: String, version: String, audio_lang: String, name: String, mut directory: String, mut runner_path: String, mut dxvk_path: String, runner_version: String, dxvk_version: String, game_icon: String, game_background: String, ignore_updates: bool, skip_hash_check: bool, use_jadeite: bool, use_xxmi: bool, use_fps_unlock: bool, env_vars: String, pre_launch_command: String, launch_command: String, fps_value: String, runner_prefix: String, launch_args: String, skip_game_dl: bool) -> Option<AddInstallRsp> {
+pub fn add_install(app: AppHandle, manifest_id: String, version: String, audio_lang: String, name: String, mut directory: String, mut runner_path: String, mut dxvk_path: String, runner_version: String, dxvk_version: String, game_icon: String, game_background: String, ignore_updates: bool, skip_hash_check: bool, use_jadeite: bool, use_xxmi: bool, use_fps_unlock: bool, env_vars: String, pre_launch_command: String, launch_command: String, fps_value: String, runner_prefix: String, launch_args: String, skip_game_dl: bool) -> Result<Option<AddInstallRsp>, DbError> {
     if manifest_id.is_empty() || version.is_empty() || name.is_empty() || directory.is_empty() || runner_path.is_empty() || dxvk_path.is_empty() || game_icon.is_empty() || game_background.is_empty() {
-        None
+        Ok(None)
     } else {
         let cuid = generate_cuid();
-        let m = manifest_id + ".json";
-        let dbm = get_manifest_info_by_filename(&app, m.clone()).unwrap();
-        let gm = get_manifest(&app, m.clone()).unwrap();
-        let g = gm.game_versions.iter().find(|e| e.metadata.version == version).unwrap();
+        let dbm = match get_manifest_info_by_id(&app, manifest_id.clone())? {
+            Some(m) => m,
+            None => return Ok(None)
+        };
+        let gm = match get_manifest(&app, manifest_key(&dbm.repository_id, &dbm.filename)) {
+            Some(m) => m,
+            None => return Ok(None)
+        };
+        let g = match gm.game_versions.iter().find(|e| e.metadata.version == version) {
+            Some(g) => g,
+            None => return Ok(None)
+        };
+
+        // Resolve everything before touching the disk so a bad version leaves nothing behind
+        #[cfg(target_os = "linux")]
+        let ((rm, runnerp), (_, dxvkp)) = match (compatibility_or_notify(&app, &runner_version), compatibility_or_notify(&app, &dxvk_version)) {
+            (Some(r), Some(d)) => (r, d),
+            _ => return Ok(None)
+        };
 
         let install_location = Path::new(directory.as_str()).to_path_buf();
         if !install_location.exists() {
@@ -96,43 +108,35 @@ pub fn add_install(app: AppHandle, manifest_id: String, version: String, audio_l
             if !Path::exists(runner_path.as_ref()) { fs::create_dir_all(runner_path.clone()).unwrap(); }
             if !Path::exists(dxvk_path.as_ref()) { fs::create_dir_all(dxvk_path.clone()).unwrap(); }
             if !Path::exists(runner_prefix.as_ref()) { fs::create_dir_all(runner_prefix.clone()).unwrap(); }
-            
+
             let archandle = Arc::new(app.clone());
             let runv = Arc::new(runner_version.clone());
             let dxvkpp = Arc::new(dxvk_path.clone());
             let runpp = Arc::new(runner_path.clone());
-            let dxvkv = Arc::new(dxvk_version.clone());
             let rpp = Arc::new(runner_prefix.clone());
 
             std::thread::spawn(move || {
-                let rm = get_compatibility(archandle.as_ref(), &runner_from_runner_version(runv.as_str().to_string()).unwrap()).unwrap();
-                let rv = rm.versions.into_iter().filter(|v| v.version.as_str() == runv.as_str()).collect::<Vec<_>>();
-                let runnerp = rv.get(0).unwrap().to_owned();
                 let rp = Path::new(runpp.as_str()).to_path_buf();
                 let dxp = Path::new(dxvkpp.as_str()).to_path_buf();
 
                 // Download selected DXVK
-                let dm = get_compatibility(archandle.as_ref(), &runner_from_runner_version(dxvkv.as_str().to_string()).unwrap()).unwrap();
-                let dv = dm.versions.into_iter().filter(|v| v.version.as_str() == dxvkv.as_str()).collect::<Vec<_>>();
-                let dxvkp = dv.get(0).unwrap().to_owned();
                 if fs::read_dir(dxvkpp.as_str().to_string()).unwrap().next().is_none() { 
-                    let r = Compatibility::download_dxvk(dxvkp.url, dxvkpp.as_str().to_string());
-                    if r { extract_archive(dxp.join("dxvk.zip").to_str().unwrap().to_string(), dxp.to_str().unwrap().to_string(), true); }
+                    if let Err(e) = install_compatibility(&dxvkp, &dxp, true) { notify_compatibility_failure(archandle.as_ref(), &dxvkp.version, e); }
                 }
 
                 if fs::read_dir(rp.as_path()).unwrap().next().is_none() {
                     archandle.emit("download_progress", runv.as_str().to_string()).unwrap();
 
-                    let r0 = Compatibility::download_runner(runnerp.url, runpp.as_str().to_string());
-                    if r0 {
-                        let er = extract_archive(rp.join("runner.zip").to_str().unwrap().to_string(), rp.to_str().unwrap().to_string(), true);
+                    let r0 = install_compatibility(&runnerp, &rp, false);
+                    if let Err(e) = &r0 { notify_compatibility_failure(archandle.as_ref(), &runnerp.version, e.clone()); }
+                    if r0.is_ok() {
                         let wine64 = if rm.paths.wine64.is_empty() { rm.paths.wine32 } else { rm.paths.wine64 };
                         let winebin = rp.join(wine64).to_str().unwrap().to_string();
                         let is_proton = rm.display_name.to_ascii_lowercase().contains("proton") && !rm.display_name.to_ascii_lowercase().contains("wine");
 
                         if is_proton {  } else {
                             let r1 = Compat::setup_prefix(winebin, rpp.as_str().to_string());
-                            if r1.is_ok() && er {
+                            if r1.is_ok() {
                                 let r = r1.unwrap();
                                 let r2 = Compat::stop_processes(r.wine.binary.to_str().unwrap().to_string(), rpp.as_str().to_string(), false);
                                 if r2.is_ok() {
@@ -166,21 +170,22 @@ pub fn add_install(app: AppHandle, manifest_id: String, version: String, audio_l
                 }
             });
         }
-        create_installation(&app, cuid.clone(), dbm.id, version, audio_lang, g.metadata.versioned_name.clone(), directory, runner_path, dxvk_path, runner_version, dxvk_version, g.assets.game_icon.clone(), g.assets.game_background.clone(), ignore_updates, skip_hash_check, use_jadeite, use_xxmi, use_fps_unlock, env_vars, pre_launch_command, launch_command, fps_value, runner_prefix, launch_args).unwrap();
-        Some(AddInstallRsp {
+        create_installation(&app, cuid.clone(), dbm.id, version, audio_lang, g.metadata.versioned_name.clone(), directory, runner_path, dxvk_path, runner_version, dxvk_version, g.assets.game_icon.clone(), g.assets.game_background.clone(), ignore_updates, skip_hash_check, use_jadeite, use_xxmi, use_fps_unlock, &parse_legacy_env_vars(env_vars.as_str()), pre_launch_command, launch_command, fps_value, runner_prefix, &parse_legacy_launch_args(launch_args.as_str()))?;
+        refresh_tray_menu(&app);
+        Ok(Some(AddInstallRsp {
             success: true,
             install_id: cuid.clone(),
             background: g.assets.game_background.clone()
-        })
+        }))
     }
 }
 
 #[tauri::command]
-pub async fn remove_install(app: AppHandle, id: String, wipe_prefix: bool) -> Option<bool> {
+pub async fn remove_install(app: AppHandle, id: String, wipe_prefix: bool) -> Result<Option<bool>, DbError> {
     if id.is_empty() {
-        None
+        Ok(None)
     } else {
-        let install = get_install_info_by_id(&app, id.clone());
+        let install = get_install_info_by_id(&app, id.clone())?;
 
         if install.is_some() {
             let i = install.unwrap();
@@ -192,17 +197,18 @@ pub async fn remove_install(app: AppHandle, id: String, wipe_prefix: bool) -> Op
             }
 
             if fs::exists(installdir.clone()).unwrap() { fs::remove_dir_all(installdir.clone()).unwrap(); }
-            delete_installation_by_id(&app, id.clone()).unwrap();
-            Some(true)
+            delete_installation_by_id(&app, id.clone())?;
+            refresh_tray_menu(&app);
+            Ok(Some(true))
         } else {
-            None
+            Ok(None)
         }
     }
 }
 
 #[tauri::command]
-pub fn update_install_game_path(app: AppHandle, id: String, path: String) -> Option<bool> {
-    let install = get_install_info_by_id(&app, id);
+pub fn update_install_game_path(app: AppHandle, id: String, path: String) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(&app, id)?;
 
     if install.is_some() {
         let m = install.unwrap();
@@ -231,16 +237,16 @@ pub fn update_install_game_path(app: AppHandle, id: String, path: String) -> Opt
                 });
             }
         }
-        update_install_game_location_by_id(&app, m.id, np);
-        Some(true)
+        update_install_game_location_by_id(&app, m.id, np)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_runner_path(app: AppHandle, id: String, path: String) -> Option<bool> {
-    let install = get_install_info_by_id(&app, id);
+pub fn update_install_runner_path(app: AppHandle, id: String, path: String) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(&app, id)?;
 
     if install.is_some() {
         let m = install.unwrap();
@@ -268,16 +274,16 @@ pub fn update_install_runner_path(app: AppHandle, id: String, path: String) -> O
                 });
             }
         }
-        update_install_runner_location_by_id(&app, m.id, np);
-        Some(true)
+        update_install_runner_location_by_id(&app, m.id, np)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_dxvk_path(app: AppHandle, id: String, path: String) -> Option<bool> {
-    let install = get_install_info_by_id(&app, id);
+pub fn update_install_dxvk_path(app: AppHandle, id: String, path: String) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(&app, id)?;
 
     if install.is_some() {
         let m = install.unwrap();
@@ -303,192 +309,136 @@ pub fn update_install_dxvk_path(app: AppHandle, id: String, path: String) -> Opt
                 });
             }
         }
-        update_install_dxvk_location_by_id(&app, m.id, np);
-        Some(true)
+        update_install_dxvk_location_by_id(&app, m.id, np)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_skip_version_updates(app: AppHandle, id: String, enabled: bool) -> Option<bool> {
-    let manifest = get_install_info_by_id(&app, id);
+pub fn update_install_skip_version_updates(app: AppHandle, id: String, enabled: bool) -> Result<Option<bool>, DbError> {
+    let manifest = get_install_info_by_id(&app, id)?;
 
     if manifest.is_some() {
         let m = manifest.unwrap();
-        update_install_ignore_updates_by_id(&app, m.id, enabled);
-        Some(true)
+        update_install_ignore_updates_by_id(&app, m.id, enabled)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_skip_hash_valid(app: AppHandle, id: String, enabled: bool) -> Option<bool> {
-    let manifest = get_install_info_by_id(&app, id);
+pub fn update_install_skip_hash_valid(app: AppHandle, id: String, enabled: bool) -> Result<Option<bool>, DbError> {
+    let manifest = get_install_info_by_id(&app, id)?;
 
     if manifest.is_some() {
         let m = manifest.unwrap();
-        update_install_skip_hash_check_by_id(&app, m.id, enabled);
-        Some(true)
+        update_install_skip_hash_check_by_id(&app, m.id, enabled)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_use_jadeite(app: AppHandle, id: String, enabled: bool) -> Option<bool> {
-    let manifest = get_install_info_by_id(&app, id);
-    let settings = get_settings(&app).unwrap();
-
-    if manifest.is_some() {
-        let m = manifest.unwrap();
-        let p = Path::new(&settings.jadeite_path).to_path_buf();
+pub async fn update_install_use_jadeite(app: AppHandle, id: String, enabled: bool) -> Result<Option<bool>, ExtraError> {
+    toggle_install_extra(&app, id, JADEITE_EXTRA, enabled, update_install_use_jadeite_by_id).await
+}
 
-        update_install_use_jadeite_by_id(&app, m.id, enabled);
-        
-        if fs::read_dir(&p).unwrap().next().is_none() && enabled {
-            std::thread::spawn(move || {
-                let dl = Extras::download_jadeite("MrLGamer/jadeite".parse().unwrap(), p.as_path().to_str().unwrap().parse().unwrap());
-                if dl {
-                    extract_archive(p.join("jadeite.zip").as_path().to_str().unwrap().parse().unwrap(), p.as_path().to_str().unwrap().parse().unwrap(), false);
-                }
-            });
-        }
-        Some(true)
-    } else {
-        None
-    }
+#[tauri::command]
+pub async fn update_install_use_xxmi(app: AppHandle, id: String, enabled: bool) -> Result<Option<bool>, ExtraError> {
+    toggle_install_extra(&app, id, XXMI_EXTRA, enabled, update_install_use_xxmi_by_id).await
 }
 
 #[tauri::command]
-pub fn update_install_use_xxmi(app: AppHandle, id: String, enabled: bool) -> Option<bool> {
-    let manifest = get_install_info_by_id(&app, id);
-    let settings = get_settings(&app).unwrap();
+pub async fn update_install_use_fps_unlock(app: AppHandle, id: String, enabled: bool) -> Result<Option<bool>, ExtraError> {
+    toggle_install_extra(&app, id, FPS_UNLOCK_EXTRA, enabled, update_install_use_fps_unlock_by_id).await
+}
 
-    if manifest.is_some() {
-        let m = manifest.unwrap();
-        let p = Path::new(&settings.xxmi_path).to_path_buf();
+#[tauri::command]
+pub async fn update_install_fps_value(app: AppHandle, id: String, fps: String) -> Result<Option<bool>, ExtraError> {
+    let install = get_install_info_by_id(&app, id)?;
 
-        update_install_use_xxmi_by_id(&app, m.id, enabled);
+    if install.is_some() {
+        let m = install.unwrap();
+        if m.use_fps_unlock { install_extra_async(&app, FPS_UNLOCK_EXTRA, false).await?; }
 
-        if fs::read_dir(&p).unwrap().next().is_none() && enabled {
-            std::thread::spawn(move || {
-                app.emit("download_progress", String::from("XXMI Modding tool")).unwrap();
-                let dl = Extras::download_xxmi("SpectrumQT/XXMI-Libs-Package".parse().unwrap(), p.as_path().to_str().unwrap().parse().unwrap(), true);
-                if dl {
-                    extract_archive(p.join("xxmi.zip").as_path().to_str().unwrap().parse().unwrap(), p.as_path().to_str().unwrap().parse().unwrap(), false);
-                    let gimi = String::from("SilentNightSound/GIMI-Package");
-                    let srmi = String::from("SpectrumQT/SRMI-Package");
-                    let zzmi = String::from("leotorrez/ZZMI-Package");
-                    let wwmi = String::from("SpectrumQT/WWMI-Package");
-                    
-                    let dl1 = Extras::download_xxmi_packages(gimi, srmi, zzmi, wwmi, p.as_path().to_str().unwrap().parse().unwrap(), false);
-                    if dl1 {
-                        for mi in ["gimi", "srmi", "zzmi", "wwmi"] {
-                            extract_archive(p.join(format!("{mi}.zip")).as_path().to_str().unwrap().parse().unwrap(), p.join(mi).as_path().to_str().unwrap().parse().unwrap(), false);
-                            for lib in ["d3d11.dll", "d3dcompiler_47.dll"] {
-                                #[cfg(target_os = "linux")]
-                                symlink(p.join(lib), p.join(mi).join(lib)).unwrap();
-                            }
-                        }
-                        app.emit("download_complete", String::from("XXMI Modding tool")).unwrap();
-                    }
-                }
-            });
-        }
-        Some(true)
+        update_install_fps_value_by_id(&app, m.id, fps)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_use_fps_unlock(app: AppHandle, id: String, enabled: bool) -> Option<bool> {
-    let manifest = get_install_info_by_id(&app, id);
-    let settings = get_settings(&app).unwrap();
-
-    if manifest.is_some() {
-        let m = manifest.unwrap();
-        let p = Path::new(&settings.fps_unlock_path).to_path_buf();
+pub fn add_install_env_var(app: AppHandle, id: String, key: String, value: String) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(&app, id)?;
 
-        update_install_use_fps_unlock_by_id(&app, m.id, enabled);
-
-        if fs::read_dir(&p).unwrap().next().is_none() && enabled {
-            std::thread::spawn(move || {
-                Extras::download_fps_unlock("mkrsym1/fpsunlock".parse().unwrap(), p.as_path().to_str().unwrap().parse().unwrap());
-            });
-        }
-        Some(true)
+    if install.is_some() && is_valid_env_key(key.as_str()) && !value.contains('\0') {
+        let m = install.unwrap();
+        set_install_env_var_by_id(&app, m.id, key, value)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_fps_value(app: AppHandle, id: String, fps: String) -> Option<bool> {
-    let install = get_install_info_by_id(&app, id);
-    let settings = get_settings(&app).unwrap();
+pub fn remove_install_env_var(app: AppHandle, id: String, key: String) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(&app, id)?;
 
     if install.is_some() {
         let m = install.unwrap();
-        let p = Path::new(&settings.fps_unlock_path).to_path_buf();
-
-        update_install_fps_value_by_id(&app, m.id, fps);
-
-        if fs::read_dir(&p).unwrap().next().is_none() && m.use_fps_unlock {
-            std::thread::spawn(move || {
-                Extras::download_fps_unlock("mkrsym1/fpsunlock".parse().unwrap(), p.as_path().to_str().unwrap().parse().unwrap());
-            });
-        }
-        Some(true)
+        delete_install_env_var_by_id(&app, m.id, key).map(Some)
     } else {
-        None
+        Ok(None)
     }
 }
 
+/// Legacy entries that never had a valid name are kept disabled, they can only be removed.
 #[tauri::command]
-pub fn update_install_env_vars(app: AppHandle, id: String, env_vars: String) -> Option<bool> {
-    let install = get_install_info_by_id(&app, id);
+pub fn toggle_install_env_var(app: AppHandle, id: String, key: String, enabled: bool) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(&app, id)?;
 
-    if install.is_some() {
+    if install.is_some() && (!enabled || is_valid_env_key(key.as_str())) {
         let m = install.unwrap();
-        update_install_env_vars_by_id(&app, m.id, env_vars);
-        Some(true)
+        update_install_env_var_enabled_by_id(&app, m.id, key, enabled).map(Some)
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_pre_launch_cmd(app: AppHandle, id: String, cmd: String) -> Option<bool> {
-    let install = get_install_info_by_id(&app, id);
+pub fn update_install_pre_launch_cmd(app: AppHandle, id: String, cmd: String) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(&app, id)?;
 
     if install.is_some() {
         let m = install.unwrap();
-        update_install_pre_launch_cmd_by_id(&app, m.id, cmd);
-        Some(true)
+        update_install_pre_launch_cmd_by_id(&app, m.id, cmd)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_launch_cmd(app: AppHandle, id: String, cmd: String) -> Option<bool> {
-    let install = get_install_info_by_id(&app, id);
+pub fn update_install_launch_cmd(app: AppHandle, id: String, cmd: String) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(&app, id)?;
 
     if install.is_some() {
         let m = install.unwrap();
-        update_install_launch_cmd_by_id(&app, m.id, cmd);
-        Some(true)
+        update_install_launch_cmd_by_id(&app, m.id, cmd)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_prefix_path(app: AppHandle, id: String, path: String) -> Option<bool> {
-    let install = get_install_info_by_id(&app, id);
+pub fn update_install_prefix_path(app: AppHandle, id: String, path: String) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(&app, id)?;
 
     if install.is_some() {
         let m = install.unwrap();
@@ -514,32 +464,36 @@ pub fn update_install_prefix_path(app: AppHandle, id: String, path: String) -> O
                 });
             }
         }
-        update_install_prefix_location_by_id(&app, m.id, np);
-        Some(true)
+        update_install_prefix_location_by_id(&app, m.id, np)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_launch_args(app: AppHandle, id: String, args: String) -> Option<bool> {
-    let install = get_install_info_by_id(&app, id);
+pub fn update_install_launch_argv(app: AppHandle, id: String, argv: Vec<String>) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(&app, id)?;
 
-    if install.is_some() {
+    if install.is_some() && !argv.iter().any(|a| a.contains('\0')) {
         let m = install.unwrap();
-        update_install_launch_args_by_id(&app, m.id, args);
-        Some(true)
+        update_install_launch_args_by_id(&app, m.id, &argv)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_runner_version(app: AppHandle, id: String, version: String) -> Option<bool> {
-    let install = get_install_info_by_id(&app, id);
+pub fn update_install_runner_version(app: AppHandle, id: String, version: String) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(&app, id)?;
 
     if install.is_some() {
         let m = install.unwrap();
+        let (rm, runnerp) = match compatibility_or_notify(&app, &version) {
+            Some(r) => r,
+            None => return Ok(None)
+        };
         let rp = m.runner_path.clone();
         let rpn = rp.replace(m.runner_version.as_str(), version.as_str());
         if !Path::exists(rpn.as_ref()) { fs::create_dir_all(rpn.clone()).unwrap(); }
@@ -551,29 +505,23 @@ pub fn update_install_runner_version(app: AppHandle, id: String, version: String
         
         if fs::read_dir(rpn.as_str()).unwrap().next().is_none() { 
             std::thread::spawn(move || {
-                let rm = get_compatibility(archandle.as_ref(), &runner_from_runner_version(runv.as_str().to_string()).unwrap()).unwrap();
-                let rv = rm.versions.into_iter().filter(|v| v.version.as_str() == runv.as_str()).collect::<Vec<_>>();
-                let runnerp = rv.get(0).unwrap().to_owned();
                 let rp = Path::new(runpp.as_str()).to_path_buf();
 
                 archandle.emit("download_progress", runv.as_str().to_string()).unwrap();
 
-                let r0 = Compatibility::download_runner(runnerp.url, runpp.as_str().to_string());
-                if r0 {
-                    let er = extract_archive(rp.join("runner.zip").to_str().unwrap().to_string(), rp.to_str().unwrap().to_string(), true);
+                let r0 = install_compatibility(&runnerp, &rp, false);
+                if let Err(e) = &r0 { notify_compatibility_failure(archandle.as_ref(), &runnerp.version, e.clone()); }
+                if r0.is_ok() {
                     let wine64 = if rm.paths.wine64.is_empty() { rm.paths.wine32 } else { rm.paths.wine64 };
                     let winebin = rp.join(wine64).to_str().unwrap().to_string();
 
                     let is_proton = rm.display_name.to_ascii_lowercase().contains("proton") && !rm.display_name.to_ascii_lowercase().contains("wine");
-                    if er {
-                        if is_proton {  } else { Compat::update_prefix(winebin, rpp.as_str().to_string()).unwrap(); }
-                        archandle.emit("download_complete", runv.as_str().to_string()).unwrap();
-                    }
+                    if is_proton {  } else { Compat::update_prefix(winebin, rpp.as_str().to_string()).unwrap(); }
+                    archandle.emit("download_complete", runv.as_str().to_string()).unwrap();
                 }
             });
         } else {
             std::thread::spawn(move || {
-                let rm = get_compatibility(archandle.as_ref(), &runner_from_runner_version(runv.as_str().to_string()).unwrap()).unwrap();
                 let rp = Path::new(runpp.as_str()).to_path_buf();
 
                 let wine64 = if rm.paths.wine64.is_empty() { rm.paths.wine32 } else { rm.paths.wine64 };
@@ -584,20 +532,28 @@ pub fn update_install_runner_version(app: AppHandle, id: String, version: String
             });
         }
 
-        update_install_runner_version_by_id(&app, m.id.clone(), version);
-        update_install_runner_location_by_id(&app, m.id, rpn);
-        Some(true)
+        update_install_runner_version_by_id(&app, m.id.clone(), version)?;
+        update_install_runner_location_by_id(&app, m.id, rpn)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_install_dxvk_version(app: AppHandle, id: String, version: String) -> Option<bool> {
-    let install = get_install_info_by_id(&app, id);
+pub fn update_install_dxvk_version(app: AppHandle, id: String, version: String) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(&app, id)?;
 
     if install.is_some() {
         let m = install.unwrap();
+        let (rm, _) = match compatibility_or_notify(&app, &m.runner_version) {
+            Some(r) => r,
+            None => return Ok(None)
+        };
+        let (_, dxp) = match compatibility_or_notify(&app, &version) {
+            Some(r) => r,
+            None => return Ok(None)
+        };
         let p = m.dxvk_path.clone();
         let pn = p.replace(m.dxvk_version.as_str(), version.as_str());
         if !Path::exists(pn.as_ref()) { fs::create_dir_all(pn.clone()).unwrap(); }
@@ -611,10 +567,6 @@ pub fn update_install_dxvk_version(app: AppHandle, id: String, version: String)
         
         if fs::read_dir(pn.as_str()).unwrap().next().is_none() {
             std::thread::spawn(move || {
-                let rm = get_compatibility(archandle.as_ref(), &runner_from_runner_version(runv.as_str().to_string()).unwrap()).unwrap();
-                let dm = get_compatibility(archandle.as_ref(), &runner_from_runner_version(dxvkv.as_str().to_string()).unwrap()).unwrap();
-                let dv = dm.versions.into_iter().filter(|v| v.version.as_str() == dxvkv.as_str()).collect::<Vec<_>>();
-                let dxp = dv.get(0).unwrap().to_owned();
                 let dxpp = Path::new(dxpp.as_str()).to_path_buf();
                 let rp = Path::new(runp.as_str()).to_path_buf();
 
@@ -623,25 +575,22 @@ pub fn update_install_dxvk_version(app: AppHandle, id: String, version: String)
                 if is_proton {  } else {
                     archandle.emit("download_progress", runv.as_str().to_string()).unwrap();
 
-                    let r0 = Compatibility::download_dxvk(dxp.url, dxpp.to_str().unwrap().to_string());
-                    if r0 {
-                        let er = extract_archive(dxpp.join("dxvk.zip").to_str().unwrap().to_string(), dxpp.to_str().unwrap().to_string(), true);
+                    let r0 = install_compatibility(&dxp, &dxpp, true);
+                    if let Err(e) = &r0 { notify_compatibility_failure(archandle.as_ref(), &dxp.version, e.clone()); }
+                    if r0.is_ok() {
                         let wine64 = if rm.paths.wine64.is_empty() { rm.paths.wine32 } else { rm.paths.wine64 };
                         let winebin = rp.join(wine64).to_str().unwrap().to_string();
 
-                        if er {
-                            let r1 = Compat::remove_dxvk(winebin.clone(), rpp.as_str().to_string());
-                            if r1.is_ok() {
-                                Compat::add_dxvk(winebin, rpp.as_str().to_string(), dxpp.to_str().unwrap().to_string(), false).unwrap();
-                                archandle.emit("download_complete", dxvkv.as_str().to_string()).unwrap();
-                            }
+                        let r1 = Compat::remove_dxvk(winebin.clone(), rpp.as_str().to_string());
+                        if r1.is_ok() {
+                            Compat::add_dxvk(winebin, rpp.as_str().to_string(), dxpp.to_str().unwrap().to_string(), false).unwrap();
+                            archandle.emit("download_complete", dxvkv.as_str().to_string()).unwrap();
                         }
                     }
                 }
             });
         } else {
             std::thread::spawn(move || {
-                let rm = get_compatibility(archandle.as_ref(), &runner_from_runner_version(runv.as_str().to_string()).unwrap()).unwrap();
                 let dxpp = Path::new(dxpp.as_str()).to_path_buf();
                 let rp = Path::new(runp.as_str()).to_path_buf();
 
@@ -656,40 +605,208 @@ pub fn update_install_dxvk_version(app: AppHandle, id: String, version: String)
             });
         }
 
-        update_install_dxvk_version_by_id(&app, m.id.clone(), version);
-        update_install_dxvk_location_by_id(&app, m.id, pn);
-        Some(true)
+        update_install_dxvk_version_by_id(&app, m.id.clone(), version)?;
+        update_install_dxvk_location_by_id(&app, m.id, pn)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
-#[tauri::command]
-pub fn game_launch(app: AppHandle, id: String) -> Option<bool> {
-    let install = get_install_info_by_id(&app, id);
-    let global_settings = get_settings(&app).unwrap();
+/// Resolves a runner or DXVK version, telling the user instead of panicking when no manifest provides it.
+/// Installs the extra first when enabling, so a failed download leaves the toggle off.
+async fn toggle_install_extra(app: &AppHandle, id: String, extra: &str, enabled: bool, update: fn(&AppHandle, String, bool) -> Result<bool, DbError>) -> Result<Option<bool>, ExtraError> {
+    let install = get_install_info_by_id(app, id)?;
 
     if install.is_some() {
         let m = install.unwrap();
-        let gmm = get_manifest_info_by_id(&app, m.clone().manifest_id).unwrap();
-        let gm = get_manifest(&app, gmm.filename).unwrap();
+        if enabled { install_extra_async(app, extra, false).await?; }
 
-        let rslt = launch(&app, m.clone(), gm, global_settings);
-        if rslt.is_ok() {
-            Some(true)
-        } else {
-            app.notification().builder().icon("dialog-error").title("TwintailLauncher").body("Failed to launch game! Please check game.log file inside game directory for more information.").show().unwrap();
+        update(app, m.id, enabled)?;
+        Ok(Some(true))
+    } else {
+        Ok(None)
+    }
+}
+
+fn compatibility_or_notify(app: &AppHandle, version: &str) -> Option<(RunnerManifest, RunnerVersion)> {
+    match get_compatibility_by_version(app, version) {
+        Ok(r) => Some(r),
+        Err(e) => {
+            app.notification().builder().icon("dialog-error").title("TwintailLauncher").body(e).show().unwrap();
             None
         }
+    }
+}
+
+fn notify_compatibility_failure(app: &AppHandle, version: &str, error: String) {
+    app.notification().builder().icon("dialog-error").title("TwintailLauncher").body(format!("Failed to install {}! {}", version, error)).show().unwrap();
+}
+
+#[tauri::command]
+pub fn game_launch(app: AppHandle, id: String, profile_id: Option<String>) -> Result<Option<bool>, DbError> {
+    launch_install(&app, id, profile_id)
+}
+
+/// Launches an install with one of its profiles applied, the default profile is used when `profile_id` is `None`.
+/// Shared by the frontend, the tray and `--launch`.
+pub fn launch_install(app: &AppHandle, id: String, profile_id: Option<String>) -> Result<Option<bool>, DbError> {
+    let install = get_install_info_by_id(app, id)?;
+    let global_settings = get_settings(app)?;
+
+    if install.is_some() && global_settings.is_some() {
+        let m = install.unwrap();
+        let global_settings = global_settings.unwrap();
+        let profile = match &profile_id {
+            Some(id) => match get_launch_profile_by_id(app, id.clone())? {
+                Some(p) if p.install_id == m.id => Some(p),
+                Some(_) => {
+                    app.notification().builder().icon("dialog-error").title("TwintailLauncher").body(format!("Failed to launch game! Launch profile does not belong to {}.", m.name)).show().unwrap();
+                    return Ok(None);
+                },
+                None => {
+                    app.notification().builder().icon("dialog-error").title("TwintailLauncher").body(format!("Failed to launch game! Unknown launch profile {}.", id)).show().unwrap();
+                    return Ok(None);
+                }
+            },
+            None => get_default_launch_profile_by_install_id(app, m.id.clone())?
+        };
+
+        let m = match &profile {
+            Some(p) => p.apply(m),
+            None => m
+        };
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(p) = profile.as_ref().filter(|p| p.runner_version.is_some()) {
+                if !fs::read_dir(&m.runner_path).map(|mut d| d.next().is_some()).unwrap_or(false) {
+                    app.notification().builder().icon("dialog-error").title("TwintailLauncher").body(format!("Failed to launch game! Runner {} of profile {} is not installed.", m.runner_version, p.name)).show().unwrap();
+                    return Ok(None);
+                }
+            }
+        }
+
+        let gm = match get_manifest_info_by_id(app, m.clone().manifest_id)?.and_then(|gmm| get_manifest(app, manifest_key(&gmm.repository_id, &gmm.filename))) {
+            Some(gm) => gm,
+            None => {
+                app.notification().builder().icon("dialog-error").title("TwintailLauncher").body(format!("Failed to launch game! Manifest of {} is not available.", m.name)).show().unwrap();
+                return Ok(None);
+            }
+        };
+
+        let rslt = launch(app, m.clone(), gm, global_settings);
+        match rslt {
+            Ok(_) => Ok(Some(true)),
+            Err(Error::Io(e)) if e.kind() == ErrorKind::NotFound => {
+                app.notification().builder().icon("dialog-error").title("TwintailLauncher").body(format!("Failed to launch game! {}", e)).show().unwrap();
+                Ok(None)
+            }
+            Err(_) => {
+                app.notification().builder().icon("dialog-error").title("TwintailLauncher").body("Failed to launch game! Please check game.log file inside game directory for more information.").show().unwrap();
+                Ok(None)
+            }
+        }
     } else {
         app.notification().builder().icon("dialog-error").title("TwintailLauncher").body("Failed to find installation! How is this even possible? Some serious fuck up happened!").show().unwrap();
-        None
+        Ok(None)
+    }
+}
+
+#[tauri::command]
+pub fn list_launch_profiles(app: AppHandle, install_id: String) -> Result<Option<String>, DbError> {
+    let profiles = get_launch_profiles_by_install_id(&app, install_id)?;
+
+    if profiles.is_empty() {
+        Ok(None)
+    } else {
+        let stringified = serde_json::to_string(&profiles).unwrap();
+        Ok(Some(stringified))
+    }
+}
+
+/// New profiles start without overrides, so they launch exactly like the install until edited.
+#[tauri::command]
+pub fn add_launch_profile(app: AppHandle, install_id: String, name: String) -> Result<Option<String>, DbError> {
+    let name = name.trim().to_string();
+    if name.is_empty() || get_install_info_by_id(&app, install_id.clone())?.is_none() { return Ok(None); }
+
+    let cuid = generate_cuid();
+    create_launch_profile(&app, cuid.clone(), install_id, name)?;
+    refresh_tray_menu(&app);
+
+    let profile = get_launch_profile_by_id(&app, cuid)?;
+    Ok(profile.map(|p| serde_json::to_string(&p).unwrap()))
+}
+
+#[tauri::command]
+pub fn update_launch_profile(app: AppHandle, profile: LaunchProfile) -> Result<Option<bool>, DbError> {
+    let install = match get_launch_profile_by_id(&app, profile.id.clone())? {
+        Some(existing) => get_install_info_by_id(&app, existing.install_id)?,
+        None => None
+    };
+    let settings = get_settings(&app)?;
+
+    if install.is_some() && settings.is_some() && !profile.name.trim().is_empty() {
+        let m = install.unwrap();
+        let settings = settings.unwrap();
+
+        // Resolve the runner first so an unknown version never ends up stored
+        let runner = match profile.runner_version.as_ref().filter(|v| **v != m.runner_version) {
+            Some(v) => match compatibility_or_notify(&app, v) {
+                Some(r) => Some(r),
+                None => return Ok(None)
+            },
+            None => None
+        };
+        update_launch_profile_by_id(&app, &LaunchProfile { name: profile.name.trim().to_string(), ..profile.clone() })?;
+        refresh_tray_menu(&app);
+
+        if profile.use_xxmi == Some(true) && fs::read_dir(&settings.xxmi_path).map(|mut d| d.next().is_none()).unwrap_or(true) { spawn_extra_install(app.clone(), XXMI_EXTRA); }
+        if profile.use_fps_unlock == Some(true) && fs::read_dir(&settings.fps_unlock_path).map(|mut d| d.next().is_none()).unwrap_or(true) { spawn_extra_install(app.clone(), FPS_UNLOCK_EXTRA); }
+
+        if let Some((_, runnerp)) = runner {
+            let rpn = profile.apply(m).runner_path;
+            if !Path::exists(rpn.as_ref()) { fs::create_dir_all(rpn.clone()).unwrap(); }
+
+            if fs::read_dir(rpn.as_str()).unwrap().next().is_none() {
+                std::thread::spawn(move || {
+                    app.emit("download_progress", runnerp.version.clone()).unwrap();
+                    match install_compatibility(&runnerp, Path::new(rpn.as_str()), false) {
+                        Ok(_) => { app.emit("download_complete", runnerp.version.clone()).unwrap(); }
+                        Err(e) => notify_compatibility_failure(&app, &runnerp.version, e)
+                    }
+                });
+            }
+        }
+        Ok(Some(true))
+    } else {
+        Ok(None)
+    }
+}
+
+#[tauri::command]
+pub fn set_default_launch_profile(app: AppHandle, install_id: String, id: String) -> Result<Option<bool>, DbError> {
+    if !set_default_launch_profile_by_id(&app, install_id, id)? { return Ok(None); }
+    refresh_tray_menu(&app);
+    Ok(Some(true))
+}
+
+/// The default profile can not be removed, make another profile the default first.
+#[tauri::command]
+pub fn remove_launch_profile(app: AppHandle, id: String) -> Result<Option<bool>, DbError> {
+    match get_launch_profile_by_id(&app, id.clone())? {
+        Some(p) if !p.is_default => {
+            delete_launch_profile_by_id(&app, id)?;
+            refresh_tray_menu(&app);
+            Ok(Some(true))
+        }
+        _ => Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn get_download_sizes(app: AppHandle, biz: String, version: String, lang: String, path: String) -> Option<String> {
-    let manifest = get_manifest(&app, biz + ".json");
+pub fn get_download_sizes(app: AppHandle, manifest_id: String, version: String, lang: String, path: String) -> Result<Option<String>, DbError> {
+    let manifest = get_manifest_info_by_id(&app, manifest_id)?.and_then(|m| get_manifest(&app, manifest_key(&m.repository_id, &m.filename)));
 
     if manifest.is_some() {
         let m = manifest.unwrap();
@@ -723,8 +840,13 @@ pub fn get_download_sizes(app: AppHandle, biz: String, version: String, lang: St
             }).unwrap();
         };
         
-        Some(stringified)
+        Ok(Some(stringified))
     } else {
-        None
+        Ok(None)
     }
-}
\ No newline at end of file
+}
+
+/// Anything else could never be passed to a process as a variable name.
+fn is_valid_env_key(key: &str) -> bool {
+    !key.is_empty() && !key.contains('=') && !key.contains('\0')
+}
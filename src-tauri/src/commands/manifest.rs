@@ -1,159 +1,244 @@
+use std::collections::HashMap;
 use linked_hash_map::LinkedHashMap;
 use tauri::{AppHandle};
-use crate::utils::db_manager::{get_manifest_info_by_filename, get_manifest_info_by_id, get_manifests_by_repository_id, update_manifest_enabled_by_id};
-use crate::utils::repo_manager::{get_manifest, get_manifests, GameManifest};
+use crate::utils::db_manager::{get_installed_extra_by_id, get_manifest_info_by_repository_and_filename, get_manifest_info_by_id, get_manifests_by_repository_id, get_manifests_info, get_repositories, get_repository_info_by_id, update_manifest_enabled_by_id, DbError};
+use crate::utils::extras_manager::{install_extra_async, ExtraError};
+use crate::utils::manifest_overrides::{apply_override, clear_override, patch_fields, read_override, write_override, OverrideError};
+use crate::utils::repo_manager::{get_extras, get_manifest, get_manifest_errors, get_manifests, get_overridden_fields, get_repository_dir, load_manifests, manifest_key, parse_manifest, ExtraManifest, GameManifest, LauncherManifest};
+use crate::utils::{ExtraManifestRsp, GameManifestRsp, ManifestOverrideRsp, OverriddenGameManifestRsp};
 
 #[cfg(target_os = "linux")]
 use crate::utils::repo_manager::{get_compatibilities, get_compatibility, RunnerManifest};
 
 #[tauri::command]
-pub fn get_manifest_by_id(app: AppHandle, id: String) -> Option<String> {
-    let manifest = get_manifest_info_by_id(&app, id);
+pub fn get_manifest_by_id(app: AppHandle, id: String) -> Result<Option<String>, DbError> {
+    let manifest = get_manifest_info_by_id(&app, id)?;
 
     if manifest.is_some() {
         let m = manifest.unwrap();
         let stringified = serde_json::to_string(&m).unwrap();
-        Some(stringified)
+        Ok(Some(stringified))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn get_manifest_by_filename(app: AppHandle, filename: String) -> Option<String> {
-    let manifest = get_manifest_info_by_filename(&app, filename);
+pub fn get_manifest_by_filename(app: AppHandle, repository_id: String, filename: String) -> Result<Option<String>, DbError> {
+    let manifest = get_manifest_info_by_repository_and_filename(&app, repository_id, filename)?;
 
     if manifest.is_some() {
         let m = manifest.unwrap();
         let stringified = serde_json::to_string(&m).unwrap();
-        Some(stringified)
+        Ok(Some(stringified))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn list_manifests_by_repository_id(app: AppHandle, repository_id: String) -> Option<String> {
-    let manifests = get_manifests_by_repository_id(&app, repository_id);
+pub fn list_manifests_by_repository_id(app: AppHandle, repository_id: String) -> Result<Option<String>, DbError> {
+    let manifests = get_manifests_by_repository_id(&app, repository_id)?;
 
-    if manifests.is_some() {
-        let manifest = manifests.unwrap();
-        let stringified = serde_json::to_string(&manifest).unwrap();
-        Some(stringified)
+    if manifests.is_empty() {
+        Ok(None)
     } else {
-        None
+        let stringified = serde_json::to_string(&manifests).unwrap();
+        Ok(Some(stringified))
     }
 }
 
 #[tauri::command]
-pub fn list_game_manifests(app: AppHandle) -> Option<String> {
+pub fn list_game_manifests(app: AppHandle) -> Result<Option<String>, DbError> {
     let manifestss: LinkedHashMap<String, GameManifest> = get_manifests(&app);
-    let mut manifests: Vec<GameManifest> = Vec::new();
+    let mut manifests: Vec<GameManifestRsp> = Vec::new();
+    // One query for all rows instead of one per loaded manifest
+    let mut rows = get_manifests_info(&app)?.into_iter().map(|m| (manifest_key(&m.repository_id, &m.filename), m)).collect::<HashMap<String, LauncherManifest>>();
 
-    for value in manifestss.clone().into_iter().map(|(_, value)| value) { manifests.push(value); }
+    for (key, value) in manifestss.into_iter() {
+        if let Some(m) = rows.remove(&key) { manifests.push(GameManifestRsp { key, manifest_id: m.id, repository_id: m.repository_id, manifest_enabled: m.enabled, manifest_file: m.filename, manifest: value }); }
+    }
 
     if manifests.is_empty() {
-        None
+        Ok(None)
     } else {
         let stringified = serde_json::to_string(&manifests).unwrap();
-        Some(stringified)
+        Ok(Some(stringified))
     }
 }
 
 #[tauri::command]
-pub fn get_game_manifest_by_filename(app: AppHandle, filename: String) -> Option<String> {
-    let manifest = get_manifest(&app, filename.clone());
-    let db_manifest = get_manifest_info_by_filename(&app, filename.clone());
+pub fn get_game_manifest_by_filename(app: AppHandle, repository_id: String, filename: String) -> Result<Option<String>, DbError> {
+    let manifest = get_manifest(&app, manifest_key(&repository_id, &filename));
+    let db_manifest = get_manifest_info_by_repository_and_filename(&app, repository_id, filename)?;
 
     if manifest.is_some() && db_manifest.is_some() {
         let dbm = db_manifest.unwrap();
 
         if dbm.enabled {
             let m = manifest.unwrap();
-            let stringified = serde_json::to_string(&m).unwrap();
-            Some(stringified)
+            let rsp = OverriddenGameManifestRsp { manifest: m, overridden_fields: get_overridden_fields(&app, &manifest_key(&dbm.repository_id, &dbm.filename)) };
+            let stringified = serde_json::to_string(&rsp).unwrap();
+            Ok(Some(stringified))
         } else {
-            None
+            Ok(None)
         }
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn get_game_manifest_by_manifest_id(app: AppHandle, id: String) -> Option<String> {
-    let db_manifest = get_manifest_info_by_id(&app, id.clone());
+pub fn get_game_manifest_by_manifest_id(app: AppHandle, id: String) -> Result<Option<String>, DbError> {
+    let manifest = get_enabled_manifest(&app, id, |key| get_manifest(&app, key))?;
 
-    if db_manifest.is_some() {
-        let dbm = db_manifest.unwrap();
-        let manifest = get_manifest(&app, dbm.filename);
+    if manifest.is_some() {
+        let m = manifest.unwrap();
+        let stringified = serde_json::to_string(&m).unwrap();
+        Ok(Some(stringified))
+    } else {
+        Ok(None)
+    }
+}
 
-        if dbm.enabled {
-            let m = manifest.unwrap();
-            let stringified = serde_json::to_string(&m).unwrap();
-            Some(stringified)
-        } else {
-            None
+#[tauri::command]
+pub fn list_extra_manifests(app: AppHandle) -> Result<Option<String>, DbError> {
+    let manifestss: LinkedHashMap<String, ExtraManifest> = get_extras(&app);
+    let mut manifests: Vec<ExtraManifestRsp> = Vec::new();
+
+    for (key, value) in manifestss.into_iter() {
+        let (repository_id, filename) = key.split_once('/').unwrap();
+        let dbm = get_manifest_info_by_repository_and_filename(&app, repository_id.to_string(), filename.to_string())?;
+        // Only report the installed version against the manifest it was actually installed from
+        let installed = get_installed_extra_by_id(&app, value.id.clone())?;
+        if let Some(m) = dbm {
+            let installed_version = installed.filter(|i| i.manifest_id == m.id).map(|i| i.version);
+            manifests.push(ExtraManifestRsp { manifest_id: m.id, repository_id: m.repository_id, installed_version, manifest: value });
         }
+    }
+
+    if manifests.is_empty() {
+        Ok(None)
     } else {
-        None
+        let stringified = serde_json::to_string(&manifests).unwrap();
+        Ok(Some(stringified))
     }
 }
 
 #[tauri::command]
-pub fn update_manifest_enabled(app: AppHandle, id: String, enabled: bool) -> Option<bool> {
-    let manifest = get_manifest_info_by_id(&app, id);
+pub async fn install_extra_by_id(app: AppHandle, id: String, force: bool) -> Result<bool, ExtraError> {
+    install_extra_async(&app, id.as_str(), force).await
+}
+
+#[tauri::command]
+pub fn list_manifest_overrides(app: AppHandle) -> Result<Option<String>, DbError> {
+    let mut overrides: Vec<ManifestOverrideRsp> = Vec::new();
+
+    for r in get_repositories(&app)? {
+        for m in get_manifests_by_repository_id(&app, r.id.clone())? {
+            let key = manifest_key(&m.repository_id, &m.filename);
+            // Unreadable overrides show up in list_manifest_errors after the next reload
+            if let Ok(Some(patch)) = read_override(&app, &r.github_id, &key) {
+                let applied = !get_overridden_fields(&app, &key).is_empty();
+                overrides.push(ManifestOverrideRsp { manifest_id: m.id, repository_id: m.repository_id, filename: m.filename, fields: patch_fields(&patch), patch, applied });
+            }
+        }
+    }
+
+    let stringified = serde_json::to_string(&overrides).unwrap();
+    Ok(Some(stringified))
+}
+
+/// Stores a JSON merge patch for a manifest after checking the patched manifest still parses.
+#[tauri::command]
+pub fn set_manifest_override(app: AppHandle, manifest_id: String, patch: String) -> Result<bool, OverrideError> {
+    let dbm = get_manifest_info_by_id(&app, manifest_id)?.ok_or(OverrideError::NotFound("Manifest does not exist!".to_string()))?;
+    let repo = get_repository_info_by_id(&app, dbm.repository_id.clone())?.ok_or(OverrideError::NotFound("Repository of this manifest does not exist!".to_string()))?;
+    let dir = get_repository_dir(&app, &repo).ok_or(OverrideError::NotFound("Repository has no files on disk!".to_string()))?;
+
+    let value: serde_json::Value = serde_json::from_str(patch.as_str()).map_err(|e| OverrideError::InvalidPatch(format!("Invalid JSON: {}", e)))?;
+    if !value.is_object() { return Err(OverrideError::InvalidPatch("Override must be a JSON object!".to_string())); }
+
+    let data = parse_manifest(&repo.github_id, &dbm.filename, None, &dir.join(dbm.filename.as_str())).map_err(|e| OverrideError::InvalidManifest(format!("{}: {}", e.path, e.reason)))?;
+    apply_override(&repo.github_id, &dbm.filename, &data, &value).map_err(|e| OverrideError::InvalidPatch(format!("{}: {}", e.path, e.reason)))?;
+
+    write_override(&app, &manifest_key(&dbm.repository_id, &dbm.filename), &value)?;
+    load_manifests(&app);
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn clear_manifest_override(app: AppHandle, manifest_id: String) -> Result<Option<bool>, DbError> {
+    let dbm = match get_manifest_info_by_id(&app, manifest_id)? {
+        Some(m) => m,
+        None => return Ok(None)
+    };
+    let removed = clear_override(&app, &manifest_key(&dbm.repository_id, &dbm.filename));
+
+    if removed { load_manifests(&app); }
+    Ok(Some(removed))
+}
+
+#[tauri::command]
+pub fn list_manifest_errors(app: AppHandle) -> Result<String, DbError> {
+    let errors = get_manifest_errors(&app);
+    let stringified = serde_json::to_string(&errors).unwrap();
+    Ok(stringified)
+}
+
+#[tauri::command]
+pub fn update_manifest_enabled(app: AppHandle, id: String, enabled: bool) -> Result<Option<bool>, DbError> {
+    let manifest = get_manifest_info_by_id(&app, id)?;
 
     if manifest.is_some() {
         let m = manifest.unwrap();
-        update_manifest_enabled_by_id(&app, m.id, enabled);
-        Some(true)
+        update_manifest_enabled_by_id(&app, m.id, enabled)?;
+        Ok(Some(true))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[cfg(target_os = "linux")]
 #[tauri::command]
-pub fn list_compatibility_manifests(app: AppHandle) -> Option<String> {
+pub fn list_compatibility_manifests(app: AppHandle) -> Result<Option<String>, DbError> {
     let manifestss: LinkedHashMap<String, RunnerManifest> = get_compatibilities(&app);
     let mut manifests: Vec<RunnerManifest> = Vec::new();
 
     for value in manifestss.clone().into_iter().map(|(_, value)| value) { manifests.push(value); }
 
     if manifests.is_empty() {
-        None
+        Ok(None)
     } else {
         let stringified = serde_json::to_string(&manifests).unwrap();
-        Some(stringified)
+        Ok(Some(stringified))
     }
 }
 
 #[cfg(target_os = "windows")]
 #[tauri::command]
-pub fn list_compatibility_manifests(_app: AppHandle) -> Option<String> { None }
+pub fn list_compatibility_manifests(_app: AppHandle) -> Result<Option<String>, DbError> { Ok(None) }
 
 #[cfg(target_os = "linux")]
 #[tauri::command]
-pub fn get_compatibility_manifest_by_manifest_id(app: AppHandle, id: String) -> Option<String> {
-    let db_manifest = get_manifest_info_by_id(&app, id.clone());
-
-    if db_manifest.is_some() {
-        let dbm = db_manifest.unwrap();
-        let manifest = get_compatibility(&app, &dbm.filename);
+pub fn get_compatibility_manifest_by_manifest_id(app: AppHandle, id: String) -> Result<Option<String>, DbError> {
+    let manifest = get_enabled_manifest(&app, id, |key| get_compatibility(&app, &key))?;
 
-        if dbm.enabled {
-            let m = manifest.unwrap();
-            let stringified = serde_json::to_string(&m).unwrap();
-            Some(stringified)
-        } else {
-            None
-        }
+    if manifest.is_some() {
+        let m = manifest.unwrap();
+        let stringified = serde_json::to_string(&m).unwrap();
+        Ok(Some(stringified))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[cfg(target_os = "windows")]
 #[tauri::command]
-pub fn get_compatibility_manifest_by_manifest_id(_app: AppHandle, _id: String) -> Option<String> { None }
\ No newline at end of file
+pub fn get_compatibility_manifest_by_manifest_id(_app: AppHandle, _id: String) -> Result<Option<String>, DbError> { Ok(None) }
+
+/// Looks up the loaded manifest behind an enabled manifest row.
+/// The row can outlive the file, e.g. a manifest that failed to parse after a sync, so this yields `None` for those too.
+fn get_enabled_manifest<T>(app: &AppHandle, id: String, lookup: impl FnOnce(String) -> Option<T>) -> Result<Option<T>, DbError> {
+    Ok(get_manifest_info_by_id(app, id)?.filter(|m| m.enabled).and_then(|m| lookup(manifest_key(&m.repository_id, &m.filename))))
+}
@@ -1,61 +1,191 @@
+use std::fs;
 use tauri::{AppHandle, Manager};
-use crate::utils::db_manager::{delete_repository_by_id, get_repositories, get_repository_info_by_id};
-use crate::utils::repo_manager::clone_new_repository;
+use crate::utils::db_manager::{delete_repository_by_id, delete_repository_credentials_by_repository_id, set_repository_credentials_by_id, get_installs_by_manifest_id, get_manifests_by_repository_id, get_repositories, get_repository_info_by_id, update_repository_ref_by_id, DbError};
+use crate::utils::repo_manager;
+use crate::utils::repo_manager::{clone_new_repository, get_repository_path, load_manifests, manifest_key, repository_lock, unload_manifests, update_repositories, RepositoryCredentials, RepositoryError, RepositoryTransport};
+use crate::utils::manifest_overrides::clear_repository_overrides;
+use crate::utils::RemoveRepositoryRsp;
+
+const CREDENTIAL_KINDS: [&str; 3] = ["token", "ssh_key", "credential_helper"];
 
 #[tauri::command]
-pub fn list_repositories(app: AppHandle) -> Option<String> {
-    let repos = get_repositories(&app);
+pub fn list_repositories(app: AppHandle) -> Result<Option<String>, DbError> {
+    let repos = get_repositories(&app)?;
 
-    if repos.is_some() {
-        let repository = repos.unwrap();
-        let stringified = serde_json::to_string(&repository).unwrap();
-        Some(stringified)
+    if repos.is_empty() {
+        Ok(None)
     } else {
-        None
+        let stringified = serde_json::to_string(&repos).unwrap();
+        Ok(Some(stringified))
     }
 }
 
 #[tauri::command]
-pub fn get_repository(app: AppHandle, repository_id: String) -> Option<String> {
-    let repo = get_repository_info_by_id(&app, repository_id);
+pub fn get_repository(app: AppHandle, repository_id: String) -> Result<Option<String>, DbError> {
+    let repo = get_repository_info_by_id(&app, repository_id)?;
 
     if repo.is_some() {
         let repository = repo.unwrap();
         let stringified = serde_json::to_string(&repository).unwrap();
-        Some(stringified)
+        Ok(Some(stringified))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn add_repository(app: AppHandle, url: String) -> Option<bool> {
+pub fn add_repository(app: AppHandle, url: String, credentials: Option<RepositoryCredentials>) -> Result<bool, RepositoryError> {
     if url.is_empty() {
-        None
+        Err(RepositoryError::InvalidUrl("Repository url is empty!".to_string()))
+    } else if credentials.as_ref().is_some_and(|c| !CREDENTIAL_KINDS.contains(&c.kind.as_str())) {
+        Err(RepositoryError::Other("Unsupported credentials kind!".to_string()))
     } else {
         let path = app.path().app_data_dir().unwrap().join("manifests");
-        let rtn = clone_new_repository(&app, &path, url);
+        clone_new_repository(&app, &path, url, credentials)
+    }
+}
+
+#[tauri::command]
+pub fn add_local_repository(app: AppHandle, path: String) -> Result<bool, RepositoryError> {
+    if path.is_empty() {
+        Err(RepositoryError::InvalidUrl("Repository path is empty!".to_string()))
+    } else {
+        repo_manager::add_local_repository(&app, path)
+    }
+}
+
+#[tauri::command]
+pub fn preview_repository(app: AppHandle, url: String, credentials: Option<RepositoryCredentials>) -> Result<String, RepositoryError> {
+    if url.is_empty() {
+        Err(RepositoryError::InvalidUrl("Repository url is empty!".to_string()))
+    } else if credentials.as_ref().is_some_and(|c| !CREDENTIAL_KINDS.contains(&c.kind.as_str())) {
+        Err(RepositoryError::Other("Unsupported credentials kind!".to_string()))
+    } else {
+        let preview = repo_manager::preview_repository(&app, url, credentials)?;
+        Ok(serde_json::to_string(&preview).unwrap())
+    }
+}
+
+#[tauri::command]
+pub fn confirm_repository_preview(app: AppHandle, preview_id: String) -> Result<bool, RepositoryError> {
+    repo_manager::confirm_repository_preview(&app, preview_id)
+}
+
+#[tauri::command]
+pub fn discard_repository_preview(app: AppHandle, preview_id: String) -> Option<bool> {
+    Some(repo_manager::discard_repository_preview(&app, preview_id))
+}
+
+#[tauri::command]
+pub fn set_repository_credentials(app: AppHandle, id: String, credentials: RepositoryCredentials) -> Result<Option<bool>, DbError> {
+    if id.is_empty() || !CREDENTIAL_KINDS.contains(&credentials.kind.as_str()) || get_repository_info_by_id(&app, id.clone())?.is_none() {
+        Ok(None)
+    } else {
+        set_repository_credentials_by_id(&app, id, &credentials).map(Some)
+    }
+}
+
+#[tauri::command]
+pub fn remove_repository_credentials(app: AppHandle, id: String) -> Result<Option<bool>, DbError> {
+    if id.is_empty() {
+        Ok(None)
+    } else {
+        delete_repository_credentials_by_repository_id(&app, id).map(Some)
+    }
+}
+
+#[tauri::command]
+pub fn remove_repository(app: AppHandle, id: String, force: Option<bool>) -> Result<Option<String>, RepositoryError> {
+    if id.is_empty() {
+        Ok(None)
+    } else {
+        let lock = repository_lock(&app, &id);
+        let _guard = lock.lock().unwrap();
+        let repo = get_repository_info_by_id(&app, id.clone())?;
+        if repo.is_none() { return Ok(None); }
+
+        let r = repo.unwrap();
+        let manifests = get_manifests_by_repository_id(&app, r.id.clone())?;
+        let mut installs = Vec::new();
+        for m in &manifests { installs.extend(get_installs_by_manifest_id(&app, m.id.clone())?); }
+        let mut rsp = RemoveRepositoryRsp { repository_id: r.id.clone(), ..Default::default() };
+
+        // Installations would point to manifests that no longer exist, refuse unless explicitly forced
+        if !installs.is_empty() && !force.unwrap_or(false) {
+            rsp.blocking_installs = installs.into_iter().map(|i| i.id).collect();
+            return Ok(Some(serde_json::to_string(&rsp).unwrap()));
+        }
+
+        // Files go first, if anything fails from here on the repository is still registered and removing it again picks up where this stopped
+        if let Some(path) = get_repository_path(&app, &r) {
+            if path.exists() { fs::remove_dir_all(&path).map_err(|e| RepositoryError::Other(format!("Failed to remove {}: {}", path.display(), e)))?; }
+            // Clean up owner directory as well if this was the last repository in it
+            if let Some(parent) = path.parent() {
+                if fs::read_dir(parent).is_ok_and(|mut d| d.next().is_none()) { fs::remove_dir(parent).map_err(|e| RepositoryError::Other(format!("Failed to remove {}: {}", parent.display(), e)))?; }
+            }
+            rsp.removed_path = Some(path.to_str().unwrap().to_string());
+        }
+        clear_repository_overrides(&app, &r.id).map_err(|e| RepositoryError::Other(format!("Failed to remove overrides of {}: {}", r.github_id, e)))?;
+
+        // Forced removal only drops installation entries, game files are left untouched
+        delete_repository_by_id(&app, r.id.clone(), true)?;
+        rsp.removed_installs = installs.into_iter().map(|i| i.id).collect();
 
+        let keys = manifests.iter().map(|m| manifest_key(&m.repository_id, &m.filename)).collect::<Vec<String>>();
+        rsp.removed_manifests = manifests.into_iter().map(|m| m.filename).collect();
+        unload_manifests(&app, &keys);
+
+        rsp.success = true;
+        Ok(Some(serde_json::to_string(&rsp).unwrap()))
+    }
+}
+
+#[tauri::command]
+pub fn update_repository_ref(app: AppHandle, id: String, ref_kind: String, ref_name: String) -> Result<Option<String>, DbError> {
+    if id.is_empty() || ref_name.is_empty() || !["branch", "tag", "commit"].contains(&ref_kind.as_str()) {
+        Ok(None)
+    } else {
+        let lock = repository_lock(&app, &id);
+        let _guard = lock.lock().unwrap();
+        let repo = get_repository_info_by_id(&app, id)?;
+        if repo.is_none() { return Ok(None); }
+
+        let r = repo.unwrap();
+        // Bundles and local repositories have no refs to track
+        if r.transport != RepositoryTransport::Git { return Ok(None); }
+        let path = match get_repository_path(&app, &r) {
+            Some(p) => p,
+            None => return Ok(None)
+        };
+        update_repository_ref_by_id(&app, r.id.clone(), ref_kind, ref_name)?;
+
+        let rtn = update_repositories(&app, &path);
         if rtn.is_ok() {
-            Some(rtn.unwrap())
+            load_manifests(&app);
+            Ok(Some(serde_json::to_string(&rtn.unwrap()).unwrap()))
         } else {
-            None
+            // Ref does not exist upstream, keep following whatever we tracked before
+            update_repository_ref_by_id(&app, r.id, r.ref_kind, r.ref_name)?;
+            Ok(None)
         }
     }
 }
 
 #[tauri::command]
-pub fn remove_repository(app: AppHandle, id: String) -> Option<bool> {
-    if id.is_empty() {
-        None
-    } else {
-        // TODO: Properly delete repository bullshit and disallow if installation with ANY manifest of a repo exists
-        // PS: can be done once installs work sort of so we can validate if manifest has install
-        let rtn = delete_repository_by_id(&app, id);
+pub fn rollback_repository(app: AppHandle, id: String) -> Result<Option<String>, DbError> {
+    let lock = repository_lock(&app, &id);
+    let _guard = lock.lock().unwrap();
+    let repo = get_repository_info_by_id(&app, id)?;
+
+    if repo.is_some() {
+        let rtn = repo_manager::rollback_repository(&app, &repo.unwrap());
         if rtn.is_ok() {
-            Some(rtn.unwrap())
+            load_manifests(&app);
+            Ok(Some(serde_json::to_string(&rtn.unwrap()).unwrap()))
         } else {
-            None
+            Ok(None)
         }
+    } else {
+        Ok(None)
     }
-}
\ No newline at end of file
+}
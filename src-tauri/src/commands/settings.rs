@@ -1,109 +1,119 @@
 use std::fs;
-use std::os::unix::fs::symlink;
 use std::path::Path;
-use fischl::download::Extras;
-use fischl::utils::extract_archive;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_opener::OpenerExt;
 use crate::utils::{block_telemetry, get_mi_path_from_game};
-use crate::utils::db_manager::{get_install_info_by_id, get_manifest_info_by_id, get_settings, update_settings_default_fps_unlock_location, update_settings_default_game_location, update_settings_default_jadeite_location, update_settings_default_prefix_location, update_settings_default_xxmi_location, update_settings_hide_manifests, update_settings_launch_action, update_settings_third_party_repo_update};
-use crate::utils::repo_manager::get_manifest;
+use crate::utils::extras_manager::update_installed_extras;
+use crate::utils::db_manager::{get_install_info_by_id, get_manifest_info_by_id, get_settings, update_settings_default_fps_unlock_location, update_settings_default_game_location, update_settings_default_jadeite_location, update_settings_default_prefix_location, update_settings_default_xxmi_location, update_settings_hide_manifests, update_settings_launch_action, update_settings_repo_sync_interval_by_minutes, update_settings_third_party_repo_update, DbError};
+use crate::utils::repo_manager::{get_manifest, manifest_key};
+use crate::utils::launcher_state::{export_state, import_state, LauncherStateBundle, PathMapping, StateError};
+use crate::utils::system_tray::refresh_tray_menu;
 
 #[tauri::command]
-pub async fn list_settings(app: AppHandle) -> Option<String> {
-    let settings = get_settings(&app);
+pub async fn list_settings(app: AppHandle) -> Result<Option<String>, DbError> {
+    let settings = get_settings(&app)?;
 
     if settings.is_some() {
         let s = settings.unwrap();
         let stringified = serde_json::to_string(&s).unwrap();
-        Some(stringified)
+        Ok(Some(stringified))
     } else {
-        None
+        Ok(None)
     }
 }
 
 #[tauri::command]
-pub fn update_settings_third_party_repo_updates(app: AppHandle, enabled: bool) -> Option<bool> {
-    update_settings_third_party_repo_update(&app, enabled);
-    Some(true)
+pub fn update_settings_third_party_repo_updates(app: AppHandle, enabled: bool) -> Result<bool, DbError> {
+    update_settings_third_party_repo_update(&app, enabled)?;
+    Ok(true)
 }
 
 #[tauri::command]
-pub fn update_settings_default_game_path(app: AppHandle, path: String) -> Option<bool> {
+pub fn update_settings_repo_sync_interval(app: AppHandle, interval: i32) -> Result<Option<bool>, DbError> {
+    if interval < 0 {
+        Ok(None)
+    } else {
+        update_settings_repo_sync_interval_by_minutes(&app, interval)?;
+        Ok(Some(true))
+    }
+}
+
+#[tauri::command]
+pub fn update_settings_default_game_path(app: AppHandle, path: String) -> Result<bool, DbError> {
     let p = Path::new(&path);
 
     if !p.exists() && p.is_dir() {
         fs::create_dir_all(&p).unwrap();
-        update_settings_default_game_location(&app, p.to_str().unwrap().parse().unwrap());
+        update_settings_default_game_location(&app, p.to_str().unwrap().parse().unwrap())?;
     } else {
-        update_settings_default_game_location(&app, p.to_str().unwrap().parse().unwrap());
+        update_settings_default_game_location(&app, p.to_str().unwrap().parse().unwrap())?;
     }
-    Some(true)
+    Ok(true)
 }
 
 #[tauri::command]
-pub fn update_settings_default_xxmi_path(app: AppHandle, path: String) -> Option<bool> {
+pub fn update_settings_default_xxmi_path(app: AppHandle, path: String) -> Result<bool, DbError> {
     let p = Path::new(&path);
 
     if !p.exists() && p.is_dir() {
         fs::create_dir_all(&p).unwrap();
-        update_settings_default_xxmi_location(&app, p.to_str().unwrap().parse().unwrap());
+        update_settings_default_xxmi_location(&app, p.to_str().unwrap().parse().unwrap())?;
     } else {
-        update_settings_default_xxmi_location(&app, p.to_str().unwrap().parse().unwrap());
+        update_settings_default_xxmi_location(&app, p.to_str().unwrap().parse().unwrap())?;
     }
-    Some(true)
+    Ok(true)
 }
 
 #[tauri::command]
-pub fn update_settings_default_fps_unlock_path(app: AppHandle, path: String) -> Option<bool> {
+pub fn update_settings_default_fps_unlock_path(app: AppHandle, path: String) -> Result<bool, DbError> {
     let p = Path::new(&path);
 
     if !p.exists() && p.is_dir() {
         fs::create_dir_all(&p).unwrap();
-        update_settings_default_fps_unlock_location(&app, p.to_str().unwrap().parse().unwrap());
+        update_settings_default_fps_unlock_location(&app, p.to_str().unwrap().parse().unwrap())?;
     } else {
-        update_settings_default_fps_unlock_location(&app, p.to_str().unwrap().parse().unwrap());
+        update_settings_default_fps_unlock_location(&app, p.to_str().unwrap().parse().unwrap())?;
     }
-    Some(true)
+    Ok(true)
 }
 
 #[tauri::command]
-pub fn update_settings_default_jadeite_path(app: AppHandle, path: String) -> Option<bool> {
+pub fn update_settings_default_jadeite_path(app: AppHandle, path: String) -> Result<bool, DbError> {
     let p = Path::new(&path);
 
     if !p.exists() && p.is_dir() {
         fs::create_dir_all(&p).unwrap();
-        update_settings_default_jadeite_location(&app, p.to_str().unwrap().parse().unwrap());
+        update_settings_default_jadeite_location(&app, p.to_str().unwrap().parse().unwrap())?;
     } else {
-        update_settings_default_jadeite_location(&app, p.to_str().unwrap().parse().unwrap());
+        update_settings_default_jadeite_location(&app, p.to_str().unwrap().parse().unwrap())?;
     }
-    Some(true)
+    Ok(true)
 }
 
 #[tauri::command]
-pub fn update_settings_default_prefix_path(app: AppHandle, path: String) -> Option<bool> {
+pub fn update_settings_default_prefix_path(app: AppHandle, path: String) -> Result<bool, DbError> {
     let p = Path::new(&path);
 
     if !p.exists() && p.is_dir() {
         fs::create_dir_all(&p).unwrap();
-        update_settings_default_prefix_location(&app, p.to_str().unwrap().parse().unwrap());
+        update_settings_default_prefix_location(&app, p.to_str().unwrap().parse().unwrap())?;
     } else {
-        update_settings_default_prefix_location(&app, p.to_str().unwrap().parse().unwrap());
+        update_settings_default_prefix_location(&app, p.to_str().unwrap().parse().unwrap())?;
     }
-    Some(true)
+    Ok(true)
 }
 
 #[tauri::command]
-pub fn update_settings_launcher_action(app: AppHandle, action: String) -> Option<bool> {
-    update_settings_launch_action(&app, action);
-    Some(true)
+pub fn update_settings_launcher_action(app: AppHandle, action: String) -> Result<bool, DbError> {
+    update_settings_launch_action(&app, action)?;
+    Ok(true)
 }
 
 #[tauri::command]
-pub fn update_settings_manifests_hide(app: AppHandle, enabled: bool) -> Option<bool> {
-    update_settings_hide_manifests(&app, enabled);
-    Some(true)
+pub fn update_settings_manifests_hide(app: AppHandle, enabled: bool) -> Result<bool, DbError> {
+    update_settings_hide_manifests(&app, enabled)?;
+    Ok(true)
 }
 
 #[tauri::command]
@@ -120,71 +130,29 @@ pub fn block_telemetry_cmd(app: AppHandle) -> Option<bool> {
 }
 
 #[tauri::command]
-pub fn update_extras(app: AppHandle) -> bool {
-    let settings = get_settings(&app);
+pub fn update_extras(app: AppHandle) -> Result<bool, DbError> {
+    let settings = get_settings(&app)?;
     if settings.is_some() {
-        let s = settings.unwrap();
-        let xxmi = Path::new(&s.xxmi_path).to_path_buf();
-        let jadeite = Path::new(&s.jadeite_path).to_path_buf();
-        let fpsu = Path::new(&s.fps_unlock_path).to_path_buf();
-
-        // Pull latest xxmi and its packages if xxmi is installed
-        if fs::read_dir(&xxmi).unwrap().next().is_some() {
-            std::thread::spawn(move || {
-                let dl = Extras::download_xxmi("SpectrumQT/XXMI-Libs-Package".parse().unwrap(), xxmi.as_path().to_str().unwrap().parse().unwrap(), false);
-                if dl {
-                    extract_archive(xxmi.join("xxmi.zip").as_path().to_str().unwrap().parse().unwrap(), xxmi.as_path().to_str().unwrap().parse().unwrap(), false);
-                    let gimi = String::from("SilentNightSound/GIMI-Package");
-                    let srmi = String::from("SpectrumQT/SRMI-Package");
-                    let zzmi = String::from("leotorrez/ZZMI-Package");
-                    let wwmi = String::from("SpectrumQT/WWMI-Package");
-
-                    let dl1 = Extras::download_xxmi_packages(gimi, srmi, zzmi, wwmi, xxmi.as_path().to_str().unwrap().parse().unwrap(), true);
-                    if dl1 {
-                        for mi in ["gimi", "srmi", "zzmi", "wwmi"] {
-                            extract_archive(xxmi.join(format!("{mi}.zip")).as_path().to_str().unwrap().parse().unwrap(), xxmi.join(mi).as_path().to_str().unwrap().parse().unwrap(), false);
-                            for lib in ["d3d11.dll", "d3dcompiler_47.dll"] {
-                                #[cfg(target_os = "linux")]
-                                symlink(xxmi.join(lib), xxmi.join(mi).join(lib)).unwrap();
-                            }
-                        }
-                    }
-                }
-            });
-        }
-
-        // Pull latest jadeite if installed
-        if fs::read_dir(&jadeite).unwrap().next().is_some() {
-            std::thread::spawn(move || {
-                let dl = Extras::download_jadeite("MrLGamer/jadeite".parse().unwrap(), jadeite.as_path().to_str().unwrap().parse().unwrap());
-                if dl {
-                    extract_archive(jadeite.join("jadeite.zip").as_path().to_str().unwrap().parse().unwrap(), jadeite.as_path().to_str().unwrap().parse().unwrap(), false);
-                }
-            });
-        }
-
-        // Pull latest fps unlock if installed
-        if fs::read_dir(&fpsu).unwrap().next().is_some() {
-            std::thread::spawn(move || {
-                Extras::download_fps_unlock("mkrsym1/fpsunlock".parse().unwrap(), fpsu.as_path().to_str().unwrap().parse().unwrap());
-            });
-        }
-        true
+        // Pull latest version of every extra that is installed
+        std::thread::spawn(move || { update_installed_extras(&app); });
+        Ok(true)
     } else {
-        false
+        Ok(false)
     }
 }
 
 #[tauri::command]
-pub fn open_folder(app: AppHandle, manifest_id: String, install_id: String, path_type: String) {
+pub fn open_folder(app: AppHandle, manifest_id: String, install_id: String, path_type: String) -> Result<(), DbError> {
     match path_type.as_str() {
         "mods" => {
-            let settings = get_settings(&app);
-            if settings.is_some() {
+            let settings = get_settings(&app)?;
+            let manifest = get_manifest_info_by_id(&app, manifest_id)?;
+            if settings.is_some() && manifest.is_some() {
                 let s = settings.unwrap();
-                let m = get_manifest_info_by_id(&app, manifest_id).unwrap();
-                let mm = get_manifest(&app, m.filename).unwrap();
-                let fm = get_mi_path_from_game(mm.paths.exe_filename).unwrap();
+                let m = manifest.unwrap();
+                // Skipped manifests and games without XXMI support have no mods folder to open
+                let Some(mm) = get_manifest(&app, manifest_key(&m.repository_id, &m.filename)) else { return Ok(()) };
+                let Some(fm) = get_mi_path_from_game(mm.paths.exe_filename) else { return Ok(()) };
 
                 let xxmi = Path::new(&s.xxmi_path).to_path_buf();
                 let fp = xxmi.join(&fm).join("Mods");
@@ -192,7 +160,7 @@ pub fn open_folder(app: AppHandle, manifest_id: String, install_id: String, path
             }
         },
         "install" => {
-            let install = get_install_info_by_id(&app, install_id);
+            let install = get_install_info_by_id(&app, install_id)?;
             if install.is_some() {
                 let i = install.unwrap();
                 let fp = Path::new(&i.directory).join("game.log");
@@ -201,6 +169,28 @@ pub fn open_folder(app: AppHandle, manifest_id: String, install_id: String, path
         },
         _ => {}
     }
+    Ok(())
+}
+
+/// Writes settings, repositories, manifests and installs into a single JSON file for [`import_launcher_state`].
+#[tauri::command]
+pub fn export_launcher_state(app: AppHandle, path: String) -> Result<bool, StateError> {
+    let bundle = export_state(&app)?;
+    let content = serde_json::to_string_pretty(&bundle).map_err(|e| StateError::Other(e.to_string()))?;
+    fs::write(&path, content)?;
+    Ok(true)
+}
+
+/// Re-adds repositories from an exported state and adopts installs whose directories exist, `mappings` rewrite path prefixes of the old machine.
+#[tauri::command]
+pub async fn import_launcher_state(app: AppHandle, path: String, mappings: Option<Vec<PathMapping>>) -> Result<String, StateError> {
+    let content = fs::read_to_string(&path)?;
+    let bundle: LauncherStateBundle = serde_json::from_str(content.as_str()).map_err(|e| StateError::InvalidBundle(e.to_string()))?;
+    // Cloning and fetching bundles blocks, keep it off the async runtime
+    let handle = app.clone();
+    let rsp = tauri::async_runtime::spawn_blocking(move || import_state(&handle, bundle, &mappings.unwrap_or_default())).await.map_err(|e| StateError::Other(e.to_string()))??;
+    refresh_tray_menu(&app);
+    Ok(serde_json::to_string(&rsp).unwrap())
 }
 
 // === STRUCTS ===
@@ -214,5 +204,6 @@ pub struct GlobalSettings {
     pub third_party_repo_updates: i32,
     pub default_runner_prefix_path: String,
     pub launcher_action: String,
-    pub hide_manifests: bool
+    pub hide_manifests: bool,
+    pub repo_sync_interval: i32
 }
\ No newline at end of file
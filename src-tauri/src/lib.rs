@@ -1,45 +1,85 @@
 use std::sync::Mutex;
-use tauri::{Manager, RunEvent, WindowEvent};
-use crate::commands::install::{add_install, game_launch, get_download_sizes, get_install_by_id, list_installs, list_installs_by_manifest_id, remove_install, update_install_dxvk_path, update_install_dxvk_version, update_install_env_vars, update_install_fps_value, update_install_game_path, update_install_launch_args, update_install_launch_cmd, update_install_pre_launch_cmd, update_install_prefix_path, update_install_runner_path, update_install_runner_version, update_install_skip_hash_valid, update_install_skip_version_updates, update_install_use_fps_unlock, update_install_use_jadeite, update_install_use_xxmi};
-use crate::commands::manifest::{get_manifest_by_filename, get_manifest_by_id, list_game_manifests, get_game_manifest_by_filename, list_manifests_by_repository_id, update_manifest_enabled, get_game_manifest_by_manifest_id, list_compatibility_manifests, get_compatibility_manifest_by_manifest_id};
-use crate::commands::repository::{list_repositories, remove_repository, add_repository, get_repository};
-use crate::commands::settings::{block_telemetry_cmd, list_settings, open_folder, update_extras, update_settings_default_fps_unlock_path, update_settings_default_game_path, update_settings_default_jadeite_path, update_settings_default_prefix_path, update_settings_default_xxmi_path, update_settings_launcher_action, update_settings_manifests_hide, update_settings_third_party_repo_updates};
-use crate::utils::db_manager::{init_db, DbInstances};
-use crate::utils::repo_manager::{load_manifests, ManifestLoader, ManifestLoaders, RunnerLoader};
+use tauri::{AppHandle, Manager, RunEvent, WindowEvent};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tauri_plugin_notification::NotificationExt;
+use crate::commands::install::{add_install, game_launch, launch_install, list_launch_profiles, add_launch_profile, update_launch_profile, set_default_launch_profile, remove_launch_profile, get_download_sizes, get_install_by_id, list_installs, list_installs_by_manifest_id, remove_install, update_install_dxvk_path, update_install_dxvk_version, add_install_env_var, remove_install_env_var, toggle_install_env_var, update_install_launch_argv, update_install_fps_value, update_install_game_path, update_install_launch_cmd, update_install_pre_launch_cmd, update_install_prefix_path, update_install_runner_path, update_install_runner_version, update_install_skip_hash_valid, update_install_skip_version_updates, update_install_use_fps_unlock, update_install_use_jadeite, update_install_use_xxmi};
+use crate::commands::manifest::{get_manifest_by_filename, get_manifest_by_id, list_game_manifests, get_game_manifest_by_filename, list_manifests_by_repository_id, update_manifest_enabled, get_game_manifest_by_manifest_id, list_compatibility_manifests, get_compatibility_manifest_by_manifest_id, list_manifest_errors, list_extra_manifests, install_extra_by_id, list_manifest_overrides, set_manifest_override, clear_manifest_override};
+use crate::commands::repository::{add_local_repository, list_repositories, remove_repository, add_repository, get_repository, update_repository_ref, rollback_repository, set_repository_credentials, remove_repository_credentials, preview_repository, confirm_repository_preview, discard_repository_preview};
+use crate::commands::settings::{block_telemetry_cmd, export_launcher_state, import_launcher_state, list_settings, open_folder, update_extras, update_settings_default_fps_unlock_path, update_settings_default_game_path, update_settings_default_jadeite_path, update_settings_default_prefix_path, update_settings_default_xxmi_path, update_settings_launcher_action, update_settings_manifests_hide, update_settings_repo_sync_interval, update_settings_third_party_repo_updates};
+use crate::utils::db_manager::{downgrade_db, get_launch_profiles_by_install_id, init_db, DbInstances};
+use crate::utils::repo_manager::{load_manifests, start_manifest_watcher, start_repository_sync_scheduler, ExtraLoader, ManifestLoader, ManifestLoaders, ManifestWatcher, RepositoryLocks, RepositoryPreviews, RunnerLoader, RunnerVersionIndex};
 use crate::utils::{block_telemetry, register_listeners, run_async_command, ActionBlocks};
 use crate::utils::system_tray::init_tray;
 
 mod utils;
 mod commands;
 
+pub use crate::utils::manifest_lint::{lint_repository, manifest_schemas};
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let builder = tauri::Builder::default()
+        // Has to come first so a second start quits before setting anything else up
+        .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+            if args.iter().any(|a| a == "--downgrade-db") {
+                app.notification().builder().icon("dialog-error").title("TwintailLauncher").body("Close the launcher before downgrading the database!").show().unwrap();
+            } else if !launch_from_args(app, &args, false) {
+                if let Some(w) = app.get_webview_window("main") { w.show().ok(); w.set_focus().ok(); }
+            }
+        }))
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_opener::init())
-        .manage(ManifestLoaders {game: ManifestLoader::default(), runner: RunnerLoader::default()})
+        .manage(ManifestLoaders {game: ManifestLoader::default(), runner: RunnerLoader::default(), extra: ExtraLoader::default(), runner_versions: RunnerVersionIndex::default(), overridden: Default::default(), reloading: Default::default(), errors: Default::default()})
+        .manage(RepositoryPreviews::default())
+        .manage(RepositoryLocks::default())
+        .manage(ManifestWatcher::default())
         .manage(Mutex::new(ActionBlocks { action_exit: false }))
         .setup(|app| {
             let handle = app.handle();
-            run_async_command(async { init_db(&handle).await; });
+
+            // `--downgrade-db <version>` reverts the schema for an older launcher and quits
+            if let Some(target) = std::env::args().skip_while(|a| a != "--downgrade-db").nth(1) {
+                let rslt = match target.parse::<i64>() {
+                    Ok(t) => run_async_command(async { downgrade_db(&handle, t).await }).map_err(|e| e.to_string()),
+                    Err(_) => Err(format!("{} is not a schema version", target))
+                };
+                if let Err(e) = &rslt { eprintln!("Failed to downgrade database: {}", e); }
+                handle.exit(if rslt.is_ok() { 0 } else { 1 });
+                return Ok(());
+            }
+
+            if let Err(e) = run_async_command(async { init_db(&handle).await }) {
+                if let Some(w) = handle.get_webview_window("main") { w.hide().ok(); }
+                let h = handle.clone();
+                handle.dialog().message(e.to_string()).title("TwintailLauncher").kind(MessageDialogKind::Error).show(move |_| h.exit(1));
+                return Ok(());
+            }
+            // Watcher first, the initial load hands it the local repositories to follow
+            start_manifest_watcher(&handle);
             load_manifests(&handle);
             init_tray(&handle).unwrap();
             register_listeners(&handle);
+            start_repository_sync_scheduler(&handle);
+
+            // Started only to launch a game, keep the window hidden and quit once it is running
+            if launch_from_args(&handle, &std::env::args().collect::<Vec<String>>(), true) {
+                if let Some(w) = handle.get_webview_window("main") { w.hide().ok(); }
+            }
 
             let path = app.path().app_data_dir().unwrap().join(".telemetry_blocked");
             if !path.exists() { block_telemetry(&handle);
             }
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![open_folder, update_extras, block_telemetry_cmd, list_settings, update_settings_third_party_repo_updates, update_settings_default_game_path, update_settings_default_xxmi_path, update_settings_default_fps_unlock_path, update_settings_default_jadeite_path, update_settings_default_prefix_path, update_settings_launcher_action, update_settings_manifests_hide,
-            remove_repository, add_repository, get_repository, list_repositories,
+        .invoke_handler(tauri::generate_handler![open_folder, update_extras, block_telemetry_cmd, list_settings, update_settings_third_party_repo_updates, update_settings_default_game_path, update_settings_default_xxmi_path, update_settings_default_fps_unlock_path, update_settings_default_jadeite_path, update_settings_default_prefix_path, update_settings_launcher_action, update_settings_manifests_hide, update_settings_repo_sync_interval, export_launcher_state, import_launcher_state,
+            remove_repository, add_repository, add_local_repository, get_repository, list_repositories, update_repository_ref, rollback_repository, set_repository_credentials, remove_repository_credentials, preview_repository, confirm_repository_preview, discard_repository_preview,
             get_manifest_by_id, get_manifest_by_filename, list_manifests_by_repository_id, update_manifest_enabled,
             get_game_manifest_by_filename, list_game_manifests, get_game_manifest_by_manifest_id,
             list_installs, list_installs_by_manifest_id, get_install_by_id, add_install, remove_install,
-            update_install_game_path, update_install_runner_path, update_install_dxvk_path, update_install_skip_version_updates, update_install_skip_hash_valid, update_install_use_jadeite, update_install_use_xxmi, update_install_use_fps_unlock, update_install_fps_value, update_install_env_vars, update_install_pre_launch_cmd, update_install_launch_cmd, update_install_prefix_path, update_install_launch_args, update_install_dxvk_version, update_install_runner_version,
-            list_compatibility_manifests, get_compatibility_manifest_by_manifest_id,
-            game_launch, get_download_sizes])
+            update_install_game_path, update_install_runner_path, update_install_dxvk_path, update_install_skip_version_updates, update_install_skip_hash_valid, update_install_use_jadeite, update_install_use_xxmi, update_install_use_fps_unlock, update_install_fps_value, add_install_env_var, remove_install_env_var, toggle_install_env_var, update_install_pre_launch_cmd, update_install_launch_cmd, update_install_prefix_path, update_install_launch_argv, update_install_dxvk_version, update_install_runner_version,
+            list_compatibility_manifests, get_compatibility_manifest_by_manifest_id, list_manifest_errors, list_extra_manifests, install_extra_by_id, list_manifest_overrides, set_manifest_override, clear_manifest_override,
+            game_launch, list_launch_profiles, add_launch_profile, update_launch_profile, set_default_launch_profile, remove_launch_profile, get_download_sizes])
         .build(tauri::generate_context!())
         .expect("Error while running KeqingLauncher!");
 
@@ -60,11 +100,48 @@ pub fn run() {
                 }
             }
             RunEvent::Exit => {
-                    run_async_command(async {
-                        app.state::<DbInstances>().0.lock().await.get("db").unwrap().close().await;
-                    });
+                    // Not managed when the database was refused at startup
+                    if let Some(instances) = app.try_state::<DbInstances>() {
+                        run_async_command(async {
+                            if let Some(pool) = instances.0.lock().await.get("db") { pool.close().await; }
+                        });
+                    }
             }
             _ => ()
         }
     })
 }
+
+/// Handles `--launch <install id> [--profile <profile id or name>]`, returns whether `args` asked for a launch at all.
+/// With `exit` the launcher quits after the launch, exit code 1 means it failed and the reason was shown as a notification.
+fn launch_from_args(app: &AppHandle, args: &[String], exit: bool) -> bool {
+    let install = match args.iter().skip_while(|a| *a != "--launch").nth(1) {
+        Some(i) => i.clone(),
+        None => return false
+    };
+    let profile = args.iter().skip_while(|a| *a != "--profile").nth(1).cloned();
+
+    let h = app.clone();
+    std::thread::spawn(move || {
+        let rslt = match profile {
+            Some(p) => match get_launch_profiles_by_install_id(&h, install.clone()).map(|profiles| profiles.into_iter().find(|lp| lp.id == p || lp.name == p)) {
+                Ok(Some(lp)) => launch_install(&h, install, Some(lp.id)).map_err(|e| e.to_string()),
+                Ok(None) => Err(format!("Launch profile {} does not exist for this installation.", p)),
+                Err(e) => Err(e.to_string())
+            },
+            None => launch_install(&h, install, None).map_err(|e| e.to_string())
+        };
+
+        // Ok(None) means launch_install already told the user why
+        let launched = match rslt {
+            Ok(l) => l.is_some(),
+            Err(e) => {
+                eprintln!("Failed to launch: {}", e);
+                h.notification().builder().icon("dialog-error").title("TwintailLauncher").body(format!("Failed to launch game! {}", e)).show().unwrap();
+                false
+            }
+        };
+        if exit { h.exit(if launched { 0 } else { 1 }); }
+    });
+    true
+}
@@ -1,14 +1,18 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use futures_core::future::BoxFuture;
-use sqlx::{query, Error, Executor, Pool, Row, Sqlite, error::BoxDynError, sqlite::SqliteQueryResult, migrate::{Migration as SqlxMigration, MigrateDatabase, MigrationSource, MigrationType, Migrator}};
+use serde::{Deserialize, Serialize};
+use sqlx::{query, Error, Pool, Row, Sqlite, error::{BoxDynError, ErrorKind}, query::Query, sqlite::{SqliteArguments, SqliteConnection, SqliteRow}, migrate::{Migration as SqlxMigration, MigrateDatabase, MigrationSource, MigrationType, Migrator}};
 use tauri::{AppHandle, Manager};
 use tokio::sync::{Mutex};
 use crate::commands::settings::GlobalSettings;
-use crate::utils::repo_manager::{setup_compatibility_repository, setup_official_repository, LauncherInstall, LauncherManifest, LauncherRepository};
-use crate::utils::{run_async_command};
+use crate::utils::repo_manager::{setup_compatibility_repository, setup_official_repository, InstallEnvVar, LaunchProfile, LauncherInstall, LauncherInstalledExtra, LauncherManifest, LauncherRepository, RepositoryCredentials, RepositoryManifest, RepositoryTransport};
+use crate::utils::{generate_cuid, parse_legacy_env_vars, parse_legacy_launch_args, run_async_command};
 
-pub async fn init_db(app: &AppHandle) {
+pub async fn init_db(app: &AppHandle) -> Result<(), DbError> {
     let data_path = app.path().app_data_dir().unwrap();
     let conn_url = data_path.join("storage.db");
     let manifests_dir = data_path.join("manifests");
@@ -23,53 +27,14 @@ pub async fn init_db(app: &AppHandle) {
         }
     }
 
-    let migrationsl = vec![
-        Migration {
-            version: 1,
-            description: "init_repository_table",
-            sql: r#"CREATE TABLE IF NOT EXISTS "repository" ("id" string PRIMARY KEY,"github_id" string);"#,
-            kind: MigrationKind::Up,
-        },
-        Migration {
-            version: 2,
-            description: "init_manifest_table",
-            sql: r#"CREATE TABLE IF NOT EXISTS manifest ("id" string PRIMARY KEY, "repository_id" string, "display_name" string, "filename" string, "enabled" bool, CONSTRAINT fk_manifest_repo FOREIGN KEY(repository_id) REFERENCES repository(id));"#,
-            kind: MigrationKind::Up,
-        },
-        Migration {
-            version: 6,
-            description: "init_install_table",
-            sql: r#"CREATE TABLE IF NOT EXISTS install ("id" TEXT PRIMARY KEY, "manifest_id" TEXT, "version" TEXT, "name" TEXT, "directory" TEXT, "runner_path" TEXT, "dxvk_path" TEXT, "runner_version" TEXT, "dxvk_version" TEXT, "game_icon" TEXT, "game_background" TEXT, "ignore_updates" bool, "skip_hash_check" bool, "use_jadeite" bool, "use_xxmi" bool, "use_fps_unlock" bool, "env_vars" TEXT, "pre_launch_command" TEXT, "launch_command" TEXT, "fps_value" TEXT, "runner_prefix_path" TEXT, "launch_args" TEXT, "audio_langs" TEXT, CONSTRAINT fk_install_manifest FOREIGN KEY(manifest_id) REFERENCES manifest(id));"#,
-            kind: MigrationKind::Up,
-        },
-        Migration {
-            version: 7,
-            description: "init_settings_table",
-            sql: r#"CREATE TABLE IF NOT EXISTS settings ("default_game_path" TEXT default null, "third_party_repo_updates" bool default 0 not null, "xxmi_path" TEXT default null, fps_unlock_path TEXT default null, jadeite_path TEXT default null, default_runner_prefix_path TEXT default null, "launcher_action" TEXT default null, id integer not null CONSTRAINT settings_pk primary key autoincrement, "hide_manifests" bool not null);"#,
-            kind: MigrationKind::Up,
-        },
-        Migration {
-            version: 5,
-            description: "populate_settings_table",
-            sql: r#"INSERT INTO settings (default_game_path, third_party_repo_updates, xxmi_path, fps_unlock_path, jadeite_path, default_runner_prefix_path, launcher_action, id, hide_manifests) values (null, false, null, null, null, null, "exit", 1, false);"#,
-            kind: MigrationKind::Up,
-        }
-    ];
-
-    let mut migrations = add_migrations("db", migrationsl);
-
-    let instances = DbInstances::default();
-    let mut tmp = instances.0.lock().await;
-    let pool: Pool<Sqlite> = Pool::connect(&conn_url.to_str().unwrap()).await.unwrap();
-
-    tmp.insert(String::from("db"), pool.clone());
-
-    if let Some(migrations) = migrations.as_mut().unwrap().remove("db") {
-        let migrator = Migrator::new(migrations).await.unwrap();
-        migrator.run(&pool).await.unwrap();
+    let pool: Pool<Sqlite> = Pool::connect(&conn_url.to_str().unwrap()).await?;
+    if let Err(e) = migrate_db(&pool, &data_path).await {
+        pool.close().await;
+        return Err(e);
     }
 
-    drop(tmp);
+    let instances = DbInstances::default();
+    instances.0.lock().await.insert(String::from("db"), pool.clone());
     app.manage(instances);
 
     // Init and setup default paths...
@@ -77,25 +42,34 @@ pub async fn init_db(app: &AppHandle) {
     let xxmipath = data_path.join("extras").join("xxmi");
     let fpsunlockpath = data_path.join("extras").join("fps_unlock");
     let jadeitepath = data_path.join("extras").join("jadeite");
+    let previewspath = data_path.join("previews");
+
+    // Previews live in memory only, anything left on disk is from a previous session
+    if previewspath.exists() {
+        if let Err(_e) = fs::remove_dir_all(&previewspath) {
+            #[cfg(debug_assertions)]
+            { println!("Failed to clean up {}: {}", previewspath.display(), _e); }
+        }
+    }
 
     if !defgpath.exists() {
         fs::create_dir_all(&defgpath).unwrap();
-        query("UPDATE settings SET 'default_game_path' = $1 WHERE id = 1;").bind(defgpath.as_path().to_str().unwrap()).execute(&pool).await.unwrap();
+        query("UPDATE settings SET 'default_game_path' = $1 WHERE id = 1;").bind(defgpath.as_path().to_str().unwrap()).execute(&pool).await?;
     }
 
     if !xxmipath.exists() {
         fs::create_dir_all(&xxmipath).unwrap();
-        query("UPDATE settings SET 'xxmi_path' = $1 WHERE id = 1;").bind(xxmipath.as_path().to_str().unwrap()).execute(&pool).await.unwrap();
+        query("UPDATE settings SET 'xxmi_path' = $1 WHERE id = 1;").bind(xxmipath.as_path().to_str().unwrap()).execute(&pool).await?;
     }
 
     if !fpsunlockpath.exists() {
         fs::create_dir_all(&fpsunlockpath).unwrap();
-        query("UPDATE settings SET 'fps_unlock_path' = $1 WHERE id = 1;").bind(fpsunlockpath.as_path().to_str().unwrap()).execute(&pool).await.unwrap();
+        query("UPDATE settings SET 'fps_unlock_path' = $1 WHERE id = 1;").bind(fpsunlockpath.as_path().to_str().unwrap()).execute(&pool).await?;
     }
 
     if !jadeitepath.exists() {
         fs::create_dir_all(&jadeitepath).unwrap();
-        query("UPDATE settings SET 'jadeite_path' = $1 WHERE id = 1;").bind(jadeitepath.as_path().to_str().unwrap()).execute(&pool).await.unwrap();
+        query("UPDATE settings SET 'jadeite_path' = $1 WHERE id = 1;").bind(jadeitepath.as_path().to_str().unwrap()).execute(&pool).await?;
     }
 
     #[cfg(target_os = "linux")]
@@ -109,7 +83,7 @@ pub async fn init_db(app: &AppHandle) {
             fs::create_dir_all(&wine).unwrap();
             fs::create_dir_all(&dxvk).unwrap();
             fs::create_dir_all(&prefixes).unwrap();
-            query("UPDATE settings SET 'default_runner_prefix_path' = $1 WHERE id = 1;").bind(prefixes.as_path().to_str().unwrap()).execute(&pool).await.unwrap();
+            query("UPDATE settings SET 'default_runner_prefix_path' = $1 WHERE id = 1;").bind(prefixes.as_path().to_str().unwrap()).execute(&pool).await?;
         }
     }
 
@@ -124,699 +98,1080 @@ pub async fn init_db(app: &AppHandle) {
         setup_official_repository(&app, &manifests_dir);
         setup_compatibility_repository(&app, &manifests_dir);
     }
+    Ok(())
 }
 
 
 // === SETTINGS ===
 
-pub fn get_settings(app: &AppHandle) -> Option<GlobalSettings> {
-    let mut rslt = vec![];
+pub fn get_settings(app: &AppHandle) -> Result<Option<GlobalSettings>, DbError> {
+    fetch_optional_as(app, query("SELECT * FROM settings WHERE id = 1"), settings_from_row)
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
-
-        let query = query("SELECT * FROM settings WHERE id = 1");
-        rslt = query.fetch_all(&db).await.unwrap();
-    });
-
-    if rslt.len() >= 1 {
-        let rsltt = GlobalSettings {
-            default_game_path: rslt.get(0).unwrap().get("default_game_path"),
-            xxmi_path: rslt.get(0).unwrap().get("xxmi_path"),
-            fps_unlock_path: rslt.get(0).unwrap().get("fps_unlock_path"),
-            jadeite_path: rslt.get(0).unwrap().get("jadeite_path"),
-            third_party_repo_updates: rslt.get(0).unwrap().get("third_party_repo_updates"),
-            default_runner_prefix_path: rslt.get(0).unwrap().get("default_runner_prefix_path"),
-            launcher_action: rslt.get(0).unwrap().get("launcher_action"),
-            hide_manifests: rslt.get(0).unwrap().get("hide_manifests"),
-        };
+pub fn update_settings_third_party_repo_update(app: &AppHandle, enabled: bool) -> Result<bool, DbError> {
+    execute(app, query("UPDATE settings SET 'third_party_repo_updates' = $1 WHERE id = 1").bind(enabled))
+}
 
-        Some(rsltt)
-    } else {
-        None
-    }
+pub fn update_settings_default_game_location(app: &AppHandle, path: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE settings SET 'default_game_path' = $1 WHERE id = 1").bind(path))
 }
 
-pub fn update_settings_third_party_repo_update(app: &AppHandle, enabled: bool) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+pub fn update_settings_default_xxmi_location(app: &AppHandle, path: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE settings SET 'xxmi_path' = $1 WHERE id = 1").bind(path))
+}
 
-        let query = query("UPDATE settings SET 'third_party_repo_updates' = $1 WHERE id = 1").bind(enabled);
-        query.execute(&db).await.unwrap();
-    });
+pub fn update_settings_default_fps_unlock_location(app: &AppHandle, path: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE settings SET 'fps_unlock_path' = $1 WHERE id = 1").bind(path))
 }
 
-pub fn update_settings_default_game_location(app: &AppHandle, path: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+pub fn update_settings_default_jadeite_location(app: &AppHandle, path: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE settings SET 'jadeite_path' = $1 WHERE id = 1").bind(path))
+}
 
-        let query = query("UPDATE settings SET 'default_game_path' = $1 WHERE id = 1").bind(path);
-        query.execute(&db).await.unwrap();
-    });
+pub fn update_settings_default_prefix_location(app: &AppHandle, path: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE settings SET 'default_runner_prefix_path' = $1 WHERE id = 1").bind(path))
 }
 
-pub fn update_settings_default_xxmi_location(app: &AppHandle, path: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+pub fn update_settings_launch_action(app: &AppHandle, action: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE settings SET 'launcher_action' = $1 WHERE id = 1").bind(action))
+}
 
-        let query = query("UPDATE settings SET 'xxmi_path' = $1 WHERE id = 1").bind(path);
-        query.execute(&db).await.unwrap();
-    });
+pub fn update_settings_hide_manifests(app: &AppHandle, enabled: bool) -> Result<bool, DbError> {
+    execute(app, query("UPDATE settings SET 'hide_manifests' = $1 WHERE id = 1").bind(enabled))
 }
 
-pub fn update_settings_default_fps_unlock_location(app: &AppHandle, path: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+pub fn update_settings_repo_sync_interval_by_minutes(app: &AppHandle, interval: i32) -> Result<bool, DbError> {
+    execute(app, query("UPDATE settings SET 'repo_sync_interval' = $1 WHERE id = 1").bind(interval))
+}
 
-        let query = query("UPDATE settings SET 'fps_unlock_path' = $1 WHERE id = 1").bind(path);
-        query.execute(&db).await.unwrap();
-    });
+/// Overwrites every setting at once, used when importing launcher state.
+fn update_settings_query(settings: &GlobalSettings) -> Query<'static, Sqlite, SqliteArguments<'static>> {
+    query("UPDATE settings SET 'default_game_path' = $1, 'xxmi_path' = $2, 'fps_unlock_path' = $3, 'jadeite_path' = $4, 'third_party_repo_updates' = $5, 'default_runner_prefix_path' = $6, 'launcher_action' = $7, 'hide_manifests' = $8, 'repo_sync_interval' = $9 WHERE id = 1").bind(settings.default_game_path.clone()).bind(settings.xxmi_path.clone()).bind(settings.fps_unlock_path.clone()).bind(settings.jadeite_path.clone()).bind(settings.third_party_repo_updates).bind(settings.default_runner_prefix_path.clone()).bind(settings.launcher_action.clone()).bind(settings.hide_manifests).bind(settings.repo_sync_interval)
 }
 
-pub fn update_settings_default_jadeite_location(app: &AppHandle, path: String) {
+fn settings_from_row(r: &SqliteRow) -> Result<GlobalSettings, Error> {
+    Ok(GlobalSettings {
+        default_game_path: r.try_get("default_game_path")?,
+        xxmi_path: r.try_get("xxmi_path")?,
+        fps_unlock_path: r.try_get("fps_unlock_path")?,
+        jadeite_path: r.try_get("jadeite_path")?,
+        third_party_repo_updates: r.try_get("third_party_repo_updates")?,
+        default_runner_prefix_path: r.try_get("default_runner_prefix_path")?,
+        launcher_action: r.try_get("launcher_action")?,
+        hide_manifests: r.try_get("hide_manifests")?,
+        repo_sync_interval: r.try_get("repo_sync_interval")?,
+    })
+}
+
+// === REPOSITORIES ===
+
+/// Inserts a local repository together with its metadata as one transaction.
+pub fn create_local_repository(app: &AppHandle, id: String, github_id: &str, remote: &str, local_path: &str, metadata: &RepositoryManifest) -> Result<bool, DbError> {
     run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+        let db = pool(app).await?;
+        let mut tx = db.begin().await?;
+
+        let rslt = query("INSERT INTO repository(id, github_id, remote, host, ref_kind, ref_name, local_path, transport) VALUES ($1, $2, $3, 'local', 'local', '', $4, $5)").bind(id.clone()).bind(github_id).bind(remote).bind(local_path).bind(RepositoryTransport::Local.as_str()).execute(&mut *tx).await?;
+        repository_metadata_query(id, metadata).execute(&mut *tx).await?;
 
-        let query = query("UPDATE settings SET 'jadeite_path' = $1 WHERE id = 1").bind(path);
-        query.execute(&db).await.unwrap();
-    });
+        tx.commit().await?;
+        Ok(rslt.rows_affected() >= 1)
+    })
 }
 
-pub fn update_settings_default_prefix_location(app: &AppHandle, path: String) {
+/// Inserts a bundle repository whose bundle is already unpacked, with its revision, index validators and metadata as one transaction.
+pub fn create_bundle_repository(app: &AppHandle, id: String, github_id: &str, remote: &str, host: &str, revision: &str, etag: Option<String>, last_modified: Option<String>, metadata: &RepositoryManifest) -> Result<bool, DbError> {
     run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+        let db = pool(app).await?;
+        let mut tx = db.begin().await?;
+
+        let rslt = query("INSERT INTO repository(id, github_id, remote, host, ref_kind, ref_name, etag, last_modified, transport) VALUES ($1, $2, $3, $4, 'revision', $5, $6, $7, $8)").bind(id.clone()).bind(github_id).bind(remote).bind(host).bind(revision).bind(etag).bind(last_modified).bind(RepositoryTransport::Bundle.as_str()).execute(&mut *tx).await?;
+        repository_metadata_query(id, metadata).execute(&mut *tx).await?;
 
-        let query = query("UPDATE settings SET 'default_runner_prefix_path' = $1 WHERE id = 1").bind(path);
-        query.execute(&db).await.unwrap();
-    });
+        tx.commit().await?;
+        Ok(rslt.rows_affected() >= 1)
+    })
 }
 
-pub fn update_settings_launch_action(app: &AppHandle, action: String) {
+/// Inserts a cloned repository with its metadata, credentials and `(id, display name, file name)` manifest rows as one transaction.
+pub fn create_repository(app: &AppHandle, id: String, github_id: &str, remote: &str, host: &str, branch: &str, metadata: &RepositoryManifest, credentials: Option<&RepositoryCredentials>, manifests: &[(String, String, String)]) -> Result<bool, DbError> {
     run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+        let db = pool(app).await?;
+        let mut tx = db.begin().await?;
 
-        let query = query("UPDATE settings SET 'launcher_action' = $1 WHERE id = 1").bind(action);
-        query.execute(&db).await.unwrap();
-    });
+        let rslt = query("INSERT INTO repository(id, github_id, remote, host, ref_kind, ref_name) VALUES ($1, $2, $3, $4, 'branch', $5)").bind(id.clone()).bind(github_id).bind(remote).bind(host).bind(branch).execute(&mut *tx).await?;
+        repository_metadata_query(id.clone(), metadata).execute(&mut *tx).await?;
+
+        if let Some(c) = credentials {
+            let secret_ref = store_secret(id.as_str(), c.secret.as_deref())?;
+            query("INSERT OR REPLACE INTO repository_credentials(repository_id, kind, username, secret_ref, ssh_key_path) VALUES ($1, $2, $3, $4, $5)").bind(id.clone()).bind(c.kind.clone()).bind(c.username.clone()).bind(secret_ref).bind(c.ssh_key_path.clone()).execute(&mut *tx).await?;
+        }
+        for (mid, display_name, filename) in manifests {
+            query("INSERT INTO manifest(id, repository_id, display_name, filename, enabled) VALUES ($1, $2, $3, $4, true)").bind(mid.clone()).bind(id.clone()).bind(display_name.clone()).bind(filename.clone()).execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(rslt.rows_affected() >= 1)
+    }).inspect_err(|_| delete_secret(id.as_str()))
 }
 
-pub fn update_settings_hide_manifests(app: &AppHandle, enabled: bool) {
+/// Removes a repository together with its manifest rows and credentials, and with `with_installs` also every installation of those manifests.
+/// Runs as a single transaction so a failure never leaves installs pointing at half of a repository.
+pub fn delete_repository_by_id(app: &AppHandle, id: String, with_installs: bool) -> Result<bool, DbError> {
     run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+        let db = pool(app).await?;
+        let mut tx = db.begin().await?;
+
+        if with_installs { query("DELETE FROM install WHERE manifest_id IN (SELECT id FROM manifest WHERE repository_id = $1)").bind(id.clone()).execute(&mut *tx).await?; }
+        query("DELETE FROM manifest WHERE repository_id = $1").bind(id.clone()).execute(&mut *tx).await?;
+        query("DELETE FROM repository_credentials WHERE repository_id = $1").bind(id.clone()).execute(&mut *tx).await?;
+        let rslt = query("DELETE FROM repository WHERE id = $1").bind(id.clone()).execute(&mut *tx).await?;
 
-        let query = query("UPDATE settings SET 'hide_manifests' = $1 WHERE id = 1").bind(enabled);
-        query.execute(&db).await.unwrap();
-    });
+        tx.commit().await?;
+        Ok(rslt.rows_affected() >= 1)
+    }).inspect(|_| delete_secret(id.as_str()))
 }
 
-// === REPOSITORIES ===
+pub fn get_repository_info_by_id(app: &AppHandle, id: String) -> Result<Option<LauncherRepository>, DbError> {
+    fetch_optional_as(app, query("SELECT * FROM repository WHERE id = $1").bind(id), repository_from_row)
+}
 
-pub fn create_repository(app: &AppHandle, id: String, github_id: &str) -> Result<bool, Error> {
-    let mut rslt = SqliteQueryResult::default();
+pub fn get_repository_info_by_remote(app: &AppHandle, remote: String) -> Result<Option<LauncherRepository>, DbError> {
+    fetch_optional_as(app, query("SELECT * FROM repository WHERE remote = $1").bind(remote), repository_from_row)
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+pub fn get_repositories(app: &AppHandle) -> Result<Vec<LauncherRepository>, DbError> {
+    fetch_all_as(app, query("SELECT * FROM repository"), repository_from_row)
+}
 
-        let query = query("INSERT INTO repository(id, github_id) VALUES ($1, $2)").bind(id).bind(github_id);
-        rslt = query.execute(&db).await.unwrap();
-    });
+pub fn update_repository_ref_by_id(app: &AppHandle, id: String, ref_kind: String, ref_name: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE repository SET 'ref_kind' = $1, 'ref_name' = $2 WHERE id = $3").bind(ref_kind).bind(ref_name).bind(id))
+}
 
-    if rslt.rows_affected() >= 1 {
-        Ok(true)
-    } else {
-        Ok(false)
-    }
+pub fn update_repository_previous_commit_by_id(app: &AppHandle, id: String, commit: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE repository SET 'previous_commit' = $1 WHERE id = $2").bind(commit).bind(id))
 }
 
-pub fn delete_repository_by_id(app: &AppHandle, id: String) -> Result<bool, Error> {
-    let mut rslt = SqliteQueryResult::default();
+/// Records the revision a bundle repository is on and the validators for the next conditional request of its index.
+pub fn update_repository_bundle_state_by_id(app: &AppHandle, id: String, revision: String, etag: Option<String>, last_modified: Option<String>) -> Result<bool, DbError> {
+    execute(app, query("UPDATE repository SET 'ref_name' = $1, 'etag' = $2, 'last_modified' = $3 WHERE id = $4").bind(revision).bind(etag).bind(last_modified).bind(id))
+}
 
+/// Records a freshly unpacked bundle, `previous` is the revision it replaced, as one transaction.
+pub fn update_bundle_repository_by_id(app: &AppHandle, id: String, previous: Option<String>, revision: String, etag: Option<String>, last_modified: Option<String>, metadata: &RepositoryManifest) -> Result<bool, DbError> {
     run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+        let db = pool(app).await?;
+        let mut tx = db.begin().await?;
 
-        let query = query("DELETE FROM repository WHERE id = $1").bind(id);
-        rslt = query.execute(&db).await.unwrap();
-    });
+        if let Some(p) = previous { query("UPDATE repository SET 'previous_commit' = $1 WHERE id = $2").bind(p).bind(id.clone()).execute(&mut *tx).await?; }
+        let rslt = query("UPDATE repository SET 'ref_name' = $1, 'etag' = $2, 'last_modified' = $3 WHERE id = $4").bind(revision).bind(etag).bind(last_modified).bind(id.clone()).execute(&mut *tx).await?;
+        repository_metadata_query(id, metadata).execute(&mut *tx).await?;
 
-    if rslt.rows_affected() >= 1 {
-        Ok(true)
-    } else {
-        Ok(false)
-    }
+        tx.commit().await?;
+        Ok(rslt.rows_affected() >= 1)
+    })
 }
 
-pub fn get_repository_info_by_id(app: &AppHandle, id: String) -> Option<LauncherRepository> {
-    let mut rslt = vec![];
+pub fn update_repository_metadata_by_id(app: &AppHandle, id: String, metadata: &RepositoryManifest) -> Result<bool, DbError> {
+    execute(app, repository_metadata_query(id, metadata))
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+fn repository_metadata_query(id: String, metadata: &RepositoryManifest) -> Query<'static, Sqlite, SqliteArguments<'static>> {
+    let maintainers = serde_json::to_string(&metadata.maintainers).unwrap();
+    query("UPDATE repository SET 'name' = $1, 'description' = $2, 'maintainers' = $3, 'homepage' = $4, 'icon' = $5, 'min_launcher_version' = $6 WHERE id = $7").bind(metadata.name.clone()).bind(metadata.description.clone()).bind(maintainers).bind(metadata.homepage.clone()).bind(metadata.icon.clone()).bind(metadata.min_launcher_version.clone()).bind(id)
+}
 
-        let query = query("SELECT * FROM repository WHERE id = $1").bind(id);
-        rslt = query.fetch_all(&db).await.unwrap();
-    });
+/// Secrets go to the OS keyring, the row only keeps the name of the keyring entry.
+pub fn set_repository_credentials_by_id(app: &AppHandle, repository_id: String, credentials: &RepositoryCredentials) -> Result<bool, DbError> {
+    let secret_ref = store_secret(repository_id.as_str(), credentials.secret.as_deref())?;
+    execute(app, query("INSERT OR REPLACE INTO repository_credentials(repository_id, kind, username, secret, secret_ref, ssh_key_path) VALUES ($1, $2, $3, null, $4, $5)").bind(repository_id).bind(credentials.kind.clone()).bind(credentials.username.clone()).bind(secret_ref).bind(credentials.ssh_key_path.clone()))
+}
 
-    if rslt.len() >= 1 {
-        let rsltt = LauncherRepository {
-            id: rslt.get(0).unwrap().get("id"),
-            github_id: rslt.get(0).unwrap().get("github_id"),
+pub fn get_repository_credentials_by_repository_id(app: &AppHandle, repository_id: String) -> Result<Option<RepositoryCredentials>, DbError> {
+    let row = fetch_optional_as(app, query("SELECT * FROM repository_credentials WHERE repository_id = $1").bind(repository_id), |r| {
+        let credentials = RepositoryCredentials {
+            kind: r.try_get("kind")?,
+            username: r.try_get("username")?,
+            // Only set for rows written before secrets moved to the keyring, see move_secrets_to_keyring
+            secret: r.try_get("secret")?,
+            ssh_key_path: r.try_get("ssh_key_path")?,
         };
+        Ok((credentials, r.try_get::<Option<String>, _>("secret_ref")?))
+    })?;
 
-        Some(rsltt)
-    } else {
-        None
+    match row {
+        Some((mut credentials, Some(secret_ref))) => {
+            credentials.secret = load_secret(secret_ref.as_str())?;
+            Ok(Some(credentials))
+        }
+        Some((credentials, None)) => Ok(Some(credentials)),
+        None => Ok(None)
     }
 }
 
-pub fn get_repository_info_by_github_id(app: &AppHandle, github_id: String) -> Option<LauncherRepository> {
-    let mut rslt = vec![];
+pub fn delete_repository_credentials_by_repository_id(app: &AppHandle, repository_id: String) -> Result<bool, DbError> {
+    let rslt = execute(app, query("DELETE FROM repository_credentials WHERE repository_id = $1").bind(repository_id.clone()))?;
+    delete_secret(repository_id.as_str());
+    Ok(rslt)
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+/// Service name all repository secrets are filed under in the OS keyring.
+const KEYRING_SERVICE: &str = "KeqingLauncher";
 
-        let query = query("SELECT * FROM repository WHERE github_id = $1").bind(github_id);
-        rslt = query.fetch_all(&db).await.unwrap();
-    });
+fn secret_entry(secret_ref: &str) -> Result<keyring::Entry, DbError> {
+    keyring::Entry::new(KEYRING_SERVICE, secret_ref).map_err(|e| DbError::Secret(e.to_string()))
+}
 
-    if rslt.len() >= 1 {
-        let rsltt = LauncherRepository {
-            id: rslt.get(0).unwrap().get("id"),
-            github_id: rslt.get(0).unwrap().get("github_id"),
-        };
+fn secret_ref(repository_id: &str) -> String {
+    format!("repository/{}", repository_id)
+}
 
-        Some(rsltt)
-    } else {
-        None
+/// Stores the secret of a repository in the keyring, returns the reference to keep in the database or `None` when there is no secret.
+fn store_secret(repository_id: &str, secret: Option<&str>) -> Result<Option<String>, DbError> {
+    match secret.filter(|s| !s.is_empty()) {
+        Some(s) => {
+            let r = secret_ref(repository_id);
+            secret_entry(r.as_str())?.set_password(s).map_err(|e| DbError::Secret(e.to_string()))?;
+            Ok(Some(r))
+        }
+        None => {
+            delete_secret(repository_id);
+            Ok(None)
+        }
     }
 }
 
-pub fn get_repositories(app: &AppHandle) -> Option<Vec<LauncherRepository>> {
-    let mut rslt = vec![];
+fn load_secret(secret_ref: &str) -> Result<Option<String>, DbError> {
+    match secret_entry(secret_ref)?.get_password() {
+        Ok(s) => Ok(Some(s)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(DbError::Secret(e.to_string()))
+    }
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
-
-        let query = query("SELECT * FROM repository");
-        rslt = query.fetch_all(&db).await.unwrap();
-    });
-
-    if rslt.len() >= 1 {
-        let mut rsltt = Vec::<LauncherRepository>::new();
-        for r in rslt {
-            rsltt.push(LauncherRepository {
-                id: r.get("id"),
-                github_id: r.get("github_id"),
-            })
+/// Best effort, a secret left behind in the keyring is harmless once nothing references it anymore.
+fn delete_secret(repository_id: &str) {
+    if let Ok(entry) = secret_entry(secret_ref(repository_id).as_str()) {
+        match entry.delete_credential() {
+            Ok(_) | Err(keyring::Error::NoEntry) => {}
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                { println!("Failed to delete keyring entry of repository {}: {}", repository_id, _e); }
+            }
         }
-
-        Some(rsltt)
-    } else {
-        None
     }
 }
 
+fn repository_from_row(r: &SqliteRow) -> Result<LauncherRepository, Error> {
+    Ok(LauncherRepository {
+        id: r.try_get("id")?,
+        github_id: r.try_get("github_id")?,
+        remote: r.try_get("remote")?,
+        host: r.try_get("host")?,
+        ref_kind: r.try_get("ref_kind")?,
+        ref_name: r.try_get("ref_name")?,
+        previous_commit: r.try_get("previous_commit")?,
+        name: r.try_get("name")?,
+        description: r.try_get("description")?,
+        maintainers: serde_json::from_str(r.try_get::<String, _>("maintainers")?.as_str()).unwrap_or_default(),
+        homepage: r.try_get("homepage")?,
+        icon: r.try_get("icon")?,
+        min_launcher_version: r.try_get("min_launcher_version")?,
+        local_path: r.try_get("local_path")?,
+        transport: r.try_get::<String, _>("transport")?.parse().map_err(|e: String| Error::ColumnDecode { index: "transport".to_string(), source: e.into() })?,
+        etag: r.try_get("etag")?,
+        last_modified: r.try_get("last_modified")?,
+    })
+}
+
 // === MANIFESTS ===
 
-pub fn create_manifest(app: &AppHandle, id: String, repository_id: String, display_name: &str, filename: &str, enabled: bool) -> Result<bool, Error> {
-    let mut rslt = SqliteQueryResult::default();
+/// Inserts a manifest row or, if the repository already has one for `filename`, only updates its display name. Keeps `id` and `enabled` of existing rows.
+pub fn upsert_manifest(app: &AppHandle, id: String, repository_id: String, display_name: &str, filename: &str) -> Result<bool, DbError> {
+    execute(app, query("INSERT INTO manifest(id, repository_id, display_name, filename, enabled) VALUES ($1, $2, $3, $4, true) ON CONFLICT(repository_id, filename) DO UPDATE SET display_name = excluded.display_name").bind(id).bind(repository_id).bind(display_name).bind(filename))
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+pub fn delete_manifest_by_id(app: &AppHandle, id: String) -> Result<bool, DbError> {
+    execute(app, query("DELETE FROM manifest WHERE id = $1").bind(id))
+}
 
-        rslt = db.execute(format!("INSERT INTO manifest(id, repository_id, display_name, filename, enabled) VALUES ('{id}', '{repository_id}', '{display_name}', '{filename}', {enabled})").as_str()).await.unwrap();
-    });
+pub fn get_manifest_info_by_id(app: &AppHandle, id: String) -> Result<Option<LauncherManifest>, DbError> {
+    fetch_optional_as(app, query("SELECT * FROM manifest WHERE id = $1").bind(id), manifest_from_row)
+}
 
-    if rslt.rows_affected() >= 1 {
-        Ok(true)
-    } else {
-        Ok(false)
-    }
+pub fn get_manifest_info_by_repository_and_filename(app: &AppHandle, repository_id: String, filename: String) -> Result<Option<LauncherManifest>, DbError> {
+    fetch_optional_as(app, query("SELECT * FROM manifest WHERE repository_id = $1 AND filename = $2").bind(repository_id).bind(filename), manifest_from_row)
 }
 
-pub fn delete_manifest_by_repository_id(app: &AppHandle, repository_id: String) -> Result<bool, Error> {
-    let mut rslt = SqliteQueryResult::default();
+pub fn get_manifests_info(app: &AppHandle) -> Result<Vec<LauncherManifest>, DbError> {
+    fetch_all_as(app, query("SELECT * FROM manifest"), manifest_from_row)
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+pub fn get_manifests_by_filename(app: &AppHandle, filename: String) -> Result<Vec<LauncherManifest>, DbError> {
+    fetch_all_as(app, query("SELECT * FROM manifest WHERE filename = $1").bind(filename), manifest_from_row)
+}
 
-        let query = query("DELETE FROM manifest WHERE repository_id = $1").bind(repository_id);
-        rslt = query.execute(&db).await.unwrap();
-    });
+pub fn get_manifests_by_repository_id(app: &AppHandle, repository_id: String) -> Result<Vec<LauncherManifest>, DbError> {
+    fetch_all_as(app, query("SELECT * FROM manifest WHERE repository_id = $1").bind(repository_id), manifest_from_row)
+}
 
-    if rslt.rows_affected() >= 1 {
-        Ok(true)
-    } else {
-        Ok(false)
-    }
+pub fn update_manifest_enabled_by_id(app: &AppHandle, id: String, enabled: bool) -> Result<bool, DbError> {
+    execute(app, query("UPDATE manifest SET 'enabled' = $1 WHERE id = $2").bind(enabled).bind(id))
 }
 
-pub fn delete_manifest_by_id(app: &AppHandle, id: String) -> Result<bool, Error> {
-    let mut rslt = SqliteQueryResult::default();
+fn manifest_from_row(r: &SqliteRow) -> Result<LauncherManifest, Error> {
+    Ok(LauncherManifest {
+        id: r.try_get("id")?,
+        repository_id: r.try_get("repository_id")?,
+        display_name: r.try_get("display_name")?,
+        filename: r.try_get("filename")?,
+        enabled: r.try_get("enabled")?
+    })
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+// === INSTALLS ===
 
-        let query = query("DELETE FROM manifest WHERE id = $1").bind(id);
-        rslt = query.execute(&db).await.unwrap();
-    });
+/// Installs with their environment variables folded into `env_vars_json`, so reading an install stays a single query.
+const INSTALL_SELECT: &str = "SELECT install.*, (SELECT json_group_array(json_object('key', e.key, 'value', e.value, 'enabled', json(CASE WHEN e.enabled THEN 'true' ELSE 'false' END))) FROM (SELECT * FROM install_env_var WHERE install_id = install.id ORDER BY rowid) e) AS env_vars_json FROM install";
 
-    if rslt.rows_affected() >= 1 {
-        Ok(true)
-    } else {
-        Ok(false)
-    }
-}
+pub fn create_installation(app: &AppHandle, id: String, manifest_id: String, version: String, audio_langs: String, name: String, directory: String, runner_path: String, dxvk_path: String, runner_version: String, dxvk_version: String, game_icon: String, game_background: String, ignore_updates: bool, skip_hash_check: bool, use_jadeite: bool, use_xxmi: bool, use_fps_unlock: bool, env_vars: &[InstallEnvVar], pre_launch_command: String, launch_command: String, fps_value: String, runner_prefix_path: String, launch_args: &[String]) -> Result<bool, DbError> {
+    run_async_command(async {
+        let db = pool(app).await?;
+        let mut tx = db.begin().await?;
 
-pub fn get_manifest_info_by_id(app: &AppHandle, id: String) -> Option<LauncherManifest> {
-    let mut rslt = vec![];
+        let rslt = insert_install(&mut tx, &LauncherInstall { id: id.clone(), manifest_id, version, audio_langs, name, directory, runner_path, dxvk_path, runner_version, dxvk_version, game_icon, game_background, ignore_updates, skip_hash_check, use_jadeite, use_xxmi, use_fps_unlock, env_vars: env_vars.to_vec(), pre_launch_command, launch_command, fps_value, runner_prefix: runner_prefix_path, launch_args: launch_args.to_vec() }).await?;
+        query("INSERT INTO launch_profile(id, install_id, name, is_default) VALUES ($1, $2, 'Default', 1)").bind(generate_cuid()).bind(id.clone()).execute(&mut *tx).await?;
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
-
-        let query = query("SELECT * FROM manifest WHERE id = $1").bind(id);
-        rslt = query.fetch_all(&db).await.unwrap();
-    });
-
-    if rslt.len() >= 1 {
-        let rsltt = LauncherManifest {
-            id: rslt.get(0).unwrap().get("id"),
-            repository_id: rslt.get(0).unwrap().get("repository_id"),
-            display_name: rslt.get(0).unwrap().get("display_name"),
-            filename: rslt.get(0).unwrap().get("filename"),
-            enabled: rslt.get(0).unwrap().get("enabled")
-        };
+        tx.commit().await?;
+        Ok(rslt)
+    })
+}
 
-        Some(rsltt)
-    } else {
-        None
+/// Inserts the install row and its environment variables, the caller adds the launch profiles.
+async fn insert_install(conn: &mut SqliteConnection, i: &LauncherInstall) -> Result<bool, DbError> {
+    // Legacy env_vars and launch_args columns stay empty, they are only filled again by the down migration
+    let rslt = query("INSERT INTO install(id, manifest_id, version, name, directory, runner_path, dxvk_path, runner_version, dxvk_version, game_icon, game_background, ignore_updates, skip_hash_check, use_jadeite, use_xxmi, use_fps_unlock, env_vars, pre_launch_command, launch_command, fps_value, runner_prefix_path, launch_args, audio_langs, launch_argv) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, '', $17, $18, $19, $20, '', $21, $22)").bind(i.id.clone()).bind(i.manifest_id.clone()).bind(i.version.clone()).bind(i.name.clone()).bind(i.directory.clone()).bind(i.runner_path.clone()).bind(i.dxvk_path.clone()).bind(i.runner_version.clone()).bind(i.dxvk_version.clone()).bind(i.game_icon.clone()).bind(i.game_background.clone()).bind(i.ignore_updates).bind(i.skip_hash_check).bind(i.use_jadeite).bind(i.use_xxmi).bind(i.use_fps_unlock).bind(i.pre_launch_command.clone()).bind(i.launch_command.clone()).bind(i.fps_value.clone()).bind(i.runner_prefix.clone()).bind(i.audio_langs.clone()).bind(serde_json::to_string(&i.launch_args).unwrap()).execute(&mut *conn).await?;
+    for e in &i.env_vars {
+        query("INSERT INTO install_env_var(install_id, key, value, enabled) VALUES ($1, $2, $3, $4)").bind(i.id.clone()).bind(e.key.clone()).bind(e.value.clone()).bind(e.enabled).execute(&mut *conn).await?;
     }
+    Ok(rslt.rows_affected() >= 1)
 }
 
-pub fn get_manifest_info_by_filename(app: &AppHandle, filename: String) -> Option<LauncherManifest> {
-    let mut rslt = vec![];
-
+/// Writes everything an import of launcher state changes as one transaction, so a failure leaves the database as it was.
+/// `manifests` are `(id, enabled)` pairs, adopted installs keep their exported profiles when those include a default one.
+pub fn import_state_rows(app: &AppHandle, settings: &GlobalSettings, manifests: &[(String, bool)], installs: &[LauncherInstall], profiles: &[LaunchProfile]) -> Result<bool, DbError> {
     run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
-
-        let query = query("SELECT * FROM manifest WHERE filename = $1").bind(filename);
-        rslt = query.fetch_all(&db).await.unwrap();
-    });
-
-    if rslt.len() >= 1 {
-        let rsltt = LauncherManifest {
-            id: rslt.get(0).unwrap().get("id"),
-            repository_id: rslt.get(0).unwrap().get("repository_id"),
-            display_name: rslt.get(0).unwrap().get("display_name"),
-            filename: rslt.get(0).unwrap().get("filename"),
-            enabled: rslt.get(0).unwrap().get("enabled")
-        };
+        let db = pool(app).await?;
+        let mut tx = db.begin().await?;
 
-        Some(rsltt)
-    } else {
-        None
-    }
+        update_settings_query(settings).execute(&mut *tx).await?;
+        for (id, enabled) in manifests {
+            query("UPDATE manifest SET 'enabled' = $1 WHERE id = $2").bind(enabled).bind(id.clone()).execute(&mut *tx).await?;
+        }
+        for i in installs {
+            insert_install(&mut tx, i).await?;
+            let own = profiles.iter().filter(|p| p.install_id == i.id).collect::<Vec<&LaunchProfile>>();
+            if own.iter().any(|p| p.is_default) {
+                for p in own { insert_launch_profile(&mut tx, i.id.as_str(), p).await?; }
+            } else {
+                query("INSERT INTO launch_profile(id, install_id, name, is_default) VALUES ($1, $2, 'Default', 1)").bind(generate_cuid()).bind(i.id.clone()).execute(&mut *tx).await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    })
 }
 
-pub fn get_manifests_by_repository_id(app: &AppHandle, repository_id: String) -> Option<Vec<LauncherManifest>> {
-    let mut rslt = vec![];
+pub fn delete_installation_by_id(app: &AppHandle, id: String) -> Result<bool, DbError> {
+    execute(app, query("DELETE FROM install WHERE id = $1").bind(id))
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
-
-        let query = query("SELECT * FROM manifest WHERE repository_id = $1").bind(repository_id);
-        rslt = query.fetch_all(&db).await.unwrap();
-    });
-
-    if rslt.len() >= 1 {
-        let mut rsltt = Vec::<LauncherManifest>::new();
-        for r in rslt {
-            rsltt.push(LauncherManifest {
-                id: r.get("id"),
-                repository_id: r.get("repository_id"),
-                display_name: r.get("display_name"),
-                filename: r.get("filename"),
-                enabled: r.get("enabled")
-            })
-        }
+pub fn get_install_info_by_id(app: &AppHandle, id: String) -> Result<Option<LauncherInstall>, DbError> {
+    let sql = format!("{} WHERE id = $1", INSTALL_SELECT);
+    fetch_optional_as(app, query(sql.as_str()).bind(id), install_from_row)
+}
 
-        Some(rsltt)
-    } else {
-        None
-    }
+pub fn get_installs_by_manifest_id(app: &AppHandle, manifest_id: String) -> Result<Vec<LauncherInstall>, DbError> {
+    let sql = format!("{} WHERE manifest_id = $1", INSTALL_SELECT);
+    fetch_all_as(app, query(sql.as_str()).bind(manifest_id), install_from_row)
 }
 
-pub fn update_manifest_enabled_by_id(app: &AppHandle, id: String, enabled: bool) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+pub fn get_installs(app: &AppHandle) -> Result<Vec<LauncherInstall>, DbError> {
+    fetch_all_as(app, query(INSTALL_SELECT), install_from_row)
+}
 
-        let query = query("UPDATE manifest SET 'enabled' = $1 WHERE id = $2").bind(enabled).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+pub fn update_install_game_location_by_id(app: &AppHandle, id: String, location: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'directory' = $1 WHERE id = $2").bind(location).bind(id))
 }
 
-// === INSTALLS ===
+pub fn update_install_runner_location_by_id(app: &AppHandle, id: String, location: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'runner_path' = $1 WHERE id = $2").bind(location).bind(id))
+}
 
-pub fn create_installation(app: &AppHandle, id: String, manifest_id: String, version: String, audio_langs: String, name: String, directory: String, runner_path: String, dxvk_path: String, runner_version: String, dxvk_version: String, game_icon: String, game_background: String, ignore_updates: bool, skip_hash_check: bool, use_jadeite: bool, use_xxmi: bool, use_fps_unlock: bool, env_vars: String, pre_launch_command: String, launch_command: String, fps_value: String, runner_prefix_path: String, launch_args: String) -> Result<bool, Error> {
-    let mut rslt = SqliteQueryResult::default();
+pub fn update_install_dxvk_location_by_id(app: &AppHandle, id: String, location: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'dxvk_path' = $1 WHERE id = $2").bind(location).bind(id))
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+pub fn update_install_ignore_updates_by_id(app: &AppHandle, id: String, enabled: bool) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'ignore_updates' = $1 WHERE id = $2").bind(enabled).bind(id))
+}
 
-        let query = query("INSERT INTO install(id, manifest_id, version, name, directory, runner_path, dxvk_path, runner_version, dxvk_version, game_icon, game_background, ignore_updates, skip_hash_check, use_jadeite, use_xxmi, use_fps_unlock, env_vars, pre_launch_command, launch_command, fps_value, runner_prefix_path, launch_args, audio_langs) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23)").bind(id).bind(manifest_id).bind(version).bind(name).bind(directory).bind(runner_path).bind(dxvk_path).bind(runner_version).bind(dxvk_version).bind(game_icon).bind(game_background).bind(ignore_updates).bind(skip_hash_check).bind(use_jadeite).bind(use_xxmi).bind(use_fps_unlock).bind(env_vars).bind(pre_launch_command).bind(launch_command).bind(fps_value).bind(runner_prefix_path).bind(launch_args).bind(audio_langs);
-        rslt = query.execute(&db).await.unwrap();
-    });
+pub fn update_install_skip_hash_check_by_id(app: &AppHandle, id: String, enabled: bool) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'skip_hash_check' = $1 WHERE id = $2").bind(enabled).bind(id))
+}
 
-    if rslt.rows_affected() >= 1 {
-        Ok(true)
-    } else {
-        Ok(false)
-    }
+pub fn update_install_use_jadeite_by_id(app: &AppHandle, id: String, enabled: bool) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'use_jadeite' = $1 WHERE id = $2").bind(enabled).bind(id))
 }
 
-pub fn delete_installation_by_id(app: &AppHandle, id: String) -> Result<bool, Error> {
-    let mut rslt = SqliteQueryResult::default();
+pub fn update_install_use_xxmi_by_id(app: &AppHandle, id: String, enabled: bool) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'use_xxmi' = $1 WHERE id = $2").bind(enabled).bind(id))
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+pub fn update_install_use_fps_unlock_by_id(app: &AppHandle, id: String, enabled: bool) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'use_fps_unlock' = $1 WHERE id = $2").bind(enabled).bind(id))
+}
 
-        let query = query("DELETE FROM install WHERE id = $1").bind(id);
-        rslt = query.execute(&db).await.unwrap();
-    });
+pub fn update_install_fps_value_by_id(app: &AppHandle, id: String, fps: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'fps_value' = $1 WHERE id = $2").bind(fps).bind(id))
+}
 
-    if rslt.rows_affected() >= 1 {
-        Ok(true)
-    } else {
-        Ok(false)
-    }
+/// Adds a variable or changes the value of an existing one, either way it ends up enabled.
+pub fn set_install_env_var_by_id(app: &AppHandle, id: String, key: String, value: String) -> Result<bool, DbError> {
+    execute(app, query("INSERT INTO install_env_var(install_id, key, value, enabled) VALUES ($1, $2, $3, 1) ON CONFLICT(install_id, key) DO UPDATE SET value = excluded.value, enabled = 1").bind(id).bind(key).bind(value))
 }
 
-pub fn get_install_info_by_id(app: &AppHandle, id: String) -> Option<LauncherInstall> {
-    let mut rslt = vec![];
+pub fn delete_install_env_var_by_id(app: &AppHandle, id: String, key: String) -> Result<bool, DbError> {
+    execute(app, query("DELETE FROM install_env_var WHERE install_id = $1 AND key = $2").bind(id).bind(key))
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
-
-        let query = query("SELECT * FROM install WHERE id = $1").bind(id);
-        rslt = query.fetch_all(&db).await.unwrap();
-    });
-
-    if rslt.len() >= 1 {
-        let rsltt = LauncherInstall {
-            id: rslt.get(0).unwrap().get("id"),
-            manifest_id: rslt.get(0).unwrap().get("manifest_id"),
-            version: rslt.get(0).unwrap().get("version"),
-            audio_langs: rslt.get(0).unwrap().get("audio_langs"),
-            name: rslt.get(0).unwrap().get("name"),
-            directory: rslt.get(0).unwrap().get("directory"),
-            runner_path: rslt.get(0).unwrap().get("runner_path"),
-            dxvk_path: rslt.get(0).unwrap().get("dxvk_path"),
-            runner_version: rslt.get(0).unwrap().get("runner_version"),
-            dxvk_version: rslt.get(0).unwrap().get("dxvk_version"),
-            game_icon: rslt.get(0).unwrap().get("game_icon"),
-            game_background: rslt.get(0).unwrap().get("game_background"),
-            ignore_updates: rslt.get(0).unwrap().get("ignore_updates"),
-            skip_hash_check: rslt.get(0).unwrap().get("skip_hash_check"),
-            use_jadeite: rslt.get(0).unwrap().get("use_jadeite"),
-            use_xxmi: rslt.get(0).unwrap().get("use_xxmi"),
-            use_fps_unlock: rslt.get(0).unwrap().get("use_fps_unlock"),
-            env_vars: rslt.get(0).unwrap().get("env_vars"),
-            pre_launch_command: rslt.get(0).unwrap().get("pre_launch_command"),
-            launch_command: rslt.get(0).unwrap().get("launch_command"),
-            fps_value: rslt.get(0).unwrap().get("fps_value"),
-            runner_prefix: rslt.get(0).unwrap().get("runner_prefix_path"),
-            launch_args: rslt.get(0).unwrap().get("launch_args")
-        };
+pub fn update_install_env_var_enabled_by_id(app: &AppHandle, id: String, key: String, enabled: bool) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install_env_var SET 'enabled' = $1 WHERE install_id = $2 AND key = $3").bind(enabled).bind(id).bind(key))
+}
 
-        Some(rsltt)
-    } else {
-        None
-    }
+pub fn update_install_pre_launch_cmd_by_id(app: &AppHandle, id: String, cmd: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'pre_launch_command' = $1 WHERE id = $2").bind(cmd).bind(id))
 }
 
-pub fn get_installs_by_manifest_id(app: &AppHandle, manifest_id: String) -> Option<Vec<LauncherInstall>> {
-    let mut rslt = vec![];
+pub fn update_install_launch_cmd_by_id(app: &AppHandle, id: String, cmd: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'launch_command' = $1 WHERE id = $2").bind(cmd).bind(id))
+}
 
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
-
-        let query = query("SELECT * FROM install WHERE manifest_id = $1").bind(manifest_id);
-        rslt = query.fetch_all(&db).await.unwrap();
-    });
-
-    if rslt.len() >= 1 {
-        let mut rsltt = Vec::<LauncherInstall>::new();
-        for r in rslt {
-            rsltt.push(LauncherInstall {
-                id: r.get("id"),
-                manifest_id: r.get("manifest_id"),
-                version: r.get("version"),
-                audio_langs: r.get("audio_langs"),
-                name: r.get("name"),
-                directory: r.get("directory"),
-                runner_path: r.get("runner_path"),
-                dxvk_path: r.get("dxvk_path"),
-                runner_version: r.get("runner_version"),
-                dxvk_version: r.get("dxvk_version"),
-                game_icon: r.get("game_icon"),
-                game_background: r.get("game_background"),
-                ignore_updates: r.get("ignore_updates"),
-                skip_hash_check: r.get("skip_hash_check"),
-                use_jadeite: r.get("use_jadeite"),
-                use_xxmi: r.get("use_xxmi"),
-                use_fps_unlock: r.get("use_fps_unlock"),
-                env_vars: r.get("env_vars"),
-                pre_launch_command: r.get("pre_launch_command"),
-                launch_command: r.get("launch_command"),
-                fps_value: r.get("fps_value"),
-                runner_prefix: r.get("runner_prefix_path"),
-                launch_args: r.get("launch_args")
-            })
-        }
+pub fn update_install_prefix_location_by_id(app: &AppHandle, id: String, location: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'runner_prefix_path' = $1 WHERE id = $2").bind(location).bind(id))
+}
 
-        Some(rsltt)
-    } else {
-        None
-    }
+pub fn update_install_launch_args_by_id(app: &AppHandle, id: String, args: &[String]) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'launch_argv' = $1 WHERE id = $2").bind(serde_json::to_string(args).unwrap()).bind(id))
+}
+
+pub fn update_install_runner_version_by_id(app: &AppHandle, id: String, version: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'runner_version' = $1 WHERE id = $2").bind(version).bind(id))
 }
 
-pub fn get_installs(app: &AppHandle) -> Option<Vec<LauncherInstall>> {
-    let mut rslt = vec![];
+pub fn update_install_dxvk_version_by_id(app: &AppHandle, id: String, version: String) -> Result<bool, DbError> {
+    execute(app, query("UPDATE install SET 'dxvk_version' = $1 WHERE id = $2").bind(version).bind(id))
+}
 
+/// Applies everything a finished game update changes in one transaction, a crash midway leaves the old row untouched.
+pub fn update_install_after_update_by_id(app: &AppHandle, id: String, name: String, icon: String, background: String, version: String, dir: String, prefix: String) -> Result<bool, DbError> {
     run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
-
-        let query = query("SELECT * FROM install");
-        rslt = query.fetch_all(&db).await.unwrap();
-    });
-
-    if rslt.len() >= 1 {
-        let mut rsltt = Vec::<LauncherInstall>::new();
-        for r in rslt {
-            rsltt.push(LauncherInstall {
-                id: r.get("id"),
-                manifest_id: r.get("manifest_id"),
-                version: r.get("version"),
-                audio_langs: r.get("audio_langs"),
-                name: r.get("name"),
-                directory: r.get("directory"),
-                runner_path: r.get("runner_path"),
-                dxvk_path: r.get("dxvk_path"),
-                runner_version: r.get("runner_version"),
-                dxvk_version: r.get("dxvk_version"),
-                game_icon: r.get("game_icon"),
-                game_background: r.get("game_background"),
-                ignore_updates: r.get("ignore_updates"),
-                skip_hash_check: r.get("skip_hash_check"),
-                use_jadeite: r.get("use_jadeite"),
-                use_xxmi: r.get("use_xxmi"),
-                use_fps_unlock: r.get("use_fps_unlock"),
-                env_vars: r.get("env_vars"),
-                pre_launch_command: r.get("pre_launch_command"),
-                launch_command: r.get("launch_command"),
-                fps_value: r.get("fps_value"),
-                runner_prefix: r.get("runner_prefix_path"),
-                launch_args: r.get("launch_args")
-            })
-        }
+        let db = pool(app).await?;
+        let mut tx = db.begin().await?;
+
+        let rslt = query("UPDATE install SET 'name' = $1 WHERE id = $2").bind(name).bind(id.clone()).execute(&mut *tx).await?;
+        query("UPDATE install SET 'game_icon' = $1 WHERE id = $2").bind(icon).bind(id.clone()).execute(&mut *tx).await?;
+        query("UPDATE install SET 'game_background' = $1 WHERE id = $2").bind(background).bind(id.clone()).execute(&mut *tx).await?;
+        query("UPDATE install SET 'version' = $1 WHERE id = $2").bind(version).bind(id.clone()).execute(&mut *tx).await?;
+        query("UPDATE install SET 'directory' = $1 WHERE id = $2").bind(dir).bind(id.clone()).execute(&mut *tx).await?;
+        query("UPDATE install SET 'runner_prefix_path' = $1 WHERE id = $2").bind(prefix).bind(id).execute(&mut *tx).await?;
+
+        tx.commit().await?;
+        Ok(rslt.rows_affected() >= 1)
+    })
+}
 
-        Some(rsltt)
-    } else {
-        None
-    }
+fn install_from_row(r: &SqliteRow) -> Result<LauncherInstall, Error> {
+    Ok(LauncherInstall {
+        id: r.try_get("id")?,
+        manifest_id: r.try_get("manifest_id")?,
+        version: r.try_get("version")?,
+        audio_langs: r.try_get("audio_langs")?,
+        name: r.try_get("name")?,
+        directory: r.try_get("directory")?,
+        runner_path: r.try_get("runner_path")?,
+        dxvk_path: r.try_get("dxvk_path")?,
+        runner_version: r.try_get("runner_version")?,
+        dxvk_version: r.try_get("dxvk_version")?,
+        game_icon: r.try_get("game_icon")?,
+        game_background: r.try_get("game_background")?,
+        ignore_updates: r.try_get("ignore_updates")?,
+        skip_hash_check: r.try_get("skip_hash_check")?,
+        use_jadeite: r.try_get("use_jadeite")?,
+        use_xxmi: r.try_get("use_xxmi")?,
+        use_fps_unlock: r.try_get("use_fps_unlock")?,
+        env_vars: json_column(r, "env_vars_json")?,
+        pre_launch_command: r.try_get("pre_launch_command")?,
+        launch_command: r.try_get("launch_command")?,
+        fps_value: r.try_get("fps_value")?,
+        runner_prefix: r.try_get("runner_prefix_path")?,
+        launch_args: json_column(r, "launch_argv")?
+    })
 }
 
-pub fn update_install_game_location_by_id(app: &AppHandle, id: String, location: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+fn json_column<T: serde::de::DeserializeOwned>(r: &SqliteRow, column: &str) -> Result<T, Error> {
+    let raw: String = r.try_get(column)?;
+    serde_json::from_str(raw.as_str()).map_err(|e| Error::ColumnDecode { index: column.to_string(), source: Box::new(e) })
+}
 
-        let query = query("UPDATE install SET 'directory' = $1 WHERE id = $2").bind(location).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+fn optional_json_column<T: serde::de::DeserializeOwned>(r: &SqliteRow, column: &str) -> Result<Option<T>, Error> {
+    let raw: Option<String> = r.try_get(column)?;
+    raw.map(|v| serde_json::from_str(v.as_str()).map_err(|e| Error::ColumnDecode { index: column.to_string(), source: Box::new(e) })).transpose()
 }
 
-pub fn update_install_runner_location_by_id(app: &AppHandle, id: String, location: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+// === LAUNCH PROFILES ===
 
-        let query = query("UPDATE install SET 'runner_path' = $1 WHERE id = $2").bind(location).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+pub fn create_launch_profile(app: &AppHandle, id: String, install_id: String, name: String) -> Result<bool, DbError> {
+    execute(app, query("INSERT INTO launch_profile(id, install_id, name, is_default) VALUES ($1, $2, $3, 0)").bind(id).bind(install_id).bind(name))
 }
 
-pub fn update_install_dxvk_location_by_id(app: &AppHandle, id: String, location: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+/// Writes the name and every override of a profile, the install it belongs to and its default flag are left alone.
+pub fn update_launch_profile_by_id(app: &AppHandle, profile: &LaunchProfile) -> Result<bool, DbError> {
+    execute(app, query("UPDATE launch_profile SET 'name' = $1, 'use_xxmi' = $2, 'use_fps_unlock' = $3, 'fps_value' = $4, 'env_vars' = $5, 'launch_args' = $6, 'runner_version' = $7 WHERE id = $8").bind(profile.name.clone()).bind(profile.use_xxmi).bind(profile.use_fps_unlock).bind(profile.fps_value.clone()).bind(profile.env_vars.as_ref().map(|e| serde_json::to_string(e).unwrap())).bind(profile.launch_args.as_ref().map(|a| serde_json::to_string(a).unwrap())).bind(profile.runner_version.clone()).bind(profile.id.clone()))
+}
 
-        let query = query("UPDATE install SET 'dxvk_path' = $1 WHERE id = $2").bind(location).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+pub fn delete_launch_profile_by_id(app: &AppHandle, id: String) -> Result<bool, DbError> {
+    execute(app, query("DELETE FROM launch_profile WHERE id = $1 AND is_default = 0").bind(id))
 }
 
-pub fn update_install_ignore_updates_by_id(app: &AppHandle, id: String, enabled: bool) {
+/// Moves the default flag of an install to `id`.
+pub fn set_default_launch_profile_by_id(app: &AppHandle, install_id: String, id: String) -> Result<bool, DbError> {
     run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+        let db = pool(app).await?;
+        let mut tx = db.begin().await?;
+
+        query("UPDATE launch_profile SET 'is_default' = 0 WHERE install_id = $1").bind(install_id.clone()).execute(&mut *tx).await?;
+        let rslt = query("UPDATE launch_profile SET 'is_default' = 1 WHERE id = $1 AND install_id = $2").bind(id).bind(install_id).execute(&mut *tx).await?;
 
-        let query = query("UPDATE install SET 'ignore_updates' = $1 WHERE id = $2").bind(enabled).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+        // Never leave an install without a default
+        if rslt.rows_affected() == 0 { tx.rollback().await?; return Ok(false); }
+        tx.commit().await?;
+        Ok(true)
+    })
 }
 
-pub fn update_install_skip_hash_check_by_id(app: &AppHandle, id: String, enabled: bool) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+async fn insert_launch_profile(conn: &mut SqliteConnection, install_id: &str, p: &LaunchProfile) -> Result<(), DbError> {
+    query("INSERT INTO launch_profile(id, install_id, name, is_default, use_xxmi, use_fps_unlock, fps_value, env_vars, launch_args, runner_version) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)").bind(p.id.clone()).bind(install_id).bind(p.name.clone()).bind(p.is_default).bind(p.use_xxmi).bind(p.use_fps_unlock).bind(p.fps_value.clone()).bind(p.env_vars.as_ref().map(|e| serde_json::to_string(e).unwrap())).bind(p.launch_args.as_ref().map(|a| serde_json::to_string(a).unwrap())).bind(p.runner_version.clone()).execute(&mut *conn).await?;
+    Ok(())
+}
 
-        let query = query("UPDATE install SET 'skip_hash_check' = $1 WHERE id = $2").bind(enabled).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+pub fn get_launch_profile_by_id(app: &AppHandle, id: String) -> Result<Option<LaunchProfile>, DbError> {
+    fetch_optional_as(app, query("SELECT * FROM launch_profile WHERE id = $1").bind(id), launch_profile_from_row)
 }
 
-pub fn update_install_use_jadeite_by_id(app: &AppHandle, id: String, enabled: bool) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+pub fn get_default_launch_profile_by_install_id(app: &AppHandle, install_id: String) -> Result<Option<LaunchProfile>, DbError> {
+    fetch_optional_as(app, query("SELECT * FROM launch_profile WHERE install_id = $1 AND is_default = 1").bind(install_id), launch_profile_from_row)
+}
 
-        let query = query("UPDATE install SET 'use_jadeite' = $1 WHERE id = $2").bind(enabled).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+pub fn get_launch_profiles_by_install_id(app: &AppHandle, install_id: String) -> Result<Vec<LaunchProfile>, DbError> {
+    fetch_all_as(app, query("SELECT * FROM launch_profile WHERE install_id = $1 ORDER BY is_default DESC, name").bind(install_id), launch_profile_from_row)
 }
 
-pub fn update_install_use_xxmi_by_id(app: &AppHandle, id: String, enabled: bool) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+fn launch_profile_from_row(r: &SqliteRow) -> Result<LaunchProfile, Error> {
+    Ok(LaunchProfile {
+        id: r.try_get("id")?,
+        install_id: r.try_get("install_id")?,
+        name: r.try_get("name")?,
+        is_default: r.try_get("is_default")?,
+        use_xxmi: r.try_get("use_xxmi")?,
+        use_fps_unlock: r.try_get("use_fps_unlock")?,
+        fps_value: r.try_get("fps_value")?,
+        env_vars: optional_json_column(r, "env_vars")?,
+        launch_args: optional_json_column(r, "launch_args")?,
+        runner_version: r.try_get("runner_version")?
+    })
+}
+
+// === EXTRAS ===
 
-        let query = query("UPDATE install SET 'use_xxmi' = $1 WHERE id = $2").bind(enabled).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+pub fn get_installed_extra_by_id(app: &AppHandle, id: String) -> Result<Option<LauncherInstalledExtra>, DbError> {
+    fetch_optional_as(app, query("SELECT * FROM installed_extra WHERE id = $1").bind(id), installed_extra_from_row)
 }
 
-pub fn update_install_use_fps_unlock_by_id(app: &AppHandle, id: String, enabled: bool) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+fn installed_extra_from_row(r: &SqliteRow) -> Result<LauncherInstalledExtra, Error> {
+    Ok(LauncherInstalledExtra {
+        id: r.try_get("id")?,
+        manifest_id: r.try_get("manifest_id")?,
+        version: r.try_get("version")?,
+        path: r.try_get("path")?,
+        installed_at: r.try_get("installed_at")?,
+    })
+}
 
-        let query = query("UPDATE install SET 'use_fps_unlock' = $1 WHERE id = $2").bind(enabled).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+pub fn set_installed_extra(app: &AppHandle, extra: &LauncherInstalledExtra) -> Result<bool, DbError> {
+    execute(app, query("INSERT OR REPLACE INTO installed_extra(id, manifest_id, version, path, installed_at) VALUES ($1, $2, $3, $4, $5)").bind(extra.id.clone()).bind(extra.manifest_id.clone()).bind(extra.version.clone()).bind(extra.path.clone()).bind(extra.installed_at))
 }
 
-pub fn update_install_fps_value_by_id(app: &AppHandle, id: String, fps: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+// === QUERY HELPERS ===
 
-        let query = query("UPDATE install SET 'fps_value' = $1 WHERE id = $2").bind(fps).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+async fn pool(app: &AppHandle) -> Result<Pool<Sqlite>, DbError> {
+    let instances = app.try_state::<DbInstances>().ok_or(DbError::Unavailable("Database is not initialized yet".to_string()))?;
+    let db = instances.0.lock().await.get("db").cloned();
+    db.ok_or(DbError::Unavailable("Database is not initialized yet".to_string()))
 }
 
-pub fn update_install_env_vars_by_id(app: &AppHandle, id: String, env_vars: String) {
+/// Runs a single statement, returns whether it touched any row.
+fn execute<'q>(app: &AppHandle, query: Query<'q, Sqlite, SqliteArguments<'q>>) -> Result<bool, DbError> {
     run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+        let db = pool(app).await?;
+        Ok(query.execute(&db).await?.rows_affected() >= 1)
+    })
+}
 
-        let query = query("UPDATE install SET 'env_vars' = $1 WHERE id = $2").bind(env_vars).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+fn fetch_all_as<'q, T>(app: &AppHandle, query: Query<'q, Sqlite, SqliteArguments<'q>>, map: impl Fn(&SqliteRow) -> Result<T, Error>) -> Result<Vec<T>, DbError> {
+    let rows = run_async_command(async {
+        let db = pool(app).await?;
+        Ok::<_, DbError>(query.fetch_all(&db).await?)
+    })?;
+    Ok(rows.iter().map(map).collect::<Result<Vec<T>, Error>>()?)
 }
 
-pub fn update_install_pre_launch_cmd_by_id(app: &AppHandle, id: String, cmd: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+fn fetch_optional_as<'q, T>(app: &AppHandle, query: Query<'q, Sqlite, SqliteArguments<'q>>, map: impl Fn(&SqliteRow) -> Result<T, Error>) -> Result<Option<T>, DbError> {
+    let row = run_async_command(async {
+        let db = pool(app).await?;
+        Ok::<_, DbError>(query.fetch_optional(&db).await?)
+    })?;
+    Ok(row.as_ref().map(map).transpose()?)
+}
 
-        let query = query("UPDATE install SET 'pre_launch_command' = $1 WHERE id = $2").bind(cmd).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+// === DB RELATED ===
+
+/// Schema history, every version has an up and a down migration and versions only ever grow.
+/// Install and settings tables used to be versions 6 and 7 which put them after the row that populates settings,
+/// see [`LEGACY_VERSIONS`] for how existing databases are moved onto the current numbering.
+fn migrations() -> Vec<Migration> {
+    vec![
+        Migration {
+            version: 1,
+            description: "init_repository_table",
+            sql: r#"CREATE TABLE IF NOT EXISTS "repository" ("id" string PRIMARY KEY,"github_id" string);"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 1,
+            description: "init_repository_table",
+            sql: r#"DROP TABLE IF EXISTS repository;"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 2,
+            description: "init_manifest_table",
+            sql: r#"CREATE TABLE IF NOT EXISTS manifest ("id" string PRIMARY KEY, "repository_id" string, "display_name" string, "filename" string, "enabled" bool, CONSTRAINT fk_manifest_repo FOREIGN KEY(repository_id) REFERENCES repository(id));"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 2,
+            description: "init_manifest_table",
+            sql: r#"DROP TABLE IF EXISTS manifest;"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 3,
+            description: "init_install_table",
+            sql: r#"CREATE TABLE IF NOT EXISTS install ("id" TEXT PRIMARY KEY, "manifest_id" TEXT, "version" TEXT, "name" TEXT, "directory" TEXT, "runner_path" TEXT, "dxvk_path" TEXT, "runner_version" TEXT, "dxvk_version" TEXT, "game_icon" TEXT, "game_background" TEXT, "ignore_updates" bool, "skip_hash_check" bool, "use_jadeite" bool, "use_xxmi" bool, "use_fps_unlock" bool, "env_vars" TEXT, "pre_launch_command" TEXT, "launch_command" TEXT, "fps_value" TEXT, "runner_prefix_path" TEXT, "launch_args" TEXT, "audio_langs" TEXT, CONSTRAINT fk_install_manifest FOREIGN KEY(manifest_id) REFERENCES manifest(id));"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 3,
+            description: "init_install_table",
+            sql: r#"DROP TABLE IF EXISTS install;"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 4,
+            description: "init_settings_table",
+            sql: r#"CREATE TABLE IF NOT EXISTS settings ("default_game_path" TEXT default null, "third_party_repo_updates" bool default 0 not null, "xxmi_path" TEXT default null, fps_unlock_path TEXT default null, jadeite_path TEXT default null, default_runner_prefix_path TEXT default null, "launcher_action" TEXT default null, id integer not null CONSTRAINT settings_pk primary key autoincrement, "hide_manifests" bool not null);"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 4,
+            description: "init_settings_table",
+            sql: r#"DROP TABLE IF EXISTS settings;"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 5,
+            description: "populate_settings_table",
+            sql: r#"INSERT INTO settings (default_game_path, third_party_repo_updates, xxmi_path, fps_unlock_path, jadeite_path, default_runner_prefix_path, launcher_action, id, hide_manifests) values (null, false, null, null, null, null, "exit", 1, false);"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 5,
+            description: "populate_settings_table",
+            sql: r#"DELETE FROM settings WHERE id = 1;"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 8,
+            description: "repository_add_remote_and_host",
+            sql: r#"ALTER TABLE repository ADD COLUMN "remote" TEXT default null; ALTER TABLE repository ADD COLUMN "host" TEXT default null; UPDATE repository SET remote = 'https://github.com/' || github_id || '.git', host = 'github.com' WHERE remote IS NULL;"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 8,
+            description: "repository_add_remote_and_host",
+            sql: r#"ALTER TABLE repository DROP COLUMN "remote"; ALTER TABLE repository DROP COLUMN "host";"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 9,
+            description: "repository_add_tracked_ref",
+            sql: r#"ALTER TABLE repository ADD COLUMN "ref_kind" TEXT default 'branch' not null; ALTER TABLE repository ADD COLUMN "ref_name" TEXT default 'main' not null; ALTER TABLE repository ADD COLUMN "previous_commit" TEXT default null;"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 9,
+            description: "repository_add_tracked_ref",
+            sql: r#"ALTER TABLE repository DROP COLUMN "ref_kind"; ALTER TABLE repository DROP COLUMN "ref_name"; ALTER TABLE repository DROP COLUMN "previous_commit";"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 10,
+            description: "settings_add_repo_sync_interval",
+            sql: r#"ALTER TABLE settings ADD COLUMN "repo_sync_interval" INTEGER default 60 not null;"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 10,
+            description: "settings_add_repo_sync_interval",
+            sql: r#"ALTER TABLE settings DROP COLUMN "repo_sync_interval";"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 11,
+            description: "init_repository_credentials_table",
+            sql: r#"CREATE TABLE IF NOT EXISTS repository_credentials ("repository_id" TEXT PRIMARY KEY, "kind" TEXT not null, "username" TEXT default null, "secret" TEXT default null, "ssh_key_path" TEXT default null, CONSTRAINT fk_credentials_repo FOREIGN KEY(repository_id) REFERENCES repository(id));"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 11,
+            description: "init_repository_credentials_table",
+            sql: r#"DROP TABLE IF EXISTS repository_credentials;"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 12,
+            description: "repository_add_metadata",
+            sql: r#"ALTER TABLE repository ADD COLUMN "name" TEXT default null; ALTER TABLE repository ADD COLUMN "description" TEXT default null; ALTER TABLE repository ADD COLUMN "maintainers" TEXT default '[]' not null; ALTER TABLE repository ADD COLUMN "homepage" TEXT default null; ALTER TABLE repository ADD COLUMN "icon" TEXT default null; ALTER TABLE repository ADD COLUMN "min_launcher_version" TEXT default null;"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 12,
+            description: "repository_add_metadata",
+            sql: r#"ALTER TABLE repository DROP COLUMN "name"; ALTER TABLE repository DROP COLUMN "description"; ALTER TABLE repository DROP COLUMN "maintainers"; ALTER TABLE repository DROP COLUMN "homepage"; ALTER TABLE repository DROP COLUMN "icon"; ALTER TABLE repository DROP COLUMN "min_launcher_version";"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 13,
+            description: "manifest_unique_per_repository",
+            sql: r#"UPDATE install SET manifest_id = (SELECT k.id FROM manifest k WHERE k.rowid = (SELECT MIN(d.rowid) FROM manifest d JOIN manifest o ON d.repository_id = o.repository_id AND d.filename = o.filename WHERE o.id = install.manifest_id)) WHERE manifest_id IN (SELECT id FROM manifest); DELETE FROM manifest WHERE rowid NOT IN (SELECT MIN(rowid) FROM manifest GROUP BY repository_id, filename); DELETE FROM manifest WHERE repository_id NOT IN (SELECT id FROM repository) AND id NOT IN (SELECT manifest_id FROM install WHERE manifest_id IS NOT NULL); CREATE UNIQUE INDEX IF NOT EXISTS manifest_repository_filename ON manifest(repository_id, filename);"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 13,
+            description: "manifest_unique_per_repository",
+            // Duplicates removed on the way up are gone for good, only the index can be undone
+            sql: r#"DROP INDEX IF EXISTS manifest_repository_filename;"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 14,
+            description: "init_installed_extra_table",
+            sql: r#"CREATE TABLE IF NOT EXISTS installed_extra ("id" TEXT PRIMARY KEY, "manifest_id" TEXT not null, "version" TEXT not null, "path" TEXT not null, "installed_at" INTEGER not null);"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 14,
+            description: "init_installed_extra_table",
+            sql: r#"DROP TABLE IF EXISTS installed_extra;"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 15,
+            description: "repository_add_local_path",
+            sql: r#"ALTER TABLE repository ADD COLUMN "local_path" TEXT default null;"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 15,
+            description: "repository_add_local_path",
+            sql: r#"ALTER TABLE repository DROP COLUMN "local_path";"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 16,
+            description: "repository_add_transport",
+            sql: r#"ALTER TABLE repository ADD COLUMN "transport" TEXT default 'git' not null; UPDATE repository SET transport = 'local' WHERE local_path IS NOT NULL; ALTER TABLE repository ADD COLUMN "etag" TEXT default null; ALTER TABLE repository ADD COLUMN "last_modified" TEXT default null;"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 16,
+            description: "repository_add_transport",
+            sql: r#"ALTER TABLE repository DROP COLUMN "transport"; ALTER TABLE repository DROP COLUMN "etag"; ALTER TABLE repository DROP COLUMN "last_modified";"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 17,
+            description: "init_install_env_var_table",
+            sql: r#"CREATE TABLE IF NOT EXISTS install_env_var ("install_id" TEXT not null, "key" TEXT not null, "value" TEXT not null, "enabled" bool default 1 not null, PRIMARY KEY(install_id, key), CONSTRAINT fk_env_var_install FOREIGN KEY(install_id) REFERENCES install(id) ON DELETE CASCADE); ALTER TABLE install ADD COLUMN "launch_argv" TEXT default '[]' not null;"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 17,
+            description: "init_install_env_var_table",
+            // Folds everything back into the legacy strings, entries containing ";" have no legacy form and are left out instead of splitting into garbage
+            sql: r#"UPDATE install SET env_vars = COALESCE((SELECT group_concat(e.key || '=' || e.value, ';') || ';' FROM install_env_var e WHERE e.install_id = install.id AND e.enabled = 1 AND instr(e.key || e.value, ';') = 0), env_vars), launch_args = COALESCE((SELECT group_concat('''' || replace(j.value, '''', '''\''''') || '''', ' ') FROM json_each(install.launch_argv) j), launch_args); DROP TABLE IF EXISTS install_env_var; ALTER TABLE install DROP COLUMN "launch_argv";"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 18,
+            description: "init_launch_profile_table",
+            // Existing installs get a default profile without overrides so launching them behaves exactly as before
+            sql: r#"CREATE TABLE IF NOT EXISTS launch_profile ("id" TEXT PRIMARY KEY, "install_id" TEXT not null, "name" TEXT not null, "is_default" bool default 0 not null, "use_xxmi" bool default null, "use_fps_unlock" bool default null, "fps_value" TEXT default null, "env_vars" TEXT default null, "launch_args" TEXT default null, "runner_version" TEXT default null, CONSTRAINT fk_profile_install FOREIGN KEY(install_id) REFERENCES install(id) ON DELETE CASCADE); CREATE UNIQUE INDEX IF NOT EXISTS launch_profile_name ON launch_profile(install_id, name); CREATE UNIQUE INDEX IF NOT EXISTS launch_profile_default ON launch_profile(install_id) WHERE is_default = 1; INSERT INTO launch_profile(id, install_id, name, is_default) SELECT lower(hex(randomblob(12))), id, 'Default', 1 FROM install;"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 18,
+            description: "init_launch_profile_table",
+            sql: r#"DROP TABLE IF EXISTS launch_profile;"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 19,
+            description: "repository_normalize_remote",
+            // Matches RepositoryUrl::parse, a remote that would collide with an already normalized one is left for the user to remove
+            sql: r#"UPDATE repository SET remote = remote || '.git' WHERE transport = 'git' AND remote IS NOT NULL AND remote NOT LIKE 'file://%' AND remote NOT LIKE '%.git' AND NOT EXISTS (SELECT 1 FROM repository d WHERE d.remote = repository.remote || '.git');"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 19,
+            description: "repository_normalize_remote",
+            // Older launchers clone the normalized remotes just the same, nothing to undo
+            sql: r#"SELECT 1;"#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 20,
+            description: "repository_credentials_add_secret_ref",
+            // Existing plaintext secrets are moved into the keyring by move_secrets_to_keyring right after migrating
+            sql: r#"ALTER TABLE repository_credentials ADD COLUMN "secret_ref" TEXT default null;"#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 20,
+            description: "repository_credentials_add_secret_ref",
+            // Secrets stay in the keyring, older launchers ask for them again
+            sql: r#"ALTER TABLE repository_credentials DROP COLUMN "secret_ref";"#,
+            kind: MigrationKind::Down,
+        }
+    ]
 }
 
-pub fn update_install_launch_cmd_by_id(app: &AppHandle, id: String, cmd: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+/// (old version, new version, description) of migrations that were renumbered, their SQL and checksums are unchanged.
+const LEGACY_VERSIONS: [(i64, i64, &str); 2] = [(6, 3, "init_install_table"), (7, 4, "init_settings_table")];
+
+/// How many pre-migration snapshots of `storage.db` are kept around.
+const DB_SNAPSHOTS_KEPT: usize = 5;
 
-        let query = query("UPDATE install SET 'launch_command' = $1 WHERE id = $2").bind(cmd).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+/// Versions recorded in the database with legacy numbers already translated, empty for a fresh database.
+async fn applied_versions(pool: &Pool<Sqlite>) -> Result<Vec<i64>, DbError> {
+    let table = query("SELECT name FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'").fetch_optional(pool).await?;
+    if table.is_none() { return Ok(Vec::new()); }
+
+    let rows = query("SELECT version, description FROM _sqlx_migrations WHERE success = 1").fetch_all(pool).await?;
+    rows.iter().map(|r| {
+        let version: i64 = r.try_get("version")?;
+        let description: String = r.try_get("description")?;
+        Ok(LEGACY_VERSIONS.iter().find(|(old, _, d)| *old == version && *d == description).map(|(_, new, _)| *new).unwrap_or(version))
+    }).collect()
 }
 
-pub fn update_install_prefix_location_by_id(app: &AppHandle, id: String, location: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+/// Moves renumbered migrations onto their current version, a no-op once done.
+async fn normalize_migration_history(pool: &Pool<Sqlite>) -> Result<(), DbError> {
+    let mut tx = pool.begin().await?;
+    for (old, new, description) in LEGACY_VERSIONS {
+        query("UPDATE _sqlx_migrations SET version = $1 WHERE version = $2 AND description = $3").bind(new).bind(old).bind(description).execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
 
-        let query = query("UPDATE install SET 'runner_prefix_path' = $1 WHERE id = $2").bind(location).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+/// Puts renumbered migrations back on their legacy version, the reverse of [`normalize_migration_history`].
+/// Launchers that predate version 8 only know the legacy numbering and refuse a history containing 3 and 4.
+async fn restore_legacy_history(pool: &Pool<Sqlite>) -> Result<(), DbError> {
+    let mut tx = pool.begin().await?;
+    for (old, new, description) in LEGACY_VERSIONS {
+        query("UPDATE _sqlx_migrations SET version = $1 WHERE version = $2 AND description = $3").bind(old).bind(new).bind(description).execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+    Ok(())
 }
 
-pub fn update_install_launch_args_by_id(app: &AppHandle, id: String, args: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+/// Copies the database into `backups/` with `VACUUM INTO` so the snapshot is consistent while the pool is open.
+async fn snapshot_db(pool: &Pool<Sqlite>, data_path: &Path, version: i64) -> Result<PathBuf, DbError> {
+    let backups = data_path.join("backups");
+    fs::create_dir_all(&backups).map_err(|e| DbError::Unavailable(e.to_string()))?;
 
-        let query = query("UPDATE install SET 'launch_args' = $1 WHERE id = $2").bind(args).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+    let snapshot = backups.join(format!("storage-v{}-{}.db", version, stamp));
+    query("VACUUM INTO $1").bind(snapshot.to_str().unwrap()).execute(pool).await?;
+
+    let mut snapshots = fs::read_dir(&backups).map(|d| d.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("storage-v") && n.ends_with(".db"))).collect::<Vec<PathBuf>>()).unwrap_or_default();
+    snapshots.sort_by_key(|p| p.metadata().and_then(|m| m.modified()).ok());
+    while snapshots.len() > DB_SNAPSHOTS_KEPT { fs::remove_file(snapshots.remove(0)).ok(); }
+
+    #[cfg(debug_assertions)]
+    { println!("Database snapshot written to {}", snapshot.display()); }
+    Ok(snapshot)
 }
 
-pub fn update_install_runner_version_by_id(app: &AppHandle, id: String, version: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+/// Refuses databases written by a newer launcher, snapshots before anything pending runs and then migrates.
+async fn migrate_db(pool: &Pool<Sqlite>, data_path: &Path) -> Result<(), DbError> {
+    let migrator = Migrator::new(MigrationList(migrations())).await.map_err(|e| DbError::Query(e.to_string()))?;
+    let known = migrator.iter().map(|m| m.version).max().unwrap_or(0);
+    let applied = applied_versions(pool).await?;
 
-        let query = query("UPDATE install SET 'runner_version' = $1 WHERE id = $2").bind(version).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+    if let Some(newest) = applied.iter().max().copied() {
+        if newest > known { return Err(DbError::Incompatible(format!("storage.db is at schema version {} but this launcher only knows up to {}, update the launcher or restore a snapshot from the backups folder", newest, known))); }
+
+        let pending = migrator.iter().any(|m| m.migration_type.is_up_migration() && !applied.contains(&m.version));
+        if pending { snapshot_db(pool, data_path, newest).await?; }
+        normalize_migration_history(pool).await?;
+    }
+
+    let converts_launch_options = !applied.contains(&17);
+    migrator.run(pool).await.map_err(|e| DbError::Query(e.to_string()))?;
+    if converts_launch_options { migrate_legacy_launch_options(pool).await?; }
+    move_secrets_to_keyring(pool).await
 }
 
-pub fn update_install_dxvk_version_by_id(app: &AppHandle, id: String, version: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+/// Moves plaintext secrets written by older launchers into the keyring and clears them from the database.
+/// A keyring that is not reachable leaves the rows alone so the next start tries again, git keeps working with the plaintext secret meanwhile.
+async fn move_secrets_to_keyring(pool: &Pool<Sqlite>) -> Result<(), DbError> {
+    let rows = query("SELECT repository_id, secret FROM repository_credentials WHERE secret IS NOT NULL").fetch_all(pool).await?;
+
+    for r in rows {
+        let repository_id: String = r.try_get("repository_id")?;
+        let secret: String = r.try_get("secret")?;
+
+        match store_secret(repository_id.as_str(), Some(secret.as_str())) {
+            Ok(secret_ref) => { query("UPDATE repository_credentials SET 'secret' = null, 'secret_ref' = $1 WHERE repository_id = $2").bind(secret_ref).bind(repository_id).execute(pool).await?; }
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                { println!("Failed to move secret of repository {} into the keyring: {}", repository_id, _e); }
+                break;
+            }
+        }
+    }
+    Ok(())
+}
 
-        let query = query("UPDATE install SET 'dxvk_version' = $1 WHERE id = $2").bind(version).bind(id);
-        query.execute(&db).await.unwrap();
-    });
+/// Moves `;` joined env vars and space separated launch args into `install_env_var` and `launch_argv`.
+/// Part of migration 17 and only run right after it was applied, done in code since neither format can be parsed reliably in SQL.
+async fn migrate_legacy_launch_options(pool: &Pool<Sqlite>) -> Result<(), DbError> {
+    let rows = query("SELECT id, env_vars, launch_args FROM install WHERE (env_vars IS NOT NULL AND env_vars != '') OR (launch_args IS NOT NULL AND launch_args != '')").fetch_all(pool).await?;
+
+    for r in rows {
+        let id: String = r.try_get("id")?;
+        let env_vars: Option<String> = r.try_get("env_vars")?;
+        let launch_args: Option<String> = r.try_get("launch_args")?;
+
+        let mut tx = pool.begin().await?;
+        for e in parse_legacy_env_vars(env_vars.unwrap_or_default().as_str()) {
+            query("INSERT OR REPLACE INTO install_env_var(install_id, key, value, enabled) VALUES ($1, $2, $3, $4)").bind(id.clone()).bind(e.key).bind(e.value).bind(e.enabled).execute(&mut *tx).await?;
+        }
+        if let Some(args) = launch_args.filter(|a| !a.is_empty()) {
+            query("UPDATE install SET 'launch_argv' = $1 WHERE id = $2").bind(serde_json::to_string(&parse_legacy_launch_args(args.as_str())).unwrap()).bind(id.clone()).execute(&mut *tx).await?;
+        }
+        query("UPDATE install SET 'env_vars' = '', 'launch_args' = '' WHERE id = $1").bind(id.clone()).execute(&mut *tx).await?;
+        tx.commit().await?;
+
+        #[cfg(debug_assertions)]
+        { println!("Converted legacy launch options of install {}", id); }
+    }
+    Ok(())
 }
 
-pub fn update_install_after_update_by_id(app: &AppHandle, id: String, name: String, icon: String, background: String, version: String, dir: String, prefix: String) {
-    run_async_command(async {
-        let db = app.state::<DbInstances>().0.lock().await.get("db").unwrap().clone();
+/// Reverts the schema down to `target` using the down migrations, taking a snapshot first.
+/// Meant for going back to an older launcher, the running launcher can not use the downgraded database afterwards.
+pub async fn downgrade_db(app: &AppHandle, target: i64) -> Result<(), DbError> {
+    let data_path = app.path().app_data_dir().unwrap();
+    let conn_url = data_path.join("storage.db");
+    if !conn_url.exists() { return Err(DbError::Unavailable(format!("{} does not exist", conn_url.display()))); }
+
+    let pool: Pool<Sqlite> = Pool::connect(&conn_url.to_str().unwrap()).await?;
+    let rslt = undo_migrations(&pool, &data_path, target).await;
+    pool.close().await;
+    rslt
+}
 
-        let query = query("UPDATE install SET 'name' = $1 WHERE id = $2").bind(name).bind(id.clone());
-        let query2 = sqlx::query("UPDATE install SET 'game_icon' = $1 WHERE id = $2").bind(icon).bind(id.clone());
-        let query3 = sqlx::query("UPDATE install SET 'game_background' = $1 WHERE id = $2").bind(background).bind(id.clone());
-        let query4 = sqlx::query("UPDATE install SET 'version' = $1 WHERE id = $2").bind(version).bind(id.clone());
-        let query5 = sqlx::query("UPDATE install SET 'directory' = $1 WHERE id = $2").bind(dir).bind(id.clone());
-        let query6 = sqlx::query("UPDATE install SET 'runner_prefix_path' = $1 WHERE id = $2").bind(prefix).bind(id.clone());
+async fn undo_migrations(pool: &Pool<Sqlite>, data_path: &Path, target: i64) -> Result<(), DbError> {
+    let migrator = Migrator::new(MigrationList(migrations())).await.map_err(|e| DbError::Query(e.to_string()))?;
+    let newest = applied_versions(pool).await?.into_iter().max().unwrap_or(0);
+    if newest <= target { return Ok(()); }
 
-        query.execute(&db).await.unwrap();
-        query2.execute(&db).await.unwrap();
-        query3.execute(&db).await.unwrap();
-        query4.execute(&db).await.unwrap();
-        query5.execute(&db).await.unwrap();
-        query6.execute(&db).await.unwrap();
-    });
+    snapshot_db(pool, data_path, newest).await?;
+    normalize_migration_history(pool).await?;
+    migrator.undo(pool, target).await.map_err(|e| DbError::Query(e.to_string()))?;
+    if target < 8 { restore_legacy_history(pool).await?; }
+    Ok(())
 }
 
-// === DB RELATED ===
+/// Errors of the database layer, serialized for the frontend the same way as [`crate::utils::repo_manager::RepositoryError`].
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum DbError {
+    /// Pool is missing or closed, usually the app is still starting up or already shutting down
+    Unavailable(String),
+    /// Unique, foreign key, not null or check constraint rejected the change
+    Constraint(String),
+    /// A stored value does not have the type the launcher expects
+    Decode(String),
+    /// Schema was written by a newer launcher
+    Incompatible(String),
+    /// OS keyring refused to store or hand out a repository secret
+    Secret(String),
+    Query(String),
+}
 
-fn add_migrations(db_url: &str, migrations: Vec<Migration>) -> Option<HashMap<String, MigrationList>> {
-    let mut migrs: Option<HashMap<String, MigrationList>> = Some(HashMap::new());
+impl From<Error> for DbError {
+    fn from(e: Error) -> Self {
+        let msg = e.to_string();
+        match e {
+            Error::Database(d) if !matches!(d.kind(), ErrorKind::Other) => DbError::Constraint(msg),
+            Error::ColumnNotFound(_) | Error::ColumnDecode { .. } | Error::Decode(_) | Error::TypeNotFound { .. } => DbError::Decode(msg),
+            Error::PoolClosed | Error::PoolTimedOut | Error::Io(_) => DbError::Unavailable(msg),
+            _ => DbError::Query(msg)
+        }
+    }
+}
 
-    migrs.get_or_insert(Default::default()).insert(db_url.to_string(), MigrationList(migrations));
-    migrs
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::Unavailable(m) => write!(f, "Database is unavailable: {}", m),
+            DbError::Constraint(m) => write!(f, "Database rejected the change: {}", m),
+            DbError::Decode(m) => write!(f, "Database contains unexpected data: {}", m),
+            DbError::Incompatible(m) => write!(f, "Database is too new: {}", m),
+            DbError::Secret(m) => write!(f, "Keyring is unavailable: {}", m),
+            DbError::Query(m) => write!(f, "Database query failed: {}", m),
+        }
+    }
 }
 
+impl std::error::Error for DbError {}
+
 #[derive(Default, Debug)]
 pub struct DbInstances(pub Mutex<HashMap<String, Pool<Sqlite>>>);
 
@@ -850,18 +1205,99 @@ struct MigrationList(Vec<Migration>);
 impl MigrationSource<'static> for MigrationList {
     fn resolve(self) -> BoxFuture<'static, Result<Vec<SqlxMigration>, BoxDynError>> {
         Box::pin(async move {
-            let mut migrations = Vec::new();
-            for migration in self.0 {
-                if matches!(migration.kind, MigrationKind::Up) {
-                    migrations.push(SqlxMigration::new(
-                        migration.version,
-                        migration.description.into(),
-                        migration.kind.into(),
-                        migration.sql.into(),
-                    ));
-                }
-            }
+            let mut migrations = self.0.into_iter().map(|migration| SqlxMigration::new(
+                migration.version,
+                migration.description.into(),
+                migration.kind.into(),
+                migration.sql.into(),
+            )).collect::<Vec<SqlxMigration>>();
+            // The migrator applies in list order, keep it in version order no matter how the list is written
+            migrations.sort_by_key(|m| (m.version, m.migration_type.is_down_migration()));
             Ok(migrations)
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn raw_versions(pool: &Pool<Sqlite>) -> Vec<(i64, String)> {
+        query("SELECT version, description FROM _sqlx_migrations ORDER BY version").fetch_all(pool).await.unwrap().iter().map(|r| (r.get("version"), r.get("description"))).collect()
+    }
+
+    async fn temp_db(dir: &Path) -> Pool<Sqlite> {
+        if dir.exists() { fs::remove_dir_all(dir).unwrap(); }
+        fs::create_dir_all(dir).unwrap();
+        let url = dir.join("storage.db");
+        Sqlite::create_database(url.to_str().unwrap()).await.unwrap();
+        Pool::connect(url.to_str().unwrap()).await.unwrap()
+    }
+
+    #[test]
+    fn legacy_history_survives_upgrade_and_downgrade() {
+        let dir = std::env::temp_dir().join(format!("keqing-db-history-{}", std::process::id()));
+
+        run_async_command(async {
+            let pool = temp_db(&dir).await;
+
+            // Database as written by a launcher that still numbered install and settings 6 and 7
+            let legacy = migrations().into_iter().filter(|m| m.version <= 5).collect::<Vec<Migration>>();
+            Migrator::new(MigrationList(legacy)).await.unwrap().run(&pool).await.unwrap();
+            for (old, new, description) in LEGACY_VERSIONS {
+                query("UPDATE _sqlx_migrations SET version = $1 WHERE version = $2 AND description = $3").bind(old).bind(new).bind(description).execute(&pool).await.unwrap();
+            }
+            let legacy_history = raw_versions(&pool).await;
+            assert_eq!(legacy_history.iter().map(|(v, _)| *v).collect::<Vec<i64>>(), vec![1, 2, 5, 6, 7]);
+            assert_eq!(applied_versions(&pool).await.unwrap(), vec![1, 2, 3, 4, 5]);
+
+            migrate_db(&pool, &dir).await.unwrap();
+            let head = migrations().iter().map(|m| m.version).max().unwrap();
+            let mut applied = applied_versions(&pool).await.unwrap();
+            applied.sort();
+            assert_eq!(applied, (1..=5).chain(8..=head).collect::<Vec<i64>>());
+            assert!(raw_versions(&pool).await.iter().all(|(v, _)| *v != 6 && *v != 7));
+
+            undo_migrations(&pool, &dir, 7).await.unwrap();
+            assert_eq!(raw_versions(&pool).await, legacy_history);
+
+            // Running the current launcher again moves it straight back onto the current numbering
+            migrate_db(&pool, &dir).await.unwrap();
+            assert_eq!(applied_versions(&pool).await.unwrap().into_iter().max(), Some(head));
+            pool.close().await;
+        });
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn launch_options_fold_back_into_legacy_columns() {
+        let dir = std::env::temp_dir().join(format!("keqing-db-launch-{}", std::process::id()));
+
+        run_async_command(async {
+            let pool = temp_db(&dir).await;
+            migrate_db(&pool, &dir).await.unwrap();
+
+            query(r#"INSERT INTO install(id, env_vars, launch_args, launch_argv) VALUES ('install', '', '', '["-dx11","my game","it''s"]')"#).execute(&pool).await.unwrap();
+            for (key, value, enabled) in [("DXVK_HUD", "fps", true), ("SPLIT", "a;b", false), ("HAS_SEMICOLON", "a;b", true), ("OFF", "1", false), ("WINEDLLOVERRIDES", "dxgi=n,b", true)] {
+                query("INSERT INTO install_env_var(install_id, key, value, enabled) VALUES ('install', $1, $2, $3)").bind(key).bind(value).bind(enabled).execute(&pool).await.unwrap();
+            }
+
+            undo_migrations(&pool, &dir, 16).await.unwrap();
+            let row = query("SELECT env_vars, launch_args FROM install WHERE id = 'install'").fetch_one(&pool).await.unwrap();
+            let env_vars: String = row.get("env_vars");
+            let launch_args: String = row.get("launch_args");
+            assert_eq!(env_vars, "DXVK_HUD=fps;WINEDLLOVERRIDES=dxgi=n,b;");
+            assert_eq!(parse_legacy_env_vars(env_vars.as_str()).len(), 2);
+            assert_eq!(parse_legacy_launch_args(launch_args.as_str()), vec!["-dx11", "my game", "it's"]);
+
+            // Going back up converts the legacy columns again since migration 17 is pending once more
+            migrate_db(&pool, &dir).await.unwrap();
+            let argv: String = query("SELECT launch_argv FROM install WHERE id = 'install'").fetch_one(&pool).await.unwrap().get("launch_argv");
+            assert_eq!(serde_json::from_str::<Vec<String>>(argv.as_str()).unwrap(), vec!["-dx11", "my game", "it's"]);
+            let keys = query("SELECT key FROM install_env_var WHERE install_id = 'install' ORDER BY rowid").fetch_all(&pool).await.unwrap().iter().map(|r| r.get::<String, _>("key")).collect::<Vec<String>>();
+            assert_eq!(keys, vec!["DXVK_HUD", "WINEDLLOVERRIDES"]);
+            pool.close().await;
+        });
+        fs::remove_dir_all(&dir).ok();
+    }
+}
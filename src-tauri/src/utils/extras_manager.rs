@@ -0,0 +1,282 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use fischl::download::Extras;
+use fischl::utils::extract_archive;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+use crate::utils::sha256_file;
+use crate::utils::db_manager::{get_installed_extra_by_id, get_manifest_info_by_repository_and_filename, get_settings, set_installed_extra, DbError};
+use crate::utils::repo_manager::{get_extra_by_id, get_extras, ExtraFile, ExtraStep, ExtraTarget, LauncherInstalledExtra};
+
+#[cfg(target_os = "linux")]
+use std::os::unix::fs::symlink;
+
+// Extra ids the per install toggles install, anything else published by repositories is only installed on request
+pub const XXMI_EXTRA: &str = "xxmi";
+pub const JADEITE_EXTRA: &str = "jadeite";
+pub const FPS_UNLOCK_EXTRA: &str = "fps_unlock";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum ExtraError {
+    /// Neither an enabled manifest nor the built-in sources provide this id
+    NotFound(String),
+    Download(String),
+    Checksum(String),
+    Install(String),
+    Db(String),
+}
+
+impl std::fmt::Display for ExtraError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtraError::NotFound(m) => write!(f, "Extra not found: {}", m),
+            ExtraError::Download(m) | ExtraError::Checksum(m) | ExtraError::Install(m) => write!(f, "{}", m),
+            ExtraError::Db(m) => write!(f, "{}", m),
+        }
+    }
+}
+
+impl From<DbError> for ExtraError {
+    fn from(e: DbError) -> Self {
+        ExtraError::Db(e.to_string())
+    }
+}
+
+impl From<io::Error> for ExtraError {
+    fn from(e: io::Error) -> Self {
+        ExtraError::Install(e.to_string())
+    }
+}
+
+/// Installs the latest version of an extra unless exactly that version is already recorded as installed.
+/// Extras no enabled manifest provides fall back to the sources the launcher always downloaded them from.
+/// Returns whether anything was downloaded.
+pub fn install_extra(app: &AppHandle, id: &str, force: bool) -> Result<bool, ExtraError> {
+    let settings = get_settings(app)?.ok_or(ExtraError::Install("Failed to read settings".to_string()))?;
+
+    let Some((key, extra)) = get_extra_by_id(app, id)? else {
+        let target = builtin_target(id).ok_or(ExtraError::NotFound(format!("No enabled manifest provides extra {}", id)))?.path(&settings);
+        if !force && has_files(&target) { return Ok(false); }
+        fs::create_dir_all(&target)?;
+        install_builtin_extra(app, id, &target)?;
+        return Ok(true);
+    };
+
+    let version = extra.versions.iter().find(|v| v.version == extra.latest_version).ok_or(ExtraError::NotFound(format!("Extra {} does not list its latest version {}", id, extra.latest_version)))?;
+    let target = extra.target.path(&settings);
+
+    let installed = get_installed_extra_by_id(app, id.to_string())?;
+    if !force && installed.map(|i| i.version == version.version).unwrap_or(false) && has_files(&target) {
+        return Ok(false);
+    }
+
+    let staging = app.path().app_data_dir().unwrap().join("extras_staging").join(id);
+    if staging.exists() { fs::remove_dir_all(&staging)?; }
+    fs::create_dir_all(&staging)?;
+    fs::create_dir_all(&target)?;
+
+    app.emit("download_progress", extra.display_name.clone()).unwrap();
+
+    let rslt = version.files.iter().try_for_each(|f| install_file(f, &staging, &target)).and_then(|_| version.post_install.iter().try_for_each(|s| run_step(s, &target)));
+    fs::remove_dir_all(&staging).ok();
+    rslt?;
+
+    let (repository_id, filename) = key.split_once('/').unwrap();
+    let manifest_id = get_manifest_info_by_repository_and_filename(app, repository_id.to_string(), filename.to_string())?.map(|m| m.id).unwrap_or_default();
+    let installed_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    set_installed_extra(app, &LauncherInstalledExtra { id: id.to_string(), manifest_id, version: version.version.clone(), path: target.to_str().unwrap().to_string(), installed_at })?;
+
+    app.emit("download_complete", extra.display_name.clone()).unwrap();
+    Ok(true)
+}
+
+/// Runs [`install_extra`] on the blocking pool, for async commands.
+pub async fn install_extra_async(app: &AppHandle, id: &str, force: bool) -> Result<bool, ExtraError> {
+    let (app, id) = (app.clone(), id.to_string());
+    tauri::async_runtime::spawn_blocking(move || install_extra(&app, id.as_str(), force)).await.map_err(|e| ExtraError::Install(e.to_string()))?
+}
+
+/// Settings directory of the extras the launcher can install without any repository.
+fn builtin_target(id: &str) -> Option<ExtraTarget> {
+    match id {
+        XXMI_EXTRA => Some(ExtraTarget::Xxmi),
+        JADEITE_EXTRA => Some(ExtraTarget::Jadeite),
+        FPS_UNLOCK_EXTRA => Some(ExtraTarget::FpsUnlock),
+        _ => None
+    }
+}
+
+/// Downloads an extra from the release pages it was always fetched from, used until a repository overrides the id.
+fn install_builtin_extra(app: &AppHandle, id: &str, target: &PathBuf) -> Result<(), ExtraError> {
+    let dir = target.to_str().unwrap().to_string();
+    let update = has_files(target);
+
+    match id {
+        JADEITE_EXTRA => {
+            if !Extras::download_jadeite("MrLGamer/jadeite".to_string(), dir) { return Err(ExtraError::Download("Failed to download jadeite".to_string())); }
+            extract(&target.join("jadeite.zip"), target)?;
+        }
+        XXMI_EXTRA => {
+            app.emit("download_progress", String::from("XXMI Modding tool")).unwrap();
+            if !Extras::download_xxmi("SpectrumQT/XXMI-Libs-Package".to_string(), dir.clone(), !update) { return Err(ExtraError::Download("Failed to download XXMI".to_string())); }
+            extract(&target.join("xxmi.zip"), target)?;
+
+            let (gimi, srmi, zzmi, wwmi) = ("SilentNightSound/GIMI-Package", "SpectrumQT/SRMI-Package", "leotorrez/ZZMI-Package", "SpectrumQT/WWMI-Package");
+            if !Extras::download_xxmi_packages(gimi.to_string(), srmi.to_string(), zzmi.to_string(), wwmi.to_string(), dir, update) { return Err(ExtraError::Download("Failed to download XXMI packages".to_string())); }
+            for mi in ["gimi", "srmi", "zzmi", "wwmi"] {
+                extract(&target.join(format!("{mi}.zip")), &target.join(mi))?;
+                for lib in ["d3d11.dll", "d3dcompiler_47.dll"] {
+                    let link = target.join(mi).join(lib);
+                    if link.symlink_metadata().is_ok() { fs::remove_file(&link)?; }
+                    #[cfg(target_os = "linux")]
+                    symlink(target.join(lib), &link)?;
+                    #[cfg(target_os = "windows")]
+                    fs::copy(target.join(lib), &link)?;
+                }
+            }
+            app.emit("download_complete", String::from("XXMI Modding tool")).unwrap();
+        }
+        FPS_UNLOCK_EXTRA => {
+            if !Extras::download_fps_unlock("mkrsym1/fpsunlock".to_string(), dir) { return Err(ExtraError::Download("Failed to download fps unlock".to_string())); }
+        }
+        _ => return Err(ExtraError::NotFound(format!("No enabled manifest provides extra {}", id)))
+    }
+    Ok(())
+}
+
+fn has_files(dir: &Path) -> bool {
+    fs::read_dir(dir).map(|mut d| d.next().is_some()).unwrap_or(false)
+}
+
+fn extract(archive: &PathBuf, dest: &PathBuf) -> Result<(), ExtraError> {
+    if !extract_archive(archive.to_str().unwrap().to_string(), dest.to_str().unwrap().to_string(), false) {
+        return Err(ExtraError::Install(format!("Failed to extract {}", archive.display())));
+    }
+    Ok(())
+}
+
+/// Fire and forget variant used when saving launch profiles, failures are shown as a notification.
+pub fn spawn_extra_install(app: AppHandle, id: &'static str) {
+    std::thread::spawn(move || {
+        if let Err(e) = install_extra(&app, id, false) {
+            app.notification().builder().icon("dialog-error").title("TwintailLauncher").body(format!("Failed to install {}: {}", id, e)).show().unwrap();
+        }
+    });
+}
+
+/// Updates every extra that has something installed in its target directory.
+pub fn update_installed_extras(app: &AppHandle) {
+    let settings = match get_settings(app) {
+        Ok(Some(s)) => s,
+        _ => return
+    };
+    let mut ids = get_extras(app).into_iter().map(|(_, e)| e).filter(|e| has_files(&e.target.path(&settings))).map(|e| e.id).collect::<Vec<String>>();
+    ids.sort();
+    ids.dedup();
+
+    // Built-in sources have no version to compare against, so they are always downloaded again
+    let builtin = [JADEITE_EXTRA, XXMI_EXTRA, FPS_UNLOCK_EXTRA].into_iter().filter(|id| !ids.iter().any(|i| i == id) && builtin_target(id).map(|t| has_files(&t.path(&settings))).unwrap_or(false)).collect::<Vec<&str>>();
+
+    for (id, force) in ids.iter().map(|i| (i.as_str(), false)).chain(builtin.into_iter().map(|i| (i, true))) {
+        if let Err(_e) = install_extra(app, id, force) {
+            #[cfg(debug_assertions)]
+            { println!("Failed to update extra {}: {}", id, _e); }
+        }
+    }
+}
+
+fn install_file(file: &ExtraFile, staging: &PathBuf, target: &PathBuf) -> Result<(), ExtraError> {
+    let dest = match &file.subdir { Some(s) => target_path(target, s)?, None => target.clone() };
+    fs::create_dir_all(&dest)?;
+
+    let downloaded = target_path(staging, &file.file_name)?;
+    download_file(&file.url, &downloaded)?;
+
+    match &file.sha256 {
+        Some(expected) => {
+            let actual = sha256_file(&downloaded)?;
+            if !actual.eq_ignore_ascii_case(expected) { return Err(ExtraError::Checksum(format!("Checksum mismatch for {}, expected {} got {}", file.file_name, expected, actual))); }
+        },
+        None => {
+            #[cfg(debug_assertions)]
+            { println!("{} has no sha256 in its manifest, installing it unverified", file.file_name); }
+        }
+    }
+
+    if file.extract {
+        if !extract_archive(downloaded.to_str().unwrap().to_string(), dest.to_str().unwrap().to_string(), file.strip_root) {
+            return Err(ExtraError::Install(format!("Failed to extract {}", file.file_name)));
+        }
+    } else {
+        fs::copy(&downloaded, dest.join(&file.file_name))?;
+    }
+    fs::remove_file(&downloaded).ok();
+    Ok(())
+}
+
+fn download_file(url: &str, dest: &PathBuf) -> Result<(), ExtraError> {
+    let err = |e: String| ExtraError::Download(format!("Failed to download {}: {}", url, e));
+    // No overall timeout, mod packages can take a while on slow connections
+    let client = Client::builder().user_agent(concat!("TwintailLauncher/", env!("CARGO_PKG_VERSION"))).timeout(None).build().map_err(|e| err(e.to_string()))?;
+    let mut response = client.get(url).send().map_err(|e| err(e.to_string()))?;
+    if !response.status().is_success() { return Err(err(format!("HTTP {}", response.status()))); }
+
+    let mut file = fs::File::create(dest)?;
+    response.copy_to(&mut file).map_err(|e| err(e.to_string()))?;
+    Ok(())
+}
+
+fn run_step(step: &ExtraStep, target: &PathBuf) -> Result<(), ExtraError> {
+    match step {
+        ExtraStep::Symlink { source, destination } => {
+            let dest = target_path(target, destination)?;
+            if dest.symlink_metadata().is_ok() { fs::remove_file(&dest)?; }
+            #[cfg(target_os = "linux")]
+            symlink(target_path(target, source)?, &dest)?;
+            #[cfg(target_os = "windows")]
+            fs::copy(target_path(target, source)?, &dest)?;
+        }
+        ExtraStep::Copy { source, destination } => { fs::copy(target_path(target, source)?, target_path(target, destination)?)?; }
+        ExtraStep::Remove { path } => {
+            let p = target_path(target, path)?;
+            if p.is_dir() { fs::remove_dir_all(&p)?; } else if p.symlink_metadata().is_ok() { fs::remove_file(&p)?; }
+        }
+    }
+    Ok(())
+}
+
+/// Joins a manifest supplied path onto the target, refusing anything that would escape it.
+fn target_path(target: &PathBuf, relative: &str) -> Result<PathBuf, ExtraError> {
+    if relative.is_empty() || Path::new(relative).is_absolute() || relative.split(['/', '\\']).any(|c| c == "..") {
+        return Err(ExtraError::Install(format!("Refusing to touch {} outside of {}", relative, target.display())));
+    }
+    Ok(target.join(relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_path_stays_inside_target() {
+        let target = PathBuf::from("/extras/xxmi");
+        assert_eq!(target_path(&target, "gimi/d3d11.dll").unwrap(), target.join("gimi/d3d11.dll"));
+
+        for escaping in ["", "/etc/passwd", "../jadeite", "gimi/../../x", "gimi\\..\\..\\x"] {
+            assert!(matches!(target_path(&target, escaping), Err(ExtraError::Install(_))), "{escaping}");
+        }
+    }
+
+    #[test]
+    fn builtin_sources_cover_the_toggles() {
+        assert_eq!(builtin_target(XXMI_EXTRA), Some(ExtraTarget::Xxmi));
+        assert_eq!(builtin_target(JADEITE_EXTRA), Some(ExtraTarget::Jadeite));
+        assert_eq!(builtin_target(FPS_UNLOCK_EXTRA), Some(ExtraTarget::FpsUnlock));
+        assert_eq!(builtin_target("reshade"), None);
+    }
+}
@@ -13,13 +13,12 @@ use crate::utils::get_mi_path_from_game;
 #[cfg(target_os = "linux")]
 use std::os::unix::process::CommandExt;
 #[cfg(target_os = "linux")]
-use crate::utils::runner_from_runner_version;
-#[cfg(target_os = "linux")]
-use crate::utils::repo_manager::{get_compatibility};
+use crate::utils::repo_manager::{get_compatibility_by_version};
 
 #[cfg(target_os = "linux")]
 pub fn launch(app: &AppHandle, install: LauncherInstall, gm: GameManifest, gs: GlobalSettings) -> Result<bool, Error> {
-    let rm = get_compatibility(&app, &runner_from_runner_version(install.runner_version.clone()).unwrap()).unwrap();
+    // Surfaces as NotFound so game_launch can show the reason instead of pointing at game.log
+    let (rm, _) = get_compatibility_by_version(&app, &install.runner_version).map_err(|e| Error::Io(std::io::Error::new(std::io::ErrorKind::NotFound, e)))?;
 
     let dir = install.directory.clone();
     let prefix = install.runner_prefix.clone();
@@ -55,29 +54,27 @@ pub fn launch(app: &AppHandle, install: LauncherInstall, gm: GameManifest, gs: G
     }
 
     let rslt = if install.launch_command.is_empty() {
-        let mut args = "";
-        if !install.launch_args.is_empty() {
-            args = &install.launch_args;
-        }
-
+        // Launch args reach the game through "$@" so bash never gets to interpret them
         let mut command = if rm.display_name.to_ascii_lowercase().contains("proton") && !rm.display_name.to_ascii_lowercase().contains("wine") {
-            format!("'{runner}/{wine64}' run '{dir}/{game}' {args}")
+            format!("'{runner}/{wine64}' run '{dir}/{game}' \"$@\"")
         } else {
-            format!("'{runner}/{wine64}' '{dir}/{game}' {args}")
+            format!("'{runner}/{wine64}' '{dir}/{game}' \"$@\"")
         };
 
         if install.use_jadeite {
             let jadeite_path = gs.jadeite_path.clone();
             command = if rm.display_name.to_ascii_lowercase().contains("proton") && !rm.display_name.to_ascii_lowercase().contains("wine") {
-                format!("'{runner}/{wine64}' run '{jadeite_path}/jadeite.exe' '{dir}/{game}' -- {args}")
+                format!("'{runner}/{wine64}' run '{jadeite_path}/jadeite.exe' '{dir}/{game}' -- \"$@\"")
             } else {
-                format!("'{runner}/{wine64}' '{jadeite_path}/jadeite.exe' '{dir}/{game}' -- {args}")
+                format!("'{runner}/{wine64}' '{jadeite_path}/jadeite.exe' '{dir}/{game}' -- \"$@\"")
             };
         }
 
         let mut cmd = Command::new("bash");
         cmd.arg("-c");
         cmd.arg(&command);
+        cmd.arg("bash");
+        cmd.args(&install.launch_args);
 
         cmd.env("WINEARCH","win64");
         cmd.env("WINEPREFIX", prefix.clone());
@@ -89,18 +86,7 @@ pub fn launch(app: &AppHandle, install: LauncherInstall, gm: GameManifest, gs: G
         cmd.current_dir(dir.clone());
         cmd.process_group(0); // Start as detached process so killing launcher does not kill the game
 
-        if !install.env_vars.is_empty() {
-            let envs = install.env_vars.clone();
-            let splitted = envs.split(";").collect::<Vec<&str>>();
-            splitted.iter().for_each(|env| {
-                if !env.is_empty() {
-                    let tmp = env.splitn(2,"=").collect::<Vec<&str>>();
-                    let k = tmp[0];
-                    let v = tmp[1].replace("\"", "");
-                    cmd.env(k, v);
-                }
-            });
-        }
+        install.env_vars.iter().filter(|e| e.enabled).for_each(|e| { cmd.env(&e.key, &e.value); });
 
         let spawned = cmd.spawn();
         if spawned.is_ok() {
@@ -117,25 +103,17 @@ pub fn launch(app: &AppHandle, install: LauncherInstall, gm: GameManifest, gs: G
     } else {
         // We assume user knows what he/she is doing so we just execute command that is configured without any checks
         let c = install.launch_command.clone();
-        let args;
-        let mut command = if rm.display_name.to_ascii_lowercase().contains("proton") && !rm.display_name.to_ascii_lowercase().contains("wine") {
-            format!("'{runner}/{wine64}' run '{c}'")
+        let command = if rm.display_name.to_ascii_lowercase().contains("proton") && !rm.display_name.to_ascii_lowercase().contains("wine") {
+            format!("'{runner}/{wine64}' run '{c}' \"$@\"")
         } else {
-            format!("'{runner}/{wine64}' '{c}'")
+            format!("'{runner}/{wine64}' '{c}' \"$@\"")
         };
 
-        if !install.launch_args.is_empty() {
-            args = &install.launch_args;
-            command = if rm.display_name.to_ascii_lowercase().contains("proton") && !rm.display_name.to_ascii_lowercase().contains("wine") {
-                format!("'{runner}/{wine64}' run '{c}' {args}")
-            } else {
-                format!("'{runner}/{wine64}' '{c}' {args}")
-            };
-        }
-
         let mut cmd = Command::new("bash");
         cmd.arg("-c");
         cmd.arg(&command);
+        cmd.arg("bash");
+        cmd.args(&install.launch_args);
 
         cmd.env("WINEARCH","win64");
         cmd.env("WINEPREFIX", prefix.clone());
@@ -147,18 +125,7 @@ pub fn launch(app: &AppHandle, install: LauncherInstall, gm: GameManifest, gs: G
         cmd.current_dir(dir.clone());
         cmd.process_group(0);
 
-        if !install.env_vars.is_empty() {
-            let envs = install.env_vars.clone();
-            let splitted = envs.split(";").collect::<Vec<&str>>();
-            splitted.iter().for_each(|env| {
-                if !env.is_empty() {
-                    let tmp = env.splitn(2, "=").collect::<Vec<&str>>();
-                    let k = tmp[0];
-                    let v = tmp[1].replace("\"", "");
-                    cmd.env(k, v);
-                }
-            });
-        }
+        install.env_vars.iter().filter(|e| e.enabled).for_each(|e| { cmd.env(&e.key, &e.value); });
 
         let spawned = cmd.spawn();
         if spawned.is_ok() {
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use crate::commands::settings::GlobalSettings;
+use crate::utils::db_manager::{get_install_info_by_id, get_installs, get_launch_profiles_by_install_id, get_manifest_info_by_repository_and_filename, get_manifests_by_repository_id, get_repositories, get_repository_info_by_id, get_repository_info_by_remote, get_settings, import_state_rows, update_repository_ref_by_id, DbError};
+use crate::utils::repo_manager::{add_bundle_repository, add_local_repository, clone_new_repository, get_repository_path, load_manifests, local_repository_remote, repository_lock, update_repositories, LaunchProfile, LauncherInstall, LauncherManifest, LauncherRepository, RepositoryError, RepositoryTransport};
+use crate::utils::{ImportIssue, ImportStateRsp};
+
+/// Highest state bundle `version` this launcher knows how to import.
+pub const STATE_BUNDLE_VERSION: i32 = 1;
+
+/// Everything needed to rebuild the launcher on another machine. Repository credentials are deliberately left out.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LauncherStateBundle {
+    pub version: i32,
+    pub launcher_version: String,
+    pub exported_at: i64,
+    pub settings: GlobalSettings,
+    /// Remotes and tracked refs, local repositories carry their directory in `local_path`
+    pub repositories: Vec<LauncherRepository>,
+    pub manifests: Vec<LauncherManifest>,
+    pub installs: Vec<LauncherInstall>,
+    /// Missing from bundles written before profiles existed, adopted installs then just keep their fresh default profile
+    #[serde(default)]
+    pub launch_profiles: Vec<LaunchProfile>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum StateError {
+    /// Reading or writing the state file failed
+    Io(String),
+    /// Not a state bundle, or one written by a newer launcher
+    InvalidBundle(String),
+    Db(String),
+    Other(String),
+}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateError::Io(m) => write!(f, "Failed to access state file: {}", m),
+            StateError::InvalidBundle(m) => write!(f, "Invalid state bundle: {}", m),
+            StateError::Db(m) | StateError::Other(m) => write!(f, "{}", m),
+        }
+    }
+}
+
+impl From<DbError> for StateError {
+    fn from(e: DbError) -> Self {
+        StateError::Db(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for StateError {
+    fn from(e: std::io::Error) -> Self {
+        StateError::Io(e.to_string())
+    }
+}
+
+/// Rewrites paths starting with `from` to start with `to` instead, matched on whole path components.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PathMapping {
+    pub from: String,
+    pub to: String,
+}
+
+pub fn export_state(app: &AppHandle) -> Result<LauncherStateBundle, DbError> {
+    let settings = get_settings(app)?.ok_or(DbError::Query("Settings are missing!".to_string()))?;
+    let repositories = get_repositories(app)?;
+    let mut manifests = Vec::new();
+    for r in &repositories { manifests.extend(get_manifests_by_repository_id(app, r.id.clone())?); }
+    let installs = get_installs(app)?;
+    let mut launch_profiles = Vec::new();
+    for i in &installs { launch_profiles.extend(get_launch_profiles_by_install_id(app, i.id.clone())?); }
+
+    Ok(LauncherStateBundle {
+        version: STATE_BUNDLE_VERSION,
+        launcher_version: app.package_info().version.to_string(),
+        exported_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64,
+        settings,
+        repositories,
+        manifests,
+        installs,
+        launch_profiles,
+    })
+}
+
+/// Applies an exported state on top of the current one. Repositories are re-added from their remotes, installs are only
+/// adopted when their (remapped) directory already exists, nothing is downloaded for them.
+/// Repositories are fetched first, everything else is only written once the whole bundle checked out and then as one transaction.
+pub fn import_state(app: &AppHandle, bundle: LauncherStateBundle, mappings: &[PathMapping]) -> Result<ImportStateRsp, StateError> {
+    if bundle.version > STATE_BUNDLE_VERSION { return Err(StateError::InvalidBundle(format!("State bundle version {} is newer than this launcher supports ({})", bundle.version, STATE_BUNDLE_VERSION))); }
+    let mut rsp = ImportStateRsp::default();
+    let settings = imported_settings(app, &bundle.settings, mappings)?;
+
+    // Exported repository id -> id of the same repository on this machine, each added repository is complete on its own
+    let mut repository_ids: HashMap<String, String> = HashMap::new();
+    for r in &bundle.repositories {
+        match import_repository(app, r, mappings) {
+            Ok((id, added)) => {
+                if added { rsp.added_repositories.push(r.github_id.clone()); }
+                repository_ids.insert(r.id.clone(), id);
+            }
+            Err(e) => rsp.failed_repositories.push(ImportIssue { id: r.github_id.clone(), reason: e.to_string() })
+        }
+    }
+    load_manifests(app);
+
+    // Exported manifest id -> local manifest id, file names are only unique within a repository
+    let mut manifest_ids: HashMap<String, String> = HashMap::new();
+    let mut manifests: Vec<(String, bool)> = Vec::new();
+    for m in &bundle.manifests {
+        let repository_id = match repository_ids.get(&m.repository_id) {
+            Some(id) => id.clone(),
+            None => continue
+        };
+        if let Some(local) = get_manifest_info_by_repository_and_filename(app, repository_id, m.filename.clone())? {
+            if local.enabled != m.enabled { manifests.push((local.id.clone(), m.enabled)); }
+            manifest_ids.insert(m.id.clone(), local.id);
+        }
+    }
+
+    let mut installs: Vec<LauncherInstall> = Vec::new();
+    for i in bundle.installs {
+        let manifest_id = match manifest_ids.get(&i.manifest_id) {
+            Some(id) => id.clone(),
+            None => {
+                rsp.skipped_installs.push(ImportIssue { id: i.id.clone(), reason: format!("Manifest of \"{}\" is not available", i.name) });
+                continue;
+            }
+        };
+        if installs.iter().any(|a| a.id == i.id) || get_install_info_by_id(app, i.id.clone())?.is_some() {
+            rsp.skipped_installs.push(ImportIssue { id: i.id.clone(), reason: format!("\"{}\" is already installed", i.name) });
+            continue;
+        }
+
+        let directory = remap_path(&i.directory, mappings);
+        if !Path::new(&directory).exists() {
+            rsp.skipped_installs.push(ImportIssue { id: i.id.clone(), reason: format!("{} does not exist", directory) });
+            continue;
+        }
+
+        installs.push(LauncherInstall { manifest_id, directory, runner_path: remap_path(&i.runner_path, mappings), dxvk_path: remap_path(&i.dxvk_path, mappings), runner_prefix: remap_path(&i.runner_prefix, mappings), ..i });
+    }
+
+    import_state_rows(app, &settings, &manifests, &installs, &bundle.launch_profiles)?;
+    rsp.adopted_installs = installs.into_iter().map(|i| i.id).collect();
+    rsp.success = true;
+    Ok(rsp)
+}
+
+/// Paths that do not exist after remapping keep whatever this machine already uses.
+fn imported_settings(app: &AppHandle, settings: &GlobalSettings, mappings: &[PathMapping]) -> Result<GlobalSettings, DbError> {
+    let current = get_settings(app)?.ok_or(DbError::Query("Settings are missing!".to_string()))?;
+    let path_or = |exported: &str, current: &str| {
+        let p = remap_path(exported, mappings);
+        if !p.is_empty() && Path::new(&p).exists() { p } else { current.to_string() }
+    };
+
+    Ok(GlobalSettings {
+        default_game_path: path_or(&settings.default_game_path, &current.default_game_path),
+        xxmi_path: path_or(&settings.xxmi_path, &current.xxmi_path),
+        fps_unlock_path: path_or(&settings.fps_unlock_path, &current.fps_unlock_path),
+        jadeite_path: path_or(&settings.jadeite_path, &current.jadeite_path),
+        third_party_repo_updates: settings.third_party_repo_updates,
+        default_runner_prefix_path: path_or(&settings.default_runner_prefix_path, &current.default_runner_prefix_path),
+        launcher_action: settings.launcher_action.clone(),
+        hide_manifests: settings.hide_manifests,
+        repo_sync_interval: settings.repo_sync_interval,
+    })
+}
+
+/// Returns the local id of the repository and whether it had to be added.
+fn import_repository(app: &AppHandle, repository: &LauncherRepository, mappings: &[PathMapping]) -> Result<(String, bool), RepositoryError> {
+    if repository.transport == RepositoryTransport::Local {
+        let dir = remap_path(repository.local_path.as_deref().unwrap_or_default(), mappings);
+        let canonical = Path::new(&dir).canonicalize().map_err(|e| RepositoryError::InvalidUrl(format!("{}: {}", dir, e)))?;
+        if let Some(e) = get_repository_info_by_remote(app, local_repository_remote(&canonical))? { return Ok((e.id, false)); }
+
+        add_local_repository(app, dir)?;
+        let local = get_repository_info_by_remote(app, local_repository_remote(&canonical))?.ok_or(RepositoryError::Other("Repository vanished while being imported!".to_string()))?;
+        return Ok((local.id, true));
+    }
+
+    let remote = repository.remote.clone();
+    let (local, added) = match get_repository_info_by_remote(app, remote.clone())? {
+        Some(r) => (r, false),
+        None => {
+            if repository.transport == RepositoryTransport::Bundle {
+                add_bundle_repository(app, remote.clone())?;
+            } else {
+                let path = app.path().app_data_dir().unwrap().join("manifests");
+                clone_new_repository(app, &path, remote.clone(), None)?;
+            }
+            (get_repository_info_by_remote(app, remote)?.ok_or(RepositoryError::Other("Repository vanished while being imported!".to_string()))?, true)
+        }
+    };
+
+    // Follow the same branch, tag or commit the old machine was pinned to
+    let lock = repository_lock(app, &local.id);
+    let _guard = lock.lock().unwrap();
+    // The scheduler or a command may have changed or removed it before the lock was ours
+    let local = get_repository_info_by_id(app, local.id)?.ok_or(RepositoryError::Other("Repository vanished while being imported!".to_string()))?;
+    if local.transport == RepositoryTransport::Git && (local.ref_kind != repository.ref_kind || local.ref_name != repository.ref_name) {
+        let path = get_repository_path(app, &local).ok_or(RepositoryError::Other("Repository has no valid remote!".to_string()))?;
+        update_repository_ref_by_id(app, local.id.clone(), repository.ref_kind.clone(), repository.ref_name.clone())?;
+        if let Err(e) = update_repositories(app, &path) {
+            update_repository_ref_by_id(app, local.id.clone(), local.ref_kind.clone(), local.ref_name.clone())?;
+            return Err(RepositoryError::from(e));
+        }
+    }
+    Ok((local.id, added))
+}
+
+/// Longest matching prefix wins, paths no mapping applies to are returned unchanged.
+pub fn remap_path(path: &str, mappings: &[PathMapping]) -> String {
+    if path.is_empty() { return String::new(); }
+
+    let p = Path::new(path);
+    mappings.iter()
+        .filter(|m| !m.from.is_empty() && p.starts_with(&m.from))
+        .max_by_key(|m| Path::new(&m.from).components().count())
+        .map(|m| {
+            let rest = p.strip_prefix(&m.from).unwrap();
+            let mapped: PathBuf = if rest.as_os_str().is_empty() { PathBuf::from(&m.to) } else { Path::new(&m.to).join(rest) };
+            mapped.to_str().unwrap().to_string()
+        })
+        .unwrap_or(path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(from: &str, to: &str) -> PathMapping {
+        PathMapping { from: from.to_string(), to: to.to_string() }
+    }
+
+    #[test]
+    fn remap_path_rewrites_matching_prefixes() {
+        let mappings = [mapping("/home/old/Games", "/mnt/games")];
+        assert_eq!(remap_path("/home/old/Games/genshin", &mappings), "/mnt/games/genshin");
+        assert_eq!(remap_path("/home/old/Games", &mappings), "/mnt/games");
+        assert_eq!(remap_path("/home/old/Other/genshin", &mappings), "/home/old/Other/genshin");
+        assert_eq!(remap_path("", &mappings), "");
+    }
+
+    #[test]
+    fn remap_path_matches_whole_components() {
+        let mappings = [mapping("/home/old/Games", "/mnt/games")];
+        assert_eq!(remap_path("/home/old/Games2/genshin", &mappings), "/home/old/Games2/genshin");
+        assert_eq!(remap_path("/home/old/Games/", &mappings), "/mnt/games");
+    }
+
+    #[test]
+    fn remap_path_prefers_the_longest_mapping() {
+        let mappings = [mapping("/home/old", "/home/new"), mapping("/home/old/Games/wine", "/opt/wine"), mapping("", "/ignored")];
+        assert_eq!(remap_path("/home/old/Games/wine/prefix", &mappings), "/opt/wine/prefix");
+        assert_eq!(remap_path("/home/old/Games/genshin", &mappings), "/home/new/Games/genshin");
+        assert_eq!(remap_path("/srv/genshin", &mappings), "/srv/genshin");
+        assert_eq!(remap_path("/home/old/Games", &[]), "/home/old/Games");
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use schemars::schema_for;
+use crate::utils::ManifestError;
+use crate::utils::repo_manager::{parse_manifest, ExtraManifest, ExtraStep, GameManifest, ManifestData, RepositoryManifest, RunnerManifest, VersionAudioFiles, VersionGameFiles};
+
+/// Download modes `start_game_download` and friends know how to handle.
+pub const KNOWN_DOWNLOAD_MODES: [&str; 3] = ["DOWNLOAD_MODE_FILE", "DOWNLOAD_MODE_CHUNK", "DOWNLOAD_MODE_RAW"];
+
+/// Result of [`lint_repository`], only errors make a repository unusable.
+#[derive(Debug, Default)]
+pub struct LintReport {
+    pub errors: Vec<ManifestError>,
+    /// Things the launcher skips over, like groups of manifest kinds it does not know yet
+    pub warnings: Vec<ManifestError>
+}
+
+/// Runs the same parsing the launcher does on a repository directory plus consistency checks that would otherwise only fail at install time.
+pub fn lint_repository(path: &Path) -> LintReport {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    let dir_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let err = |file: &str, json_path: String, reason: String| ManifestError { repository: dir_name.clone(), file: file.to_string(), path: json_path, reason };
+
+    let rma = match fs::read_to_string(path.join("repository.json")).map_err(|e| e.to_string()).and_then(|c| serde_json::from_str::<RepositoryManifest>(c.as_str()).map_err(|e| e.to_string())) {
+        Ok(rma) => rma,
+        Err(e) => {
+            errors.push(err("repository.json", "$".to_string(), e));
+            return LintReport { errors, warnings };
+        }
+    };
+
+    if let Some(min) = &rma.min_launcher_version {
+        if min.trim_start_matches('v').split('.').any(|p| p.parse::<u64>().is_err()) {
+            errors.push(err("repository.json", "$.min_launcher_version".to_string(), format!("\"{}\" is not a dotted version number", min)));
+        }
+    }
+
+    for group in rma.manifests.unknown_groups() {
+        warnings.push(err("repository.json", format!("$.manifests.{}", group), format!("Unknown manifest kind {}, the launcher skips these files", group)));
+    }
+
+    let mut seen = HashSet::new();
+    for (json_path, kind, m) in rma.manifests.located_entries() {
+        if !seen.insert(m.clone()) {
+            errors.push(err("repository.json", json_path, format!("{} is listed more than once", m)));
+            continue;
+        }
+
+        match parse_manifest(&dir_name, &m, kind, &path.join(m.as_str())) {
+            Ok(ManifestData::Game(gm)) => lint_game(&gm, &mut |p, r| errors.push(err(&m, p, r))),
+            Ok(ManifestData::Runner(rm)) => lint_runner(&rm, &mut |p, r| errors.push(err(&m, p, r)), &mut |p, r| warnings.push(err(&m, p, r))),
+            Ok(ManifestData::Extra(em)) => lint_extra(&em, &mut |p, r| errors.push(err(&m, p, r)), &mut |p, r| warnings.push(err(&m, p, r))),
+            Err(e) => errors.push(e)
+        }
+    }
+
+    LintReport { errors, warnings }
+}
+
+fn lint_game(gm: &GameManifest, report: &mut dyn FnMut(String, String)) {
+    let versions = gm.game_versions.iter().map(|v| v.metadata.version.as_str()).collect::<HashSet<&str>>();
+
+    if !versions.contains(gm.latest_version.as_str()) {
+        report("$.latest_version".to_string(), format!("{} is not listed in game_versions", gm.latest_version));
+    }
+
+    for (i, v) in gm.game_versions.iter().enumerate() {
+        let base = format!("$.game_versions[{}]", i);
+
+        if !KNOWN_DOWNLOAD_MODES.contains(&v.metadata.download_mode.as_str()) {
+            report(format!("{}.metadata.download_mode", base), format!("Unknown download mode {}", v.metadata.download_mode));
+        }
+
+        lint_game_files(&v.game, &versions, format!("{}.game", base), report);
+        lint_audio_files(&v.audio, &versions, format!("{}.audio", base), report);
+    }
+
+    if let Some(preload) = &gm.extra.preload {
+        if let Some(metadata) = &preload.metadata {
+            if !KNOWN_DOWNLOAD_MODES.contains(&metadata.download_mode.as_str()) {
+                report("$.extra.preload.metadata.download_mode".to_string(), format!("Unknown download mode {}", metadata.download_mode));
+            }
+        }
+        if let Some(game) = &preload.game { lint_game_files(game, &versions, "$.extra.preload.game".to_string(), report); }
+        if let Some(audio) = &preload.audio { lint_audio_files(audio, &versions, "$.extra.preload.audio".to_string(), report); }
+    }
+}
+
+fn lint_game_files(files: &VersionGameFiles, versions: &HashSet<&str>, base: String, report: &mut dyn FnMut(String, String)) {
+    for (i, f) in files.full.iter().enumerate() {
+        lint_sizes(&f.compressed_size, &f.decompressed_size, format!("{}.full[{}]", base, i), report);
+    }
+
+    for (i, f) in files.diff.iter().enumerate() {
+        lint_sizes(&f.compressed_size, &f.decompressed_size, format!("{}.diff[{}]", base, i), report);
+        lint_original_version(&f.original_version, versions, format!("{}.diff[{}]", base, i), report);
+    }
+}
+
+fn lint_audio_files(files: &VersionAudioFiles, versions: &HashSet<&str>, base: String, report: &mut dyn FnMut(String, String)) {
+    for (i, f) in files.full.iter().enumerate() {
+        lint_sizes(&f.compressed_size, &f.decompressed_size, format!("{}.full[{}]", base, i), report);
+    }
+
+    for (i, f) in files.diff.iter().enumerate() {
+        lint_sizes(&f.compressed_size, &f.decompressed_size, format!("{}.diff[{}]", base, i), report);
+        lint_original_version(&f.original_version, versions, format!("{}.diff[{}]", base, i), report);
+    }
+}
+
+fn lint_sizes(compressed: &str, decompressed: &str, base: String, report: &mut dyn FnMut(String, String)) {
+    if compressed.parse::<u64>().is_err() { report(format!("{}.compressed_size", base), format!("\"{}\" is not a valid size in bytes", compressed)); }
+    if decompressed.parse::<u64>().is_err() { report(format!("{}.decompressed_size", base), format!("\"{}\" is not a valid size in bytes", decompressed)); }
+}
+
+fn lint_original_version(original: &str, versions: &HashSet<&str>, base: String, report: &mut dyn FnMut(String, String)) {
+    if !versions.contains(original) {
+        report(format!("{}.original_version", base), format!("Diff is based on {} which is not listed in game_versions", original));
+    }
+}
+
+/// Versions without a `sha256` install without any verification, that is allowed but worth a warning.
+fn lint_runner(rm: &RunnerManifest, report: &mut dyn FnMut(String, String), warn: &mut dyn FnMut(String, String)) {
+    if rm.versions.is_empty() { report("$.versions".to_string(), "Runner manifest has no versions".to_string()); }
+
+    let mut seen = HashSet::new();
+    for (i, v) in rm.versions.iter().enumerate() {
+        if !seen.insert(v.version.as_str()) { report(format!("$.versions[{}].version", i), format!("{} is listed more than once", v.version)); }
+        if v.url.is_empty() { report(format!("$.versions[{}].url", i), "Download url is empty".to_string()); }
+        match &v.sha256 {
+            Some(h) => lint_sha256(h, format!("$.versions[{}].sha256", i), report),
+            None => warn(format!("$.versions[{}]", i), format!("{} has no sha256, downloads of it can not be verified", v.version))
+        }
+        if v.size == Some(0) { report(format!("$.versions[{}].size", i), "Archive size is zero".to_string()); }
+        if v.recommended && v.deprecated { report(format!("$.versions[{}]", i), format!("{} is marked both recommended and deprecated", v.version)); }
+        if let Some(d) = &v.release_date {
+            let parts = d.split('-').collect::<Vec<&str>>();
+            if parts.len() != 3 || parts.iter().any(|p| p.parse::<u32>().is_err()) { report(format!("$.versions[{}].release_date", i), format!("\"{}\" is not a YYYY-MM-DD date", d)); }
+        }
+    }
+}
+
+fn lint_sha256(h: &str, base: String, report: &mut dyn FnMut(String, String)) {
+    if h.len() != 64 || !h.chars().all(|c| c.is_ascii_hexdigit()) { report(base, format!("\"{}\" is not a sha256 hex digest", h)); }
+}
+
+fn lint_extra(em: &ExtraManifest, report: &mut dyn FnMut(String, String), warn: &mut dyn FnMut(String, String)) {
+    if em.id.is_empty() { report("$.id".to_string(), "Extra id is empty".to_string()); }
+    if !em.versions.iter().any(|v| v.version == em.latest_version) { report("$.latest_version".to_string(), format!("{} is not listed in versions", em.latest_version)); }
+
+    let mut seen = HashSet::new();
+    for (i, v) in em.versions.iter().enumerate() {
+        if !seen.insert(v.version.as_str()) { report(format!("$.versions[{}].version", i), format!("{} is listed more than once", v.version)); }
+        if v.files.is_empty() { report(format!("$.versions[{}].files", i), "Version has no files".to_string()); }
+
+        for (j, f) in v.files.iter().enumerate() {
+            let base = format!("$.versions[{}].files[{}]", i, j);
+            if f.url.is_empty() { report(format!("{}.url", base), "Download url is empty".to_string()); }
+            if f.file_name.is_empty() || f.file_name.contains(['/', '\\']) { report(format!("{}.file_name", base), format!("\"{}\" is not a plain file name", f.file_name)); }
+            match &f.sha256 {
+                Some(h) => lint_sha256(h, format!("{}.sha256", base), report),
+                None => warn(base.clone(), format!("{} has no sha256, downloads of it can not be verified", f.file_name))
+            }
+            if let Some(d) = &f.subdir { lint_relative_path(d, format!("{}.subdir", base), report); }
+        }
+
+        for (j, step) in v.post_install.iter().enumerate() {
+            let base = format!("$.versions[{}].post_install[{}]", i, j);
+            match step {
+                ExtraStep::Symlink { source, destination } | ExtraStep::Copy { source, destination } => {
+                    lint_relative_path(source, format!("{}.source", base), report);
+                    lint_relative_path(destination, format!("{}.destination", base), report);
+                }
+                ExtraStep::Remove { path } => lint_relative_path(path, format!("{}.path", base), report)
+            }
+        }
+    }
+}
+
+/// Extras may only touch their own target directory.
+fn lint_relative_path(p: &str, base: String, report: &mut dyn FnMut(String, String)) {
+    if p.is_empty() || Path::new(p).is_absolute() || p.split(['/', '\\']).any(|c| c == "..") {
+        report(base, format!("\"{}\" must be a path inside the target directory", p));
+    }
+}
+
+/// JSON Schema of every manifest kind, keyed by the file name it should be exported as.
+pub fn manifest_schemas() -> Vec<(&'static str, serde_json::Value)> {
+    vec![
+        ("repository.schema.json", serde_json::to_value(schema_for!(RepositoryManifest)).unwrap()),
+        ("game.schema.json", serde_json::to_value(schema_for!(GameManifest)).unwrap()),
+        ("runner.schema.json", serde_json::to_value(schema_for!(RunnerManifest)).unwrap()),
+        ("extra.schema.json", serde_json::to_value(schema_for!(ExtraManifest)).unwrap()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/manifest_lint").join(name)
+    }
+
+    fn diagnostics(errors: &[ManifestError]) -> Vec<(&str, &str)> {
+        errors.iter().map(|e| (e.file.as_str(), e.path.as_str())).collect()
+    }
+
+    #[test]
+    fn valid_repository_has_no_diagnostics() {
+        let report = lint_repository(&fixture("valid"));
+        assert!(report.errors.is_empty(), "{:?}", diagnostics(&report.errors));
+        assert!(report.warnings.is_empty(), "{:?}", diagnostics(&report.warnings));
+    }
+
+    #[test]
+    fn invalid_repository_reports_every_problem() {
+        let errors = lint_repository(&fixture("invalid")).errors;
+        assert_eq!(diagnostics(&errors), vec![
+            ("repository.json", "$.min_launcher_version"),
+            ("game.json", "$.latest_version"),
+            ("game.json", "$.game_versions[0].metadata.download_mode"),
+            ("game.json", "$.game_versions[0].game.full[0].compressed_size"),
+            ("game.json", "$.game_versions[0].game.diff[0].original_version"),
+            ("broken.json", "$"),
+            ("runner.json", "$.versions[0].sha256"),
+            ("runner.json", "$.versions[0].size"),
+            ("runner.json", "$.versions[0]"),
+            ("runner.json", "$.versions[0].release_date"),
+            ("runner.json", "$.versions[1].version"),
+            ("runner.json", "$.versions[1].url"),
+            ("extra.json", "$.latest_version"),
+            ("extra.json", "$.versions[0].files[0].file_name"),
+            ("extra.json", "$.versions[0].files[0].subdir"),
+            ("extra.json", "$.versions[0].post_install[0].path"),
+        ]);
+        assert!(errors[5].reason.starts_with("Invalid JSON"), "{}", errors[5].reason);
+    }
+
+    #[test]
+    fn missing_repository_json_is_reported() {
+        let errors = lint_repository(&fixture("does-not-exist")).errors;
+        assert_eq!(diagnostics(&errors), vec![("repository.json", "$")]);
+    }
+
+    #[test]
+    fn duplicates_point_at_the_entry() {
+        let errors = lint_repository(&fixture("duplicate")).errors;
+        assert_eq!(diagnostics(&errors), vec![("repository.json", "$.manifests.runner[1]")]);
+    }
+
+    #[test]
+    fn unknown_groups_are_warnings() {
+        let report = lint_repository(&fixture("unknown_group"));
+        assert!(report.errors.is_empty(), "{:?}", diagnostics(&report.errors));
+        assert_eq!(diagnostics(&report.warnings), vec![("repository.json", "$.manifests.tool")]);
+    }
+
+    #[test]
+    fn missing_checksums_are_warnings() {
+        let report = lint_repository(&fixture("unverified"));
+        assert!(report.errors.is_empty(), "{:?}", diagnostics(&report.errors));
+        assert_eq!(diagnostics(&report.warnings), vec![
+            ("runner.json", "$.versions[1]"),
+            ("extra.json", "$.versions[0].files[1]"),
+        ]);
+    }
+
+    #[test]
+    fn schemas_are_exported_for_every_kind() {
+        let names = manifest_schemas().into_iter().map(|(n, _)| n).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["repository.schema.json", "game.schema.json", "runner.schema.json", "extra.schema.json"]);
+    }
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager};
+use crate::utils::db_manager::DbError;
+use crate::utils::ManifestError;
+use crate::utils::repo_manager::ManifestData;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum OverrideError {
+    /// Manifest, its repository or the repository's files are gone
+    NotFound(String),
+    /// Patch is not a JSON object or the patched manifest no longer validates
+    InvalidPatch(String),
+    /// Manifest does not validate even without the patch
+    InvalidManifest(String),
+    Io(String),
+    Db(String),
+}
+
+impl std::fmt::Display for OverrideError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverrideError::NotFound(m) | OverrideError::Db(m) => write!(f, "{}", m),
+            OverrideError::InvalidPatch(m) => write!(f, "Invalid override: {}", m),
+            OverrideError::InvalidManifest(m) => write!(f, "Invalid manifest: {}", m),
+            OverrideError::Io(m) => write!(f, "Failed to write override: {}", m),
+        }
+    }
+}
+
+impl From<DbError> for OverrideError {
+    fn from(e: DbError) -> Self {
+        OverrideError::Db(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for OverrideError {
+    fn from(e: std::io::Error) -> Self {
+        OverrideError::Io(e.to_string())
+    }
+}
+
+/// User overrides live next to the database as `overrides/<repository id>/<file name>`, one RFC 7386 merge patch per manifest.
+pub fn override_path(app: &AppHandle, key: &str) -> PathBuf {
+    app.path().app_data_dir().unwrap().join("overrides").join(key)
+}
+
+/// `Ok(None)` when the manifest has no override, one that can not be read or is not valid JSON is reported instead of ignored.
+pub fn read_override(app: &AppHandle, repository: &str, key: &str) -> Result<Option<Value>, ManifestError> {
+    let path = override_path(app, key);
+    let err = |reason: String| ManifestError { repository: repository.to_string(), file: key.split_once('/').map(|(_, f)| f).unwrap_or(key).to_string(), path: "$".to_string(), reason };
+
+    let content = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(err(format!("Failed to read user override {}: {}", path.display(), e)))
+    };
+    serde_json::from_str(content.as_str()).map(Some).map_err(|e| err(format!("User override {} is not valid JSON: {}", path.display(), e)))
+}
+
+pub fn write_override(app: &AppHandle, key: &str, patch: &Value) -> std::io::Result<()> {
+    let path = override_path(app, key);
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(path, serde_json::to_string_pretty(patch).unwrap())
+}
+
+/// Returns whether there was an override to remove.
+pub fn clear_override(app: &AppHandle, key: &str) -> bool {
+    fs::remove_file(override_path(app, key)).is_ok()
+}
+
+/// Drops every override of a repository, used when the repository itself goes away.
+pub fn clear_repository_overrides(app: &AppHandle, repository_id: &str) -> std::io::Result<()> {
+    let path = app.path().app_data_dir().unwrap().join("overrides").join(repository_id);
+    if path.exists() { fs::remove_dir_all(path)?; }
+    Ok(())
+}
+
+/// Applies `patch` on top of a parsed manifest and validates the result the same way a repository file would be.
+pub fn apply_override(repository: &str, filename: &str, data: &ManifestData, patch: &Value) -> Result<ManifestData, ManifestError> {
+    let mut value = match data {
+        ManifestData::Game(g) => serde_json::to_value(g),
+        ManifestData::Runner(r) => serde_json::to_value(r),
+        ManifestData::Extra(e) => serde_json::to_value(e),
+    }.unwrap();
+    merge_patch(&mut value, patch);
+
+    let patched = match data {
+        ManifestData::Game(_) => serde_path_to_error::deserialize(value).map(ManifestData::Game),
+        ManifestData::Runner(_) => serde_path_to_error::deserialize(value).map(ManifestData::Runner),
+        ManifestData::Extra(_) => serde_path_to_error::deserialize(value).map(ManifestData::Extra),
+    };
+
+    patched.map_err(|e| {
+        let path = e.path().to_string();
+        ManifestError { repository: repository.to_string(), file: filename.to_string(), path: if path == "." { "$".to_string() } else { format!("$.{}", path) }, reason: format!("User override is invalid: {}", e.inner()) }
+    })
+}
+
+/// RFC 7386 merge patch, `null` removes a field and anything that is not an object replaces the target outright.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    match patch {
+        Value::Object(p) => {
+            if !target.is_object() { *target = Value::Object(Map::new()); }
+            let t = target.as_object_mut().unwrap();
+
+            for (k, v) in p {
+                if v.is_null() {
+                    t.remove(k);
+                } else {
+                    merge_patch(t.entry(k.clone()).or_insert(Value::Null), v);
+                }
+            }
+        }
+        _ => *target = patch.clone()
+    }
+}
+
+/// JSON paths of every field a patch replaces or removes, arrays count as a single field since merge patches replace them whole.
+pub fn patch_fields(patch: &Value) -> Vec<String> {
+    let mut fields = Vec::new();
+    collect_fields(patch, "$".to_string(), &mut fields);
+    fields
+}
+
+fn collect_fields(patch: &Value, path: String, fields: &mut Vec<String>) {
+    match patch {
+        Value::Object(p) => {
+            for (k, v) in p { collect_fields(v, format!("{}.{}", path, k), fields); }
+        }
+        _ => fields.push(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn merge_patch_follows_rfc_7386() {
+        // Examples from RFC 7386 appendix A
+        let cases = [
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "b"}), json!({"b": "c"}), json!({"a": "b", "b": "c"})),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (json!({"a": "b", "b": "c"}), json!({"a": null}), json!({"b": "c"})),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+            (json!({"a": {"b": "c"}}), json!({"a": {"b": "d", "c": null}}), json!({"a": {"b": "d"}})),
+            (json!({"a": [{"b": "c"}]}), json!({"a": [1]}), json!({"a": [1]})),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+            (json!({"a": "foo"}), json!(null), json!(null)),
+            (json!({"a": "foo"}), json!("bar"), json!("bar")),
+            (json!({"e": null}), json!({"a": 1}), json!({"e": null, "a": 1})),
+            (json!([1, 2]), json!({"a": "b", "c": null}), json!({"a": "b"})),
+            (json!({}), json!({"a": {"bb": {"ccc": null}}}), json!({"a": {"bb": {}}})),
+        ];
+
+        for (mut target, patch, expected) in cases {
+            merge_patch(&mut target, &patch);
+            assert_eq!(target, expected, "patch {}", patch);
+        }
+    }
+
+    #[test]
+    fn patch_fields_lists_replaced_and_removed_paths() {
+        let patch = json!({"display_name": "Mine", "paths": {"exe_filename": "Game.exe", "screenshot_dir": null}, "telemetry_hosts": ["a", "b"]});
+        let mut fields = patch_fields(&patch);
+        fields.sort();
+
+        assert_eq!(fields, vec!["$.display_name", "$.paths.exe_filename", "$.paths.screenshot_dir", "$.telemetry_hosts"]);
+        assert!(patch_fields(&json!({})).is_empty());
+    }
+}
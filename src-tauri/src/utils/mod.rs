@@ -7,14 +7,30 @@ use fischl::download::game::{Game, Hoyo, Kuro, Sophon};
 use fischl::utils::{assemble_multipart_archive, extract_archive, KuroFile};
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, Listener, Manager};
+use tauri_plugin_notification::NotificationExt;
 use crate::utils::db_manager::{get_install_info_by_id, get_manifest_info_by_id, update_install_after_update_by_id};
-use crate::utils::repo_manager::{get_manifest, get_manifests, DiffGameFile, GameVersion};
+use crate::utils::repo_manager::{get_manifest, get_manifests, manifest_key, DiffGameFile, ExtraManifest, GameManifest, GameVersion, InstallEnvVar, LauncherInstall, LauncherManifest};
+use sha2::{Digest, Sha256};
+
+#[cfg(target_os = "linux")]
+use fischl::download::Compatibility;
+#[cfg(target_os = "linux")]
+use crate::utils::repo_manager::RunnerVersion;
+use crate::utils::git_helpers::SyncOutcome;
 
 pub mod db_manager;
 pub mod repo_manager;
 mod git_helpers;
 pub mod game_launch_manager;
 pub mod system_tray;
+pub mod manifest_lint;
+pub mod extras_manager;
+pub mod manifest_overrides;
+pub mod launcher_state;
+pub mod repo_bundle;
+
+/// How often a runner or DXVK archive is fetched again after failing verification or extraction.
+pub const COMPATIBILITY_DOWNLOAD_ATTEMPTS: usize = 3;
 
 pub fn generate_cuid() -> String {
     cuid2::create_id()
@@ -139,10 +155,12 @@ pub fn register_listeners(app: &AppHandle) {
         let h4 = h4.clone();
         std::thread::spawn(move || {
             let payload: DownloadGamePayload = serde_json::from_str(event.payload()).unwrap();
-            let install = get_install_info_by_id(&h4, payload.install).unwrap(); // Should exist by now, if not we FUCKED UP
-            let gid = payload.biz.clone() + ".json";
+            let (install, gid) = match install_with_manifest(&h4, payload.install) {
+                Ok(r) => r,
+                Err(e) => { notify_task_error(&h4, "download", e.as_str()); return; }
+            };
 
-            let mm = get_manifest(&h4, gid);
+            let mm = get_manifest(&h4, manifest_key(&gid.repository_id, &gid.filename));
             if let Some(gm) = mm {
                 let version = gm.game_versions.iter().filter(|e| e.metadata.version == install.version).collect::<Vec<&GameVersion>>();
                 let picked = version.get(0).unwrap();
@@ -223,7 +241,7 @@ pub fn register_listeners(app: &AppHandle) {
                     _ => {}
                 }
             } else {
-                println!("Failed to download game!");
+                notify_task_error(&h4, "download", format!("Manifest of {} is not available", install.name).as_str());
             }
         });
     });
@@ -234,10 +252,12 @@ pub fn register_listeners(app: &AppHandle) {
         let h5 = h5.clone();
         std::thread::spawn(move || {
             let payload: DownloadGamePayload = serde_json::from_str(event.payload()).unwrap();
-            let install = get_install_info_by_id(&h5, payload.install).unwrap(); // Should exist by now, if not we FUCKED UP
-            let gid = get_manifest_info_by_id(&h5, install.manifest_id).unwrap();
+            let (install, gid) = match install_with_manifest(&h5, payload.install) {
+                Ok(r) => r,
+                Err(e) => { notify_task_error(&h5, "update", e.as_str()); return; }
+            };
 
-            let mm = get_manifest(&h5, gid.filename);
+            let mm = get_manifest(&h5, manifest_key(&gid.repository_id, &gid.filename));
             if let Some(gm) = mm {
                 let version = gm.game_versions.iter().filter(|e| e.metadata.version == gm.latest_version).collect::<Vec<&GameVersion>>();
                 let picked = version.get(0).unwrap();
@@ -271,9 +291,13 @@ pub fn register_listeners(app: &AppHandle) {
 
                                 let nd = install.directory.clone().replace(install.version.clone().as_str(), picked.metadata.version.as_str());
                                 let np = install.runner_prefix.clone().replace(install.version.clone().as_str(), picked.metadata.version.as_str());
-                                fs::rename(install.directory.clone(), nd.clone()).unwrap();
-                                fs::rename(install.runner_prefix.clone(), np.clone()).unwrap();
-                                update_install_after_update_by_id(&h5, install.id, picked.metadata.versioned_name.clone(), picked.assets.game_icon.clone(), picked.assets.game_background.clone(), picked.metadata.version.clone(), nd, np);
+                                // Directories are only renamed together, the install keeps pointing at the old ones if either fails
+                                let moved = fs::rename(&install.directory, &nd).and_then(|_| {
+                                    if install.runner_prefix.is_empty() { return Ok(()); }
+                                    fs::rename(&install.runner_prefix, &np).inspect_err(|_| { fs::rename(&nd, &install.directory).ok(); })
+                                });
+                                let saved = moved.map_err(|e| e.to_string()).and_then(|_| update_install_after_update_by_id(&h5, install.id.clone(), picked.metadata.versioned_name.clone(), picked.assets.game_icon.clone(), picked.assets.game_background.clone(), picked.metadata.version.clone(), nd, np).map_err(|e| e.to_string()));
+                                if let Err(e) = saved { notify_task_error(&h5, "save updated", e.as_str()); }
                             }
                         }
                     }
@@ -285,7 +309,7 @@ pub fn register_listeners(app: &AppHandle) {
                     _ => {}
                 }
             } else {
-                println!("Failed to update game!");
+                notify_task_error(&h5, "update", format!("Manifest of {} is not available", install.name).as_str());
             }
         });
     });
@@ -296,9 +320,14 @@ pub fn register_listeners(app: &AppHandle) {
         let h5 = h5.clone();
         std::thread::spawn(move || {
             let payload: DownloadGamePayload = serde_json::from_str(event.payload()).unwrap();
-            let install = get_install_info_by_id(&h5, payload.install); // Should exist by now, if not we FUCKED UP
-            let lm = get_manifest_info_by_id(&h5, install.clone().unwrap().manifest_id.clone()).unwrap();
-            let gm = get_manifest(&h5, lm.filename).unwrap();
+            let (install, lm) = match install_with_manifest(&h5, payload.install) {
+                Ok((i, lm)) => (Some(i), lm),
+                Err(e) => { notify_task_error(&h5, "repair", e.as_str()); return; }
+            };
+            let gm = match get_manifest(&h5, manifest_key(&lm.repository_id, &lm.filename)) {
+                Some(gm) => gm,
+                None => { notify_task_error(&h5, "repair", format!("Manifest {} is not available", lm.filename).as_str()); return; }
+            };
 
             if install.is_some() { 
                 let i = install.unwrap();
@@ -383,57 +412,97 @@ pub fn register_listeners(app: &AppHandle) {
     });
 }
 
+/// Download, update and repair run on their own threads with nobody waiting on them, failures can only be shown as a notification.
+fn notify_task_error(app: &AppHandle, action: &str, reason: &str) {
+    app.notification().builder().icon("dialog-error").title("TwintailLauncher").body(format!("Failed to {} game! {}", action, reason)).show().unwrap();
+}
+
+/// Looks up an installation and its manifest row for the download, update and repair listeners.
+fn install_with_manifest(app: &AppHandle, id: String) -> Result<(LauncherInstall, LauncherManifest), String> {
+    let install = get_install_info_by_id(app, id.clone()).map_err(|e| e.to_string())?.ok_or(format!("Installation {} does not exist", id))?;
+    let manifest = get_manifest_info_by_id(app, install.manifest_id.clone()).map_err(|e| e.to_string())?.ok_or(format!("Manifest {} does not exist", install.manifest_id))?;
+    Ok((install, manifest))
+}
+
+/// Downloads a runner (or DXVK when `dxvk` is set) version, checks it against the size and hash from its manifest and extracts it.
+/// Everything happens in a sibling `.partial` directory that is only renamed to `dest` once complete, so a crash never leaves a half extracted runner that looks installed.
 #[cfg(target_os = "linux")]
-pub fn runner_from_runner_version(runner_version: String) -> Option<String> {
-    let mut rslt = String::new();
+pub fn install_compatibility(version: &RunnerVersion, dest: &Path, dxvk: bool) -> Result<(), String> {
+    let staging = dest.with_file_name(format!(".{}.partial", dest.file_name().unwrap().to_string_lossy()));
+    let archive = staging.join(if dxvk { "dxvk.zip" } else { "runner.zip" });
+    let mut last_error = String::new();
+
+    for _ in 0..COMPATIBILITY_DOWNLOAD_ATTEMPTS {
+        if staging.exists() { fs::remove_dir_all(&staging).map_err(|e| e.to_string())?; }
+        fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+
+        let dl = if dxvk { Compatibility::download_dxvk(version.url.clone(), staging.to_str().unwrap().to_string()) } else { Compatibility::download_runner(version.url.clone(), staging.to_str().unwrap().to_string()) };
+
+        last_error = if !dl {
+            format!("Failed to download {}", version.version)
+        } else if let Err(e) = verify_download(version, &archive) {
+            e
+        } else if !extract_archive(archive.to_str().unwrap().to_string(), staging.to_str().unwrap().to_string(), true) {
+            format!("Failed to extract {}", version.version)
+        } else {
+            if archive.exists() { fs::remove_file(&archive).ok(); }
+            if dest.exists() { fs::remove_dir_all(dest).map_err(|e| e.to_string())?; }
+            return fs::rename(&staging, dest).map_err(|e| e.to_string());
+        };
 
-    if runner_version.is_empty() {
-        None
-    } else {
-        if runner_version.contains("vanilla") {
-            rslt = "dxvk_vanilla.json".to_string();
-        }
-        if runner_version.contains("async") {
-            rslt = "dxvk_async.json".to_string();
-        }
-        if runner_version.contains("gplasync") {
-            rslt = "dxvk_gplasync.json".to_string();
-        }
-        if runner_version.contains("wine-vanilla") {
-            rslt = "wine_vanilla.json".to_string();
-        }
-        if runner_version.contains("wine-staging") {
-            rslt = "wine_staging.json".to_string();
-        }
-        if runner_version.contains("wine-staging-tkg") {
-            rslt = "wine_staging_tkg.json".to_string();
-        }
-        if runner_version.contains("wine-vaniglia") {
-            rslt = "wine_vaniglia.json".to_string();
-        }
-        if runner_version.contains("wine-soda") {
-            rslt = "wine_soda.json".to_string();
-        }
-        if runner_version.contains("wine-lutris") {
-            rslt = "wine_lutris.json".to_string();
-        }
-        if runner_version.contains("wine-ge-proton") {
-            rslt = "wine_ge_proton.json".to_string();
-        }
-        if runner_version.contains("wine-caffe") {
-            rslt = "wine_caffe.json".to_string();
-        }
-        if runner_version.contains("proton-ge") {
-            rslt = "proton_ge.json".to_string();
-        }
-        if runner_version.contains("proton-cachyos") {
-            rslt = "proton_cachyos.json".to_string();
-        }
-        if runner_version.contains("proton-umu") {
-            rslt = "proton_umu.json".to_string();
+        #[cfg(debug_assertions)]
+        { println!("{}, retrying", last_error); }
+    }
+
+    fs::remove_dir_all(&staging).ok();
+    Err(format!("{} after {} attempts", last_error, COMPATIBILITY_DOWNLOAD_ATTEMPTS))
+}
+
+#[cfg(target_os = "linux")]
+fn verify_download(version: &RunnerVersion, archive: &Path) -> Result<(), String> {
+    if let Some(size) = version.size {
+        let actual = fs::metadata(archive).map_err(|e| e.to_string())?.len();
+        if actual != size { return Err(format!("Size mismatch for {}, expected {} bytes got {}", version.version, size, actual)); }
+    }
+
+    match &version.sha256 {
+        Some(expected) => {
+            let actual = sha256_file(archive).map_err(|e| e.to_string())?;
+            if !actual.eq_ignore_ascii_case(expected) { return Err(format!("Checksum mismatch for {}, expected {} got {}", version.version, expected, actual)); }
+        },
+        None => {
+            #[cfg(debug_assertions)]
+            { println!("{} has no sha256 in its manifest, installing it unverified", version.version); }
         }
-        Some(rslt)
     }
+    Ok(())
+}
+
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Parses the old `KEY=value;KEY2="value 2";` format, quotes are dropped like the launcher always did.
+/// Entries without a name were never passed to the game, they are kept disabled as a whole so the user can see and fix them.
+pub fn parse_legacy_env_vars(env_vars: &str) -> Vec<InstallEnvVar> {
+    let mut vars: Vec<InstallEnvVar> = Vec::new();
+    for entry in env_vars.split(';').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+        let var = match entry.split_once('=') {
+            Some((k, v)) if !k.trim().is_empty() => InstallEnvVar { key: k.trim().to_string(), value: v.replace('"', ""), enabled: true },
+            _ => InstallEnvVar { key: entry.to_string(), value: String::new(), enabled: false }
+        };
+        // Later entries used to win since they were applied last
+        vars.retain(|e| e.key != var.key);
+        vars.push(var);
+    }
+    vars
+}
+
+/// Splits the old space separated launch arguments the way bash would have, unbalanced quotes fall back to plain whitespace splitting.
+pub fn parse_legacy_launch_args(args: &str) -> Vec<String> {
+    shlex::split(args).unwrap_or_else(|| args.split_whitespace().map(|a| a.to_string()).collect())
 }
 
 pub fn get_mi_path_from_game(exe_name: String) -> Option<String> {
@@ -471,6 +540,141 @@ pub struct AddInstallRsp {
     pub background: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RemoveRepositoryRsp {
+    pub success: bool,
+    pub repository_id: String,
+    pub blocking_installs: Vec<String>,
+    pub removed_installs: Vec<String>,
+    pub removed_manifests: Vec<String>,
+    pub removed_path: Option<String>,
+}
+
+/// Something from a state bundle that could not be imported, `id` is the repository or install it belongs to.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportIssue {
+    pub id: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ImportStateRsp {
+    pub success: bool,
+    pub added_repositories: Vec<String>,
+    pub failed_repositories: Vec<ImportIssue>,
+    pub adopted_installs: Vec<String>,
+    pub skipped_installs: Vec<ImportIssue>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SyncedRepository {
+    pub repository_id: String,
+    pub github_id: String,
+    pub from_commit: Option<String>,
+    pub to_commit: Option<String>,
+    pub outcome: SyncOutcome,
+}
+
+/// Game manifest together with the database entry it belongs to, file names are not unique across repositories.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameManifestRsp {
+    /// Loader key `<repository id>/<file name>`, unique where `biz` is not once several repositories ship the same game
+    pub key: String,
+    pub manifest_id: String,
+    pub repository_id: String,
+    pub manifest_enabled: bool,
+    pub manifest_file: String,
+    #[serde(flatten)]
+    pub manifest: GameManifest,
+}
+
+/// Payload of `manifests_reloaded`, loader keys (`<repository id>/<file name>`) grouped by what happened to them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ManifestsReloaded {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ManifestsReloaded {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Game manifest as loaded, with the JSON paths user overrides replaced.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OverriddenGameManifestRsp {
+    #[serde(flatten)]
+    pub manifest: GameManifest,
+    pub overridden_fields: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestOverrideRsp {
+    pub manifest_id: String,
+    pub repository_id: String,
+    pub filename: String,
+    pub patch: serde_json::Value,
+    pub fields: Vec<String>,
+    /// False when the override no longer fits the manifest, see `list_manifest_errors`
+    pub applied: bool,
+}
+
+/// Extra manifest with the version currently installed from it, if any.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExtraManifestRsp {
+    pub manifest_id: String,
+    pub repository_id: String,
+    pub installed_version: Option<String>,
+    #[serde(flatten)]
+    pub manifest: ExtraManifest,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PreviewManifest {
+    pub filename: String,
+    pub display_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestError {
+    pub repository: String,
+    pub file: String,
+    /// JSON path of the offending field, `$` when the whole file is unusable
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RepositoryPreviewRsp {
+    pub preview_id: String,
+    pub github_id: String,
+    pub remote: String,
+    pub name: String,
+    pub description: String,
+    pub maintainers: Vec<String>,
+    pub homepage: Option<String>,
+    pub icon: Option<String>,
+    pub min_launcher_version: Option<String>,
+    pub games: Vec<PreviewManifest>,
+    pub runners: Vec<PreviewManifest>,
+    pub extras: Vec<PreviewManifest>,
+    /// Manifests that share a filename with one that is already loaded
+    pub collisions: Vec<String>,
+    pub invalid_manifests: Vec<ManifestError>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RepoSyncProgress {
+    pub repository: String,
+    pub received_objects: usize,
+    pub total_objects: usize,
+    pub received_bytes: usize,
+    pub indexed_deltas: usize,
+    pub total_deltas: usize,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DownloadGamePayload {
     pub install: String,
@@ -484,4 +688,66 @@ pub struct DownloadSizesRsp {
     pub free_disk_space: String,
     pub game_decompressed_size_raw: u64,
     pub free_disk_space_raw: u64
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    fn runner_version(size: Option<u64>, sha256: Option<&str>) -> RunnerVersion {
+        RunnerVersion { version: "9.0-1".to_string(), url: String::new(), sha256: sha256.map(|s| s.to_string()), size, release_date: None, recommended: false, deprecated: false }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn verify_download_checks_size_and_hash() {
+        let archive = std::env::temp_dir().join(format!("keqing-verify-{}.zip", std::process::id()));
+        fs::write(&archive, b"runner").unwrap();
+        // sha256 of "runner"
+        let digest = "527aa9f431539da8e151d5434d1d5e611d973f601d8e970790882624554146b0";
+
+        assert!(verify_download(&runner_version(None, None), &archive).is_ok());
+        assert!(verify_download(&runner_version(Some(6), Some(digest)), &archive).is_ok());
+        assert!(verify_download(&runner_version(Some(6), Some(digest.to_uppercase().as_str())), &archive).is_ok());
+        assert!(verify_download(&runner_version(Some(7), Some(digest)), &archive).unwrap_err().starts_with("Size mismatch"));
+        assert!(verify_download(&runner_version(None, Some(&"0".repeat(64))), &archive).unwrap_err().starts_with("Checksum mismatch"));
+
+        fs::remove_file(&archive).unwrap();
+        assert!(verify_download(&runner_version(Some(6), None), &archive).is_err());
+    }
+
+    fn env_vars(raw: &str) -> Vec<(String, String, bool)> {
+        parse_legacy_env_vars(raw).into_iter().map(|e| (e.key, e.value, e.enabled)).collect()
+    }
+
+    #[test]
+    fn legacy_env_vars_are_split_and_unquoted() {
+        assert_eq!(env_vars(r#"DXVK_HUD=fps;WINEDLLOVERRIDES="dxgi=n,b"; PATH_WITH_SPACES="/home/user/my games";"#), vec![
+            ("DXVK_HUD".to_string(), "fps".to_string(), true),
+            ("WINEDLLOVERRIDES".to_string(), "dxgi=n,b".to_string(), true),
+            ("PATH_WITH_SPACES".to_string(), "/home/user/my games".to_string(), true),
+        ]);
+        assert!(parse_legacy_env_vars("").is_empty());
+        assert!(parse_legacy_env_vars(" ; ;").is_empty());
+    }
+
+    #[test]
+    fn legacy_env_vars_keep_the_last_duplicate() {
+        assert_eq!(env_vars("A=1;B=2;A=3;"), vec![("B".to_string(), "2".to_string(), true), ("A".to_string(), "3".to_string(), true)]);
+        assert_eq!(env_vars("EMPTY=;"), vec![("EMPTY".to_string(), String::new(), true)]);
+    }
+
+    #[test]
+    fn legacy_env_vars_without_a_name_are_kept_disabled() {
+        assert_eq!(env_vars("DXVK_HUD;A=1;=orphan;"), vec![("DXVK_HUD".to_string(), String::new(), false), ("A".to_string(), "1".to_string(), true), ("=orphan".to_string(), String::new(), false)]);
+    }
+
+    #[test]
+    fn legacy_launch_args_are_split_like_a_shell() {
+        assert_eq!(parse_legacy_launch_args("-dx11 -screen-fullscreen 0"), vec!["-dx11", "-screen-fullscreen", "0"]);
+        assert_eq!(parse_legacy_launch_args(r#"-log "C:\my games\log.txt" 'single quoted'"#), vec!["-log", r"C:\my games\log.txt", "single quoted"]);
+        assert_eq!(parse_legacy_launch_args("  "), Vec::<String>::new());
+        // Unbalanced quotes can not be parsed by shlex, fall back to whitespace so nothing is lost
+        assert_eq!(parse_legacy_launch_args(r#"-name "unterminated value"#), vec!["-name", "\"unterminated", "value"]);
+    }
+}
@@ -0,0 +1,243 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use fischl::utils::extract_archive;
+use reqwest::blocking::Client;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::utils::sha256_file;
+use crate::utils::repo_manager::RepositoryError;
+
+/// `index.json` published next to a repository bundle, it is the only thing polled on every sync.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BundleIndex {
+    /// Opaque identifier of the bundle contents, must change whenever the bundle does
+    pub revision: String,
+    /// Archive containing `repository.json` and its manifests at the root, relative urls are resolved against the index
+    pub bundle: String,
+    pub sha256: Option<String>,
+}
+
+/// Body of a fetched url along with the validators to send on the next conditional request.
+pub struct Fetched {
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Bundle repositories are added by pointing at their index, git remotes never end in `.json`.
+pub fn is_bundle_url(url: &str) -> bool {
+    Url::parse(url.trim()).map(|u| ["http", "https", "file"].contains(&u.scheme()) && u.path().ends_with(".json")).unwrap_or(false)
+}
+
+pub fn parse_index_url(url: &str) -> Result<Url, RepositoryError> {
+    let parsed = Url::parse(url.trim()).map_err(|e| RepositoryError::InvalidUrl(e.to_string()))?;
+    if !is_bundle_url(parsed.as_str()) { return Err(RepositoryError::InvalidUrl(format!("{} is not a repository index", parsed))); }
+    Ok(parsed)
+}
+
+/// Short identifier shown in the UI, host followed by the directory the index lives in.
+pub fn bundle_display_id(index: &Url) -> String {
+    let mut segments = index.path_segments().map(|s| s.filter(|p| !p.is_empty()).collect::<Vec<&str>>()).unwrap_or_default();
+    segments.pop();
+    format!("{}/{}", index.host_str().unwrap_or("local"), segments.join("/"))
+}
+
+/// Fetches `url`, `None` means the server confirmed the copy identified by `etag`/`last_modified` is still current.
+/// `file://` urls use the modification time in place of Last-Modified so local mirrors behave the same way.
+pub fn fetch(url: &Url, etag: Option<&str>, last_modified: Option<&str>) -> Result<Option<Fetched>, RepositoryError> {
+    if url.scheme() == "file" {
+        let path = url.to_file_path().map_err(|_| RepositoryError::InvalidUrl(url.to_string()))?;
+        let meta = fs::metadata(&path).map_err(|e| RepositoryError::Network(format!("{}: {}", path.display(), e)))?;
+        let modified = meta.modified().ok().and_then(|m| m.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs().to_string());
+        if modified.is_some() && modified.as_deref() == last_modified { return Ok(None); }
+
+        let body = fs::read(&path).map_err(|e| RepositoryError::Network(format!("{}: {}", path.display(), e)))?;
+        return Ok(Some(Fetched { body, etag: None, last_modified: modified }));
+    }
+
+    // reqwest picks up HTTP(S)_PROXY and NO_PROXY from the environment, which is most of the point of this transport
+    let client = Client::builder().user_agent(concat!("TwintailLauncher/", env!("CARGO_PKG_VERSION"))).timeout(Duration::from_secs(300)).build().map_err(|e| RepositoryError::Network(e.to_string()))?;
+    let mut req = client.get(url.as_str());
+    if let Some(e) = etag { req = req.header(IF_NONE_MATCH, e); }
+    if let Some(m) = last_modified { req = req.header(IF_MODIFIED_SINCE, m); }
+
+    let rsp = req.send().map_err(|e| RepositoryError::Network(e.to_string()))?;
+    let status = rsp.status();
+    if status == StatusCode::NOT_MODIFIED { return Ok(None); }
+    if status.as_u16() == 401 || status.as_u16() == 403 { return Err(RepositoryError::AuthFailed(format!("{} answered {}", url, status))); }
+    if !status.is_success() { return Err(RepositoryError::Network(format!("{} answered {}", url, status))); }
+
+    let etag = rsp.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+    let last_modified = rsp.headers().get(LAST_MODIFIED).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+    let body = rsp.bytes().map_err(|e| RepositoryError::Network(e.to_string()))?.to_vec();
+    Ok(Some(Fetched { body, etag, last_modified }))
+}
+
+pub fn parse_index(body: &[u8]) -> Result<BundleIndex, RepositoryError> {
+    serde_json::from_slice(body).map_err(|e| RepositoryError::Other(format!("Invalid index.json: {}", e)))
+}
+
+/// Downloads and verifies the bundle an index points to and extracts it into `work/contents`, returning that directory.
+/// `work` is wiped first and the caller is expected to remove it once the contents are moved into place.
+pub fn unpack_bundle(index_url: &Url, index: &BundleIndex, work: &Path) -> Result<PathBuf, RepositoryError> {
+    let bundle_url = index_url.join(index.bundle.as_str()).map_err(|e| RepositoryError::InvalidUrl(format!("{}: {}", index.bundle, e)))?;
+    // A remote index has no business pointing at files on this machine
+    if bundle_url.scheme() == "file" && index_url.scheme() != "file" { return Err(RepositoryError::InvalidUrl(format!("{} points at a local file", index_url))); }
+
+    let file_name = bundle_url.path_segments().and_then(|mut s| s.next_back()).filter(|n| !n.is_empty()).ok_or(RepositoryError::InvalidUrl(format!("{} has no file name", bundle_url)))?.to_string();
+    if work.exists() { fs::remove_dir_all(work).map_err(|e| RepositoryError::Other(e.to_string()))?; }
+    fs::create_dir_all(work).map_err(|e| RepositoryError::Other(e.to_string()))?;
+
+    let fetched = fetch(&bundle_url, None, None)?.ok_or(RepositoryError::Network(format!("{} returned no content", bundle_url)))?;
+    let archive = work.join(&file_name);
+    fs::write(&archive, &fetched.body).map_err(|e| RepositoryError::Other(e.to_string()))?;
+
+    match &index.sha256 {
+        Some(expected) => {
+            let actual = sha256_file(&archive).map_err(|e| RepositoryError::Other(e.to_string()))?;
+            if !actual.eq_ignore_ascii_case(expected) { return Err(RepositoryError::Other(format!("Checksum mismatch for {}, expected {} got {}", file_name, expected, actual))); }
+        },
+        None => {
+            #[cfg(debug_assertions)]
+            { println!("Bundle index {} has no sha256, unpacking {} unverified", index_url, file_name); }
+        }
+    }
+
+    let contents = work.join("contents");
+    fs::create_dir_all(&contents).map_err(|e| RepositoryError::Other(e.to_string()))?;
+    if !extract_archive(archive.to_str().unwrap().to_string(), contents.to_str().unwrap().to_string(), false) {
+        return Err(RepositoryError::Other(format!("Failed to extract {}", file_name)));
+    }
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("keqing-bundle-{}-{}", name, std::process::id()));
+        if dir.exists() { fs::remove_dir_all(&dir).unwrap(); }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Answers one connection per response on a loopback port and hands back the requests it got.
+    fn serve(responses: Vec<&'static str>) -> (Url, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/repo/index.json", listener.local_addr().unwrap())).unwrap();
+
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).unwrap();
+                    if n == 0 { break; }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                requests.push(String::from_utf8_lossy(&request).to_lowercase());
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn index_urls_are_recognized() {
+        assert!(is_bundle_url("https://example.com/repo/index.json"));
+        assert!(is_bundle_url("file:///srv/mirror/index.json"));
+        assert!(!is_bundle_url("https://github.com/owner/repo.git"));
+        assert!(!is_bundle_url("ssh://example.com/index.json"));
+        assert!(matches!(parse_index_url("owner/repo"), Err(RepositoryError::InvalidUrl(_))));
+
+        assert_eq!(bundle_display_id(&Url::parse("https://example.com/mirrors/keqing/index.json").unwrap()), "example.com/mirrors/keqing");
+    }
+
+    #[test]
+    fn http_fetch_sends_validators_and_handles_not_modified() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 200 OK\r\nETag: \"r1\"\r\nLast-Modified: Sun, 18 Oct 2026 10:00:00 GMT\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}",
+            "HTTP/1.1 304 Not Modified\r\nETag: \"r1\"\r\nConnection: close\r\n\r\n",
+        ]);
+
+        let fetched = fetch(&url, None, None).unwrap().unwrap();
+        assert_eq!(fetched.body, b"{}");
+        assert_eq!(fetched.etag.as_deref(), Some("\"r1\""));
+        assert_eq!(fetched.last_modified.as_deref(), Some("Sun, 18 Oct 2026 10:00:00 GMT"));
+
+        assert!(fetch(&url, fetched.etag.as_deref(), fetched.last_modified.as_deref()).unwrap().is_none());
+
+        let requests = server.join().unwrap();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"r1\""));
+        assert!(requests[1].contains("if-modified-since: sun, 18 oct 2026 10:00:00 gmt"));
+    }
+
+    #[test]
+    fn http_fetch_classifies_failures() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+
+        assert!(matches!(fetch(&url, None, None), Err(RepositoryError::AuthFailed(_))));
+        assert!(matches!(fetch(&url, None, None), Err(RepositoryError::Network(_))));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn file_fetch_uses_modification_time_as_validator() {
+        let dir = temp_dir("file");
+        let index = dir.join("index.json");
+        fs::write(&index, b"{\"revision\": \"1\", \"bundle\": \"bundle.zip\"}").unwrap();
+        let url = Url::from_file_path(&index).unwrap();
+
+        let fetched = fetch(&url, None, None).unwrap().unwrap();
+        assert_eq!(parse_index(&fetched.body).unwrap().revision, "1");
+        assert!(fetched.last_modified.is_some());
+        assert!(fetch(&url, None, fetched.last_modified.as_deref()).unwrap().is_none());
+        assert!(fetch(&url, None, Some("0")).unwrap().is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(fetch(&url, None, None), Err(RepositoryError::Network(_))));
+    }
+
+    #[test]
+    fn unpack_bundle_verifies_and_extracts() {
+        let dir = temp_dir("unpack");
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/repo_bundle/bundle.zip");
+        fs::copy(&fixture, dir.join("bundle.zip")).unwrap();
+        let index_url = Url::from_file_path(dir.join("index.json")).unwrap();
+        let sha256 = sha256_file(&fixture).unwrap();
+
+        let index = BundleIndex { revision: "1".to_string(), bundle: "bundle.zip".to_string(), sha256: Some(sha256.to_uppercase()) };
+        let contents = unpack_bundle(&index_url, &index, &dir.join("work")).unwrap();
+        assert!(contents.join("repository.json").exists());
+        assert!(contents.join("runner.json").exists());
+
+        let index = BundleIndex { sha256: Some("0".repeat(64)), ..index };
+        match unpack_bundle(&index_url, &index, &dir.join("work")) {
+            Err(RepositoryError::Other(m)) => assert!(m.starts_with("Checksum mismatch"), "{m}"),
+            other => panic!("unexpected {:?}", other.map(|p| p.display().to_string()))
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn remote_index_can_not_point_at_local_files() {
+        let index = BundleIndex { revision: "1".to_string(), bundle: "file:///etc/passwd".to_string(), sha256: None };
+        let rslt = unpack_bundle(&Url::parse("https://example.com/repo/index.json").unwrap(), &index, &std::env::temp_dir().join("keqing-bundle-never"));
+        assert!(matches!(rslt, Err(RepositoryError::InvalidUrl(_))));
+    }
+}
@@ -1,258 +1,985 @@
 use std::fs;
 use std::io::BufReader;
-use std::path::{PathBuf};
-use std::sync::{RwLock};
-use git2::{Error, Repository};
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use git2::{Config, Cred, CredentialType, Error, ErrorClass, ErrorCode, RemoteCallbacks, Repository};
 use linked_hash_map::LinkedHashMap;
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Manager};
-use crate::utils::db_manager::{create_manifest, create_repository, get_manifest_info_by_filename, get_repository_info_by_github_id};
-use crate::utils::{generate_cuid};
-use crate::utils::git_helpers::{do_fetch, do_merge};
+use tauri::{AppHandle, Emitter, Manager};
+use url::Url;
+use tauri_plugin_notification::NotificationExt;
+use crate::utils::db_manager::{create_bundle_repository, create_local_repository, create_repository, get_repository_info_by_id, update_bundle_repository_by_id, update_repository_bundle_state_by_id, delete_manifest_by_id, get_installs_by_manifest_id, upsert_manifest, get_manifest_info_by_repository_and_filename, get_manifests_by_filename, get_manifests_info, get_repositories, get_repository_credentials_by_repository_id, get_settings, get_repository_info_by_remote, update_repository_metadata_by_id, update_repository_previous_commit_by_id, update_repository_ref_by_id, DbError};
+use crate::commands::settings::GlobalSettings;
+use crate::utils::{copy_dir_all, generate_cuid, ManifestError, ManifestsReloaded, PreviewManifest, RepoSyncProgress, RepositoryPreviewRsp, SyncedRepository};
+use crate::utils::manifest_overrides::{apply_override, patch_fields, read_override};
+use crate::utils::repo_bundle::{bundle_display_id, BundleIndex, fetch, is_bundle_url, parse_index, parse_index_url, unpack_bundle};
+use crate::utils::git_helpers::{clone_repository, get_head_branch, get_head_commit, get_origin_url, sync_tracked_ref, RepositoryUrl, SyncOutcome};
+
+pub const OFFICIAL_GAME_REPOSITORY: &str = "https://github.com/AndigenaTeam/game-manifests.git";
+pub const OFFICIAL_RUNNER_REPOSITORY: &str = "https://github.com/AndigenaTeam/runner-manifests.git";
+/// Highest manifest `version` this launcher knows how to read.
+pub const MANIFEST_SCHEMA_VERSION: i32 = 1;
 
 pub fn setup_official_repository(app: &AppHandle, path: &PathBuf) {
-    let url = "https://github.com/AndigenaTeam/game-manifests.git";
+    setup_builtin_repository(app, path, OFFICIAL_GAME_REPOSITORY);
+}
+
+/// Clones or syncs one of the repositories the launcher ships with and registers it if that did not happen yet.
+/// Whatever keeps it from being registered is shown to the user, the next start tries again after syncing.
+fn setup_builtin_repository(app: &AppHandle, path: &PathBuf, url: &str) {
+    let parsed = RepositoryUrl::parse(url).unwrap();
+    let repo_path = path.join(parsed.local_path());
+    if !path.exists() { return; }
+
+    let repo = if !repo_path.exists() {
+        match clone_repository(parsed.remote.as_str(), &repo_path, remote_callbacks(app, parsed.display_id(), None)) {
+            Ok(r) => r,
+            Err(e) => {
+                discard_clone(&repo_path);
+                notify_repository_error(app, format!("Failed to clone {}: {}", parsed.display_id(), RepositoryError::from(e)));
+                return;
+            }
+        }
+    } else {
+        if let Err(_e) = update_repositories(app, &repo_path) {
+            #[cfg(debug_assertions)]
+            { println!("Failed to sync {}: {}", parsed.display_id(), _e); }
+        }
+        match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => {
+                notify_repository_error(app, format!("Failed to open {}: {}", parsed.display_id(), e.message()));
+                return;
+            }
+        }
+    };
+
+    match get_repository_info_by_remote(app, parsed.remote.clone()) {
+        Ok(None) => {}
+        Ok(Some(_)) => return,
+        Err(e) => {
+            notify_repository_error(app, format!("Failed to set up {}: {}", parsed.display_id(), e));
+            return;
+        }
+    }
+
+    let branch = get_head_branch(&repo).unwrap_or("main".to_string());
+    let rslt = parse_repository_manifest(&repo_path)
+        .and_then(|rma| check_launcher_version(app, &rma).map(|_| rma))
+        .and_then(|rma| register_repository(app, &parsed, &repo_path, branch, &rma, None).map_err(|e| e.to_string()));
+    match rslt {
+        Ok((_, skipped)) => notify_skipped_manifests(app, &parsed.display_id(), &skipped),
+        Err(e) => notify_repository_error(app, format!("Failed to set up {}: {}", parsed.display_id(), e))
+    }
+}
+
+fn notify_repository_error(app: &AppHandle, body: String) {
+    app.notification().builder().icon("dialog-error").title("TwintailLauncher").body(body).show().unwrap();
+}
 
-    let tmp = url.split("/").collect::<Vec<&str>>()[4];
-    let user = url.split("/").collect::<Vec<&str>>()[3];
-    let repo_name = tmp.split(".").collect::<Vec<&str>>()[0];
+/// Manifests that failed to parse are not registered, they are picked up by [`load_manifests`] once fixed upstream.
+fn notify_skipped_manifests(app: &AppHandle, repository: &str, skipped: &[ManifestError]) {
+    if skipped.is_empty() { return; }
+    let files = skipped.iter().map(|e| format!("{} ({}: {})", e.file, e.path, e.reason)).collect::<Vec<String>>().join(", ");
+    notify_repository_error(app, format!("{} contains invalid manifests that were skipped: {}", repository, files));
+}
 
-    let repo_path = path.join(format!("{}/{}", user, repo_name).as_str());
+pub fn clone_new_repository(app: &AppHandle, path: &PathBuf, url: String, credentials: Option<RepositoryCredentials>) -> Result<bool, RepositoryError> {
+    if is_bundle_url(url.as_str()) {
+        if credentials.is_some() { return Err(RepositoryError::Other("Credentials are only supported for git repositories!".to_string())); }
+        return add_bundle_repository(app, url);
+    }
+
+    let parsed = RepositoryUrl::parse(url.as_str()).map_err(|e| RepositoryError::InvalidUrl(e.message().to_string()))?;
+    let repo_path = path.join(parsed.local_path());
     let repo_manifest = repo_path.join("repository.json");
 
     if !path.exists() {
-        return;
-    } else if !repo_path.exists() {
-        Repository::clone(url, &repo_path).unwrap();
-        
-        if repo_manifest.exists() {
-            let rm = fs::File::open(&repo_manifest).unwrap();
-            let reader = BufReader::new(rm);
-            let rma: RepositoryManifest = serde_json::from_reader(reader).unwrap();
+        Err(RepositoryError::Other("Manifests directory does not exist!".to_string()))
+    } else if get_repository_info_by_remote(app, parsed.remote.clone())?.is_some() {
+        Err(RepositoryError::AlreadyAdded)
+    } else {
+        // Leftover from a failed or removed clone that never made it into the database
+        if repo_path.exists() { fs::remove_dir_all(&repo_path).map_err(|e| RepositoryError::Other(format!("Failed to remove {}: {}", repo_path.display(), e)))?; }
+
+        let repo = match clone_repository(parsed.remote.as_str(), &repo_path, remote_callbacks(app, parsed.display_id(), credentials.clone())) {
+            Ok(r) => r,
+            Err(e) => {
+                discard_clone(&repo_path);
+                return Err(RepositoryError::from(e));
+            }
+        };
 
-            let repo_id = generate_cuid();
-            create_repository(app, repo_id.clone(), format!("{user}/{repo_name}").as_str()).unwrap();
+        if repo_manifest.exists() {
+            let branch = get_head_branch(&repo).unwrap_or("main".to_string());
+            let rma: RepositoryManifest = match fs::File::open(&repo_manifest).map_err(|e| e.to_string()).and_then(|f| serde_json::from_reader(BufReader::new(f)).map_err(|e| e.to_string())) {
+                Ok(rma) => rma,
+                Err(e) => {
+                    discard_clone(&repo_path);
+                    return Err(RepositoryError::Other(format!("Invalid repository.json: {}", e)));
+                }
+            };
 
-            for m in rma.manifests {
-                let mf = fs::File::open(&repo_path.join(&m.as_str())).unwrap();
-                let reader = BufReader::new(mf);
-                let mi: GameManifest = serde_json::from_reader(reader).unwrap();
+            if let Err(msg) = check_launcher_version(app, &rma) {
+                discard_clone(&repo_path);
+                return Err(RepositoryError::LauncherTooOld(msg));
+            }
 
-                let cuid = generate_cuid();
-                create_manifest(app, cuid.clone(), repo_id.clone(), mi.display_name.as_str(), m.as_str(), true).unwrap();
+            match register_repository(app, &parsed, &repo_path, branch, &rma, credentials) {
+                Ok((_, skipped)) => notify_skipped_manifests(app, &parsed.display_id(), &skipped),
+                Err(e) => {
+                    discard_clone(&repo_path);
+                    return Err(e.into());
+                }
             }
+            Ok(true)
+        } else {
+            #[cfg(debug_assertions)]
+            { println!("Cannot clone repository! Not a valid repository?"); }
+            discard_clone(&repo_path);
 
-            ()
+            Err(RepositoryError::NotLauncherRepository)
         }
-    } else {
+    }
+}
+
+/// Best effort cleanup of a clone on an error path, the error that got us there is what the user needs to see.
+fn discard_clone(path: &Path) {
+    if !path.exists() { return; }
+    if let Err(_e) = fs::remove_dir_all(path) {
         #[cfg(debug_assertions)]
-        { println!("Official game repository is already cloned!"); }
-        update_repositories(&repo_path).unwrap();
+        { println!("Failed to clean up {}: {}", path.display(), _e); }
     }
 }
 
-pub fn clone_new_repository(app: &AppHandle, path: &PathBuf, url: String) -> Result<bool, Error> {
-    let tmp = url.split("/").collect::<Vec<&str>>()[4];
-    let user = url.split("/").collect::<Vec<&str>>()[3];
-    let repo_name = tmp.split(".").collect::<Vec<&str>>()[0];
+/// Registers a directory containing a `repository.json` as a repository that is read in place, it is never copied, synced or deleted by the launcher.
+pub fn add_local_repository(app: &AppHandle, path: String) -> Result<bool, RepositoryError> {
+    let dir = fs::canonicalize(path.trim()).map_err(|e| RepositoryError::InvalidUrl(format!("{}: {}", path, e)))?;
+    if !dir.is_dir() { return Err(RepositoryError::InvalidUrl(format!("{} is not a directory", dir.display()))); }
 
-    let repo_path = path.join(format!("{}/{}", user, repo_name).as_str());
-    let repo_manifest = repo_path.join("repository.json");
+    let rma = read_repository_manifest(&dir).ok_or(RepositoryError::NotLauncherRepository)?;
+    check_launcher_version(app, &rma).map_err(RepositoryError::LauncherTooOld)?;
 
-    if !path.exists() {
-        Ok(false)
-    } else if !repo_path.exists() {
-       let repo = Repository::clone(url.as_str(), &repo_path);
+    let remote = local_repository_remote(&dir);
+    if get_repository_info_by_remote(app, remote.clone())?.is_some() { return Err(RepositoryError::AlreadyAdded); }
 
-        if repo_manifest.exists() && repo.is_ok() {
-            let rm = fs::File::open(&repo_manifest).unwrap();
-            let reader = BufReader::new(rm);
-            let rma: RepositoryManifest = serde_json::from_reader(reader).unwrap();
+    let repo_id = generate_cuid();
+    let display_id = format!("local/{}", dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default());
+    create_local_repository(app, repo_id, display_id.as_str(), remote.as_str(), dir.to_str().unwrap(), &rma)?;
 
-            let repo_id = generate_cuid();
-            create_repository(app, repo_id.clone(), format!("{user}/{repo_name}").as_str()).unwrap();
+    load_manifests(app);
+    Ok(true)
+}
 
-            for m in rma.manifests {
-                let mf = fs::File::open(&repo_path.join(&m.as_str())).unwrap();
-                let reader = BufReader::new(mf);
-                let mi: GameManifest = serde_json::from_reader(reader).unwrap();
+/// Registers a repository that is distributed as an `index.json` plus a single archive instead of a git remote.
+/// The bundle is unpacked before the row is written, so a repository that turns out to be unusable never shows up in the database.
+pub fn add_bundle_repository(app: &AppHandle, url: String) -> Result<bool, RepositoryError> {
+    let index_url = parse_index_url(url.as_str())?;
+    if get_repository_info_by_remote(app, index_url.to_string())?.is_some() { return Err(RepositoryError::AlreadyAdded); }
+
+    let repo_id = generate_cuid();
+    let fetched = fetch(&index_url, None, None)?.ok_or(RepositoryError::Network(format!("{} returned nothing", index_url)))?;
+    let index = parse_index(&fetched.body)?;
+    let dir = bundle_dir(app, &repo_id);
+    let rma = install_bundle(app, &index_url, &index, &dir, &repo_id)?;
+
+    if let Err(e) = create_bundle_repository(app, repo_id, bundle_display_id(&index_url).as_str(), index_url.as_str(), index_url.host_str().unwrap_or("localhost"), index.revision.as_str(), fetched.etag, fetched.last_modified, &rma) {
+        fs::remove_dir_all(&dir).map_err(|err| RepositoryError::Other(format!("{} (failed to clean up {}: {})", e, dir.display(), err)))?;
+        return Err(e.into());
+    }
 
-                let cuid = generate_cuid();
-                create_manifest(app, cuid.clone(), repo_id.clone(), mi.clone().display_name.as_str(), m.clone().as_str(), true).unwrap();
-            }
+    load_manifests(app);
+    Ok(true)
+}
 
-            Ok(true)
+/// Polls the index of a bundle repository and swaps in the new bundle when its revision moved, returns whether anything changed.
+/// `force` skips the conditional request and the revision check.
+pub fn update_bundle_repository(app: &AppHandle, repository: &LauncherRepository, force: bool) -> Result<bool, RepositoryError> {
+    let index_url = parse_index_url(repository.remote.as_str())?;
+    let dir = bundle_dir(app, &repository.id);
+    let downloaded = dir.join("repository.json").exists();
+
+    let fetched = if force || !downloaded { fetch(&index_url, None, None)? } else { fetch(&index_url, repository.etag.as_deref(), repository.last_modified.as_deref())? };
+    let fetched = match fetched {
+        Some(f) => f,
+        None => return Ok(false)
+    };
+
+    let index = parse_index(&fetched.body)?;
+    if !force && downloaded && index.revision == repository.ref_name {
+        update_repository_bundle_state_by_id(app, repository.id.clone(), index.revision, fetched.etag, fetched.last_modified)?;
+        return Ok(false);
+    }
 
-        } else {
-            #[cfg(debug_assertions)]
-            { println!("Cannot clone repository! Not a valid repository?"); }
+    let rma = install_bundle(app, &index_url, &index, &dir, &repository.id)?;
+    // Revision plays the part of the commit so the sync notifications read the same as for git repositories
+    let previous = Some(repository.ref_name.clone()).filter(|r| !r.is_empty());
+    update_bundle_repository_by_id(app, repository.id.clone(), previous, index.revision, fetched.etag, fetched.last_modified, &rma)?;
+
+    #[cfg(debug_assertions)]
+    { println!("Updated bundle repository {}!", repository.github_id); }
+    Ok(true)
+}
 
-            Ok(false)
+fn bundle_dir(app: &AppHandle, repository_id: &str) -> PathBuf {
+    app.path().app_data_dir().unwrap().join("bundles").join(repository_id)
+}
+
+/// Unpacks the bundle an index points to into `dir` and returns its `repository.json`, `dir` is left alone when the bundle is unusable.
+fn install_bundle(app: &AppHandle, index_url: &Url, index: &BundleIndex, dir: &Path, repository_id: &str) -> Result<RepositoryManifest, RepositoryError> {
+    let work = app.path().app_data_dir().unwrap().join("bundles_staging").join(repository_id);
+    let rslt = unpack_bundle(index_url, index, &work).and_then(|contents| {
+        let rma = read_repository_manifest(&contents).ok_or(RepositoryError::NotLauncherRepository)?;
+        check_launcher_version(app, &rma).map_err(RepositoryError::LauncherTooOld)?;
+
+        fs::create_dir_all(dir.parent().unwrap()).map_err(|e| RepositoryError::Other(e.to_string()))?;
+        // Move the old contents aside instead of deleting them, a failed swap then puts them back
+        let previous = work.join("previous");
+        if dir.exists() { fs::rename(dir, &previous).map_err(|e| RepositoryError::Other(e.to_string()))?; }
+        if let Err(e) = fs::rename(&contents, dir) {
+            if previous.exists() { fs::rename(&previous, dir).map_err(|err| RepositoryError::Other(format!("{} (failed to restore {}: {})", e, dir.display(), err)))?; }
+            return Err(RepositoryError::Other(e.to_string()));
         }
+        Ok(rma)
+    });
+    // Takes the replaced contents with it
+    fs::remove_dir_all(&work).ok();
+    rslt
+}
+
+/// Remote local repositories are stored under, deliberately not something [`RepositoryUrl::parse`] accepts so git never touches them.
+pub fn local_repository_remote(dir: &Path) -> String {
+    format!("local://{}", dir.display())
+}
+
+/// Inserts a freshly cloned repository, its metadata, credentials and manifests into the database.
+/// Returns the new repository id and the manifests that were left out because they failed to parse.
+fn register_repository(app: &AppHandle, parsed: &RepositoryUrl, repo_path: &PathBuf, branch: String, rma: &RepositoryManifest, credentials: Option<RepositoryCredentials>) -> Result<(String, Vec<ManifestError>), DbError> {
+    let repo_id = generate_cuid();
+    let mut manifests = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (kind, m) in rma.manifests.entries() {
+        match parse_manifest(&parsed.display_id(), &m, kind, &repo_path.join(m.as_str())) {
+            Ok(data) => manifests.push((generate_cuid(), data.display_name().to_string(), m)),
+            Err(e) => skipped.push(e)
+        }
+    }
+
+    create_repository(app, repo_id.clone(), parsed.display_id().as_str(), parsed.remote.as_str(), parsed.host.as_str(), branch.as_str(), rma, credentials.as_ref(), &manifests)?;
+    Ok((repo_id, skipped))
+}
+
+/// Clones a repository into the `previews` directory and validates it without touching the database.
+/// The clone is kept around until [`confirm_repository_preview`] moves it into place or it gets discarded.
+pub fn preview_repository(app: &AppHandle, url: String, credentials: Option<RepositoryCredentials>) -> Result<RepositoryPreviewRsp, RepositoryError> {
+    if is_bundle_url(url.as_str()) { return Err(RepositoryError::Other("Previews are only supported for git repositories!".to_string())); }
+    let parsed = RepositoryUrl::parse(url.as_str()).map_err(|e| RepositoryError::InvalidUrl(e.message().to_string()))?;
+    if get_repository_info_by_remote(app, parsed.remote.clone())?.is_some() { return Err(RepositoryError::AlreadyAdded); }
+
+    expire_repository_previews(app);
+    let preview_id = generate_cuid();
+    let preview_path = app.path().app_data_dir().unwrap().join("previews").join(&preview_id);
+
+    let repo = match clone_repository(parsed.remote.as_str(), &preview_path, remote_callbacks(app, parsed.display_id(), credentials.clone())) {
+        Ok(r) => r,
+        Err(e) => {
+            discard_clone(&preview_path);
+            return Err(RepositoryError::from(e));
+        }
+    };
+    let branch = get_head_branch(&repo).unwrap_or("main".to_string());
+
+    let repo_manifest = preview_path.join("repository.json");
+    if !repo_manifest.exists() {
+        discard_clone(&preview_path);
+        return Err(RepositoryError::NotLauncherRepository);
+    }
+
+    let rma = match fs::File::open(&repo_manifest).map_err(|e| e.to_string()).and_then(|f| serde_json::from_reader::<_, RepositoryManifest>(BufReader::new(f)).map_err(|e| e.to_string())) {
+        Ok(rma) => rma,
+        Err(e) => {
+            discard_clone(&preview_path);
+            return Err(RepositoryError::Other(format!("Invalid repository.json: {}", e)));
+        }
+    };
+    if let Err(msg) = check_launcher_version(app, &rma) {
+        discard_clone(&preview_path);
+        return Err(RepositoryError::LauncherTooOld(msg));
+    }
+
+    let mut rsp = RepositoryPreviewRsp { preview_id: preview_id.clone(), github_id: parsed.display_id(), remote: parsed.remote.clone(), ..Default::default() };
+
+    for (kind, m) in rma.manifests.entries() {
+        match parse_manifest(&parsed.display_id(), &m, kind, &preview_path.join(m.as_str())) {
+            Ok(ManifestData::Game(mi)) => rsp.games.push(PreviewManifest { filename: m.clone(), display_name: mi.display_name }),
+            Ok(ManifestData::Runner(ri)) => rsp.runners.push(PreviewManifest { filename: m.clone(), display_name: ri.display_name }),
+            Ok(ManifestData::Extra(ei)) => rsp.extras.push(PreviewManifest { filename: m.clone(), display_name: ei.display_name }),
+            Err(e) => { rsp.invalid_manifests.push(e); continue; }
+        }
+
+        // Not a conflict anymore since manifests are namespaced per repository, but still worth a heads-up
+        if !get_manifests_by_filename(app, m.clone())?.is_empty() { rsp.collisions.push(m); }
+    }
+
+    rsp.name = rma.name.clone();
+    rsp.description = rma.description.clone();
+    rsp.maintainers = rma.maintainers.clone();
+    rsp.homepage = rma.homepage.clone();
+    rsp.icon = rma.icon.clone();
+    rsp.min_launcher_version = rma.min_launcher_version.clone();
+
+    let valid = rsp.invalid_manifests.is_empty();
+    app.state::<RepositoryPreviews>().0.lock().unwrap().insert(preview_id, PendingRepository { url: parsed.remote, path: preview_path, branch, valid, credentials, created_at: Instant::now() });
+    Ok(rsp)
+}
+
+/// Moves a previewed clone into the `manifests` directory and registers it like [`clone_new_repository`] would.
+pub fn confirm_repository_preview(app: &AppHandle, preview_id: String) -> Result<bool, RepositoryError> {
+    let pending = app.state::<RepositoryPreviews>().0.lock().unwrap().remove(&preview_id).ok_or(RepositoryError::Other("Preview does not exist or has expired!".to_string()))?;
+
+    if !pending.valid {
+        discard_clone(&pending.path);
+        return Err(RepositoryError::Other("Repository contains invalid manifests!".to_string()));
+    }
+
+    let parsed = RepositoryUrl::parse(pending.url.as_str()).map_err(|e| RepositoryError::InvalidUrl(e.message().to_string()))?;
+    if get_repository_info_by_remote(app, parsed.remote.clone())?.is_some() {
+        discard_clone(&pending.path);
+        return Err(RepositoryError::AlreadyAdded);
+    }
+
+    let repo_path = app.path().app_data_dir().unwrap().join("manifests").join(parsed.local_path());
+    let moved = (|| {
+        // Leftover from a failed or removed clone that never made it into the database
+        if repo_path.exists() { fs::remove_dir_all(&repo_path)?; }
+        fs::create_dir_all(repo_path.parent().unwrap())?;
+        move_dir(app, &pending.path, &repo_path, parsed.display_id())
+    })();
+    if let Err(e) = moved {
+        discard_clone(&pending.path);
+        discard_clone(&repo_path);
+        return Err(RepositoryError::Other(format!("Failed to move preview into place: {}", e)));
+    }
+
+    let rma = match read_repository_manifest(&repo_path) {
+        Some(rma) => rma,
+        None => {
+            discard_clone(&repo_path);
+            return Err(RepositoryError::NotLauncherRepository);
+        }
+    };
+    if let Err(e) = register_repository(app, &parsed, &repo_path, pending.branch, &rma, pending.credentials) {
+        discard_clone(&repo_path);
+        return Err(e.into());
+    }
+    load_manifests(app);
+    Ok(true)
+}
+
+pub fn discard_repository_preview(app: &AppHandle, preview_id: String) -> bool {
+    let pending = app.state::<RepositoryPreviews>().0.lock().unwrap().remove(&preview_id);
+
+    if let Some(p) = pending {
+        discard_clone(&p.path);
+        true
     } else {
-        #[cfg(debug_assertions)]
-        { println!("Target repository already exists!"); }
-        update_repositories(&repo_path)?;
+        false
+    }
+}
 
-        Ok(false)
+/// How long an unconfirmed preview is kept, the user most likely abandoned the dialog after that.
+const PREVIEW_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Drops previews nobody confirmed or discarded in time, leftovers of earlier sessions are wiped by `init_db`.
+fn expire_repository_previews(app: &AppHandle) {
+    let expired = {
+        let state = app.state::<RepositoryPreviews>();
+        let mut previews = state.0.lock().unwrap();
+        let ids = previews.iter().filter(|(_, p)| p.created_at.elapsed() >= PREVIEW_TTL).map(|(id, _)| id.clone()).collect::<Vec<String>>();
+        ids.iter().filter_map(|id| previews.remove(id)).collect::<Vec<PendingRepository>>()
+    };
+    for p in expired { discard_clone(&p.path); }
+}
+
+/// Renames a directory, falling back to [`copy_dir_all`] when source and target are on different filesystems.
+fn move_dir(app: &AppHandle, from: &Path, to: &Path, name: String) -> std::io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy_dir_all(app, from, to, name.clone(), name, "Repository".to_string())?;
+            fs::remove_dir_all(from)
+        }
+        Err(e) => Err(e)
+    }
+}
+
+/// Remote callbacks reporting transfer progress to the frontend as `repo_sync_progress` events and
+/// answering authentication requests with the repository's stored credentials, if any.
+pub fn remote_callbacks(app: &AppHandle, repository: String, credentials: Option<RepositoryCredentials>) -> RemoteCallbacks<'static> {
+    let h = app.clone();
+    let mut last = Instant::now();
+    let mut cb = RemoteCallbacks::new();
+
+    if let Some(c) = credentials {
+        let mut attempts = 0;
+        cb.credentials(move |url, username_from_url, allowed| {
+            // libgit2 keeps asking again when the server rejects what we gave it
+            attempts += 1;
+            if attempts > 3 { return Err(Error::new(ErrorCode::Auth, ErrorClass::Callback, "Repository rejected the configured credentials!")); }
+
+            let user = c.username.clone().or(username_from_url.map(|u| u.to_string())).unwrap_or("git".to_string());
+            if allowed.contains(CredentialType::USERNAME) { return Cred::username(user.as_str()); }
+
+            match c.kind.as_str() {
+                "token" if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) => Cred::userpass_plaintext(user.as_str(), c.secret.clone().unwrap_or_default().as_str()),
+                "ssh_key" if allowed.contains(CredentialType::SSH_KEY) => {
+                    let key = c.ssh_key_path.clone().unwrap_or_default();
+                    Cred::ssh_key(user.as_str(), None, Path::new(key.as_str()), c.secret.as_deref())
+                }
+                "credential_helper" => Cred::credential_helper(&Config::open_default()?, url, username_from_url),
+                _ => Err(Error::new(ErrorCode::Auth, ErrorClass::Callback, "Configured credentials are not supported by this remote!"))
+            }
+        });
     }
+
+    cb.transfer_progress(move |stats| {
+        let done = stats.received_objects() == stats.total_objects() && stats.indexed_deltas() == stats.total_deltas();
+        // Callback fires for every received object, throttle it so the frontend does not get flooded
+        if done || last.elapsed() >= Duration::from_millis(100) {
+            last = Instant::now();
+            let rslt = h.emit("repo_sync_progress", RepoSyncProgress {
+                repository: repository.clone(),
+                received_objects: stats.received_objects(),
+                total_objects: stats.total_objects(),
+                received_bytes: stats.received_bytes(),
+                indexed_deltas: stats.indexed_deltas(),
+                total_deltas: stats.total_deltas(),
+            });
+            if let Err(_e) = rslt {
+                #[cfg(debug_assertions)]
+                { println!("Failed to emit repo_sync_progress: {}", _e); }
+            }
+        }
+        true
+    });
+    cb
 }
 
-pub fn update_repositories(path: &PathBuf) -> Result<bool, Error> {
+pub fn update_repositories(app: &AppHandle, path: &PathBuf) -> Result<SyncOutcome, Error> {
     let repo = Repository::open(&path);
 
     if repo.is_ok() && path.exists() {
         let r = repo?;
-        let mut remote = r.find_remote("origin")?;
-        let fetch_commit = do_fetch(&r, &["main"], &mut remote)?;
-        do_merge(&r, "main", fetch_commit)?;
+        let dbr = match get_origin_url(path) {
+            Some(o) => get_repository_info_by_remote(app, o.remote).map_err(|e| Error::from_str(e.to_string().as_str()))?,
+            None => None
+        };
+        let (ref_kind, ref_name) = match &dbr {
+            Some(d) => (d.ref_kind.clone(), d.ref_name.clone()),
+            None => ("branch".to_string(), "main".to_string())
+        };
+
+        let before = get_head_commit(&r);
+        let creds = match &dbr {
+            Some(d) => get_repository_credentials_by_repository_id(app, d.id.clone()).map_err(|e| Error::from_str(e.to_string().as_str()))?,
+            None => None
+        };
+        let label = dbr.as_ref().map(|d| d.github_id.clone()).unwrap_or(path.to_str().unwrap().to_string());
+        let outcome = sync_tracked_ref(&r, ref_kind.as_str(), ref_name.as_str(), remote_callbacks(app, label, creds))?;
+        let after = get_head_commit(&r);
+
+        if let Some(d) = dbr {
+            // Remember where we were so a bad manifest push can be rolled back
+            if before.is_some() && before != after { update_repository_previous_commit_by_id(app, d.id.clone(), before.unwrap()).map_err(|e| Error::from_str(e.to_string().as_str()))?; }
+            if let Some(rma) = read_repository_manifest(path) { update_repository_metadata_by_id(app, d.id, &rma).map_err(|e| Error::from_str(e.to_string().as_str()))?; }
+        }
 
         #[cfg(debug_assertions)]
-        { println!("Successfully updated repositories!"); }
-        Ok(true)
+        { println!("Successfully updated repositories! {:?}", outcome); }
+        Ok(outcome)
     } else {
         #[cfg(debug_assertions)]
         { println!("Failed to fetch repository updates!"); }
-        Ok(false)
+        Err(Error::from_str("Not a valid git repository!"))
     }
 }
 
-#[cfg(target_os = "linux")]
-pub fn setup_compatibility_repository(app: &AppHandle, path: &PathBuf) {
-    let url = "https://github.com/AndigenaTeam/runner-manifests.git";
+pub fn read_repository_manifest(path: &PathBuf) -> Option<RepositoryManifest> {
+    parse_repository_manifest(path).ok()
+}
 
-    let tmp = url.split("/").collect::<Vec<&str>>()[4];
-    let user = url.split("/").collect::<Vec<&str>>()[3];
-    let repo_name = tmp.split(".").collect::<Vec<&str>>()[0];
+/// Like [`read_repository_manifest`] but says why the `repository.json` could not be read.
+pub fn parse_repository_manifest(path: &PathBuf) -> Result<RepositoryManifest, String> {
+    let file = fs::File::open(path.join("repository.json")).map_err(|e| format!("Failed to read repository.json: {}", e))?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| format!("Invalid repository.json: {}", e))
+}
 
-    let repo_path = path.join(format!("{}/{}", user, repo_name).as_str());
-    let repo_manifest = repo_path.join("repository.json");
+/// Refuses repositories whose `min_launcher_version` is newer than the running launcher.
+pub fn check_launcher_version(app: &AppHandle, repository: &RepositoryManifest) -> Result<(), String> {
+    let current = &app.package_info().version;
+    if let Some(min) = &repository.min_launcher_version {
+        let required = parse_launcher_version(min).ok_or(format!("Repository \"{}\" requires launcher version \"{}\" which is not a valid version!", repository.name, min))?;
 
-    if !path.exists() {
-        return;
-    } else if !repo_path.exists() {
-        Repository::clone(url, &repo_path).unwrap();
+        if required > (current.major, current.minor, current.patch) {
+            return Err(format!("Repository \"{}\" requires launcher version {} or newer, you are running {}. Please update the launcher!", repository.name, min, current));
+        }
+    }
+    Ok(())
+}
 
-        if repo_manifest.exists() {
-            let rm = fs::File::open(&repo_manifest).unwrap();
-            let reader = BufReader::new(rm);
-            let rma: RepositoryManifest = serde_json::from_reader(reader).unwrap();
+/// Parses `major[.minor[.patch]]` with an optional `v` prefix and pre-release or build suffix, missing parts count as `0`.
+fn parse_launcher_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.trim().trim_start_matches('v').split(['-', '+']).next()?;
+    let parts = core.split('.').map(|p| p.parse::<u64>().ok()).collect::<Option<Vec<u64>>>()?;
+    if parts.is_empty() || parts.len() > 3 { return None; }
+    Some((parts[0], parts.get(1).copied().unwrap_or(0), parts.get(2).copied().unwrap_or(0)))
+}
+
+/// Checks out the commit a repository was on before its last sync and pins it there.
+pub fn rollback_repository(app: &AppHandle, repository: &LauncherRepository) -> Result<SyncOutcome, Error> {
+    if repository.transport != RepositoryTransport::Git { return Err(Error::from_str("Only git repositories can be rolled back!")); }
+    let path = get_repository_path(app, repository).ok_or(Error::from_str("Repository has no valid remote!"))?;
+    let previous = repository.previous_commit.clone().ok_or(Error::from_str("Repository has not been synced yet!"))?;
+
+    let r = Repository::open(&path)?;
+    let current = get_head_commit(&r);
+    let creds = get_repository_credentials_by_repository_id(app, repository.id.clone()).map_err(|e| Error::from_str(e.to_string().as_str()))?;
+    let outcome = sync_tracked_ref(&r, "commit", previous.as_str(), remote_callbacks(app, repository.github_id.clone(), creds))?;
+
+    // Pin to the old commit, otherwise the next sync would pull the bad push right back in
+    update_repository_ref_by_id(app, repository.id.clone(), "commit".to_string(), previous).map_err(|e| Error::from_str(e.to_string().as_str()))?;
+    if let Some(c) = current { update_repository_previous_commit_by_id(app, repository.id.clone(), c).map_err(|e| Error::from_str(e.to_string().as_str()))?; }
+    Ok(outcome)
+}
 
-            let repo_id = generate_cuid();
-            create_repository(app, repo_id.clone(), format!("{user}/{repo_name}").as_str()).unwrap();
+#[cfg(target_os = "linux")]
+pub fn setup_compatibility_repository(app: &AppHandle, path: &PathBuf) {
+    setup_builtin_repository(app, path, OFFICIAL_RUNNER_REPOSITORY);
+}
 
-            for m in rma.manifests {
-                let mf = fs::File::open(&repo_path.join(&m.as_str())).unwrap();
-                let reader = BufReader::new(mf);
-                let mi: RunnerManifest = serde_json::from_reader(reader).unwrap();
+#[cfg(target_os = "windows")]
+pub fn setup_compatibility_repository(_app: &AppHandle, _path: &PathBuf) {}
 
-                let cuid = generate_cuid();
-                create_manifest(app, cuid.clone(), repo_id.clone(), mi.display_name.as_str(), m.as_str(), true).unwrap();
+pub fn is_official_repository(repository: &LauncherRepository) -> bool {
+    [OFFICIAL_GAME_REPOSITORY, OFFICIAL_RUNNER_REPOSITORY].iter().any(|u| RepositoryUrl::parse(u).unwrap().remote == repository.remote)
+}
+
+/// Returns the lock guarding a repository, lock it before touching its clone or its ref and drop it once done.
+pub fn repository_lock(app: &AppHandle, id: &str) -> Arc<Mutex<()>> {
+    app.state::<RepositoryLocks>().0.lock().unwrap().entry(id.to_string()).or_default().clone()
+}
+
+/// Syncs every repository the settings allow, reloads manifests and notifies the frontend if anything moved.
+pub fn sync_all_repositories(app: &AppHandle) -> Vec<SyncedRepository> {
+    let third_party = get_settings(app).ok().flatten().map(|s| s.third_party_repo_updates != 0).unwrap_or(false);
+    let mut synced = Vec::new();
+
+    for r in get_repositories(app).unwrap_or_default() {
+        if !third_party && !is_official_repository(&r) { continue; }
+
+        // Re-read under the lock, the repository may have been removed or re-pinned while waiting for it
+        let lock = repository_lock(app, &r.id);
+        let _guard = lock.lock().unwrap();
+        let r = match get_repository_info_by_id(app, r.id.clone()) {
+            Ok(Some(r)) => r,
+            _ => continue
+        };
+
+        if r.transport == RepositoryTransport::Bundle {
+            match update_bundle_repository(app, &r, false) {
+                Ok(true) => {
+                    let after = get_repository_info_by_id(app, r.id.clone()).ok().flatten().map(|u| u.ref_name);
+                    synced.push(SyncedRepository { repository_id: r.id, github_id: r.github_id, from_commit: Some(r.ref_name).filter(|c| !c.is_empty()), to_commit: after, outcome: SyncOutcome::Reset });
+                }
+                Ok(false) => {}
+                Err(_e) => {
+                    #[cfg(debug_assertions)]
+                    { println!("Failed to sync bundle repository {}: {:?}", r.github_id, _e); }
+                }
             }
+            continue;
+        }
+        if r.transport != RepositoryTransport::Git { continue; }
+
+        let path = get_repository_path(app, &r);
+        if path.is_none() || !path.as_ref().unwrap().exists() { continue; }
+        let p = path.unwrap();
+
+        let before = Repository::open(&p).ok().and_then(|repo| get_head_commit(&repo));
+        match update_repositories(app, &p) {
+            Ok(outcome) => {
+                let after = Repository::open(&p).ok().and_then(|repo| get_head_commit(&repo));
+                if before != after {
+                    synced.push(SyncedRepository { repository_id: r.id, github_id: r.github_id, from_commit: before, to_commit: after, outcome });
+                }
+            }
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                { println!("Failed to sync repository {}: {}", r.github_id, _e); }
+            }
+        }
+    }
 
-            ()
+    if !synced.is_empty() {
+        load_manifests(app);
+        if let Err(_e) = app.emit("repositories_synced", &synced) {
+            #[cfg(debug_assertions)]
+            { println!("Failed to emit repositories_synced: {}", _e); }
         }
-    } else {
-        #[cfg(debug_assertions)]
-        { println!("Official compatibility repository is already cloned!"); }
-        update_repositories(&repo_path).unwrap();
     }
+    synced
 }
 
-#[cfg(target_os = "windows")]
-pub fn setup_compatibility_repository(_app: &AppHandle, _path: &PathBuf) {}
+/// Periodically syncs repositories in the background, interval is read from settings in minutes and `0` disables it.
+pub fn start_repository_sync_scheduler(app: &AppHandle) {
+    let h = app.clone();
+    std::thread::spawn(move || {
+        let mut elapsed = 0;
+        loop {
+            std::thread::sleep(Duration::from_secs(60));
+            elapsed += 1;
+
+            // Re-read every tick so interval changes apply without a restart
+            let interval = get_settings(&h).ok().flatten().map(|s| s.repo_sync_interval).unwrap_or(60);
+            if interval <= 0 || elapsed < interval { continue; }
+
+            elapsed = 0;
+            sync_all_repositories(&h);
+        }
+    });
+}
 
 // === MANIFESTS ===
 
-pub fn load_manifests(app: &AppHandle) {
-        let data_path = app.path().app_data_dir().unwrap();
-        let manifets_path = data_path.join("manifests");
+/// Re-reads every repository clone and local repository and swaps the results into [`ManifestLoaders`] in one go, then emits `manifests_reloaded` if anything changed.
+pub fn load_manifests(app: &AppHandle) -> ManifestsReloaded {
+    // Watcher, sync and repository commands can all trigger a reload at the same time
+    let ml = app.state::<ManifestLoaders>();
+    let _reloading = ml.reloading.lock().unwrap();
 
-        if !manifets_path.exists() {
-            fs::create_dir_all(&manifets_path).unwrap();
-        } else {
-            for d in fs::read_dir(&manifets_path).unwrap() {
-                let p = d.unwrap().path();
+    let data_path = app.path().app_data_dir().unwrap();
+    let manifets_path = data_path.join("manifests");
+    let mut loaded = LoadedManifests::default();
 
-                if p.is_dir() {
-                    for pp in fs::read_dir(p).unwrap() {
-                        let p = pp.unwrap().path();
-                        #[cfg(debug_assertions)]
-                        { println!("Loading manifests from: {}", p.display()); }
-                        let repo_manifest = p.join("repository.json");
-
-                        if repo_manifest.exists() {
-                            let rm = fs::File::open(&repo_manifest).unwrap();
-                            let reader = BufReader::new(rm);
-                            let rma: RepositoryManifest = serde_json::from_reader(reader).unwrap();
-
-                            let ml = app.state::<ManifestLoaders>().clone();
-
-                            let mut tmp = ml.game.0.write().unwrap();
-                            #[cfg(target_os = "linux")]
-                            let mut tmp1 = ml.runner.0.write().unwrap();
-
-                            for m in rma.manifests {
-                                let file = fs::File::open(&p.join(&m.as_str())).unwrap();
-                                let reader = BufReader::new(file);
-                                let manifest_data = serde_json::from_reader(reader).unwrap();
-                                
-                                match manifest_data {
-                                    ManifestData::Game(mi) => {
-                                        tmp.insert(m.clone(), mi.clone());
-                                        update_manifest_table(&app, m.clone(), mi.display_name.clone().as_str(), p.clone());
-                                        #[cfg(debug_assertions)]
-                                        { println!("Loaded game manifest {}", m.as_str()); }
-                                    }
-                                    #[cfg(target_os = "linux")]
-                                    ManifestData::Runner(ri) => {
-                                        tmp1.insert(m.clone(), ri.clone());
-                                        update_manifest_table(&app, m.clone(), ri.display_name.clone().as_str(), p.clone());
-                                        #[cfg(debug_assertions)]
-                                        { println!("Loaded compatibility manifest {}", m.as_str()); }
-                                    }
-                                    #[cfg(target_os = "windows")]
-                                    ManifestData::Runner(_) => {}
-                                }
-                            }
-
-                            drop(tmp);
-                            #[cfg(target_os = "linux")]
-                            drop(tmp1);
-                        } else {
-                            #[cfg(debug_assertions)]
-                            { println!("Failed to load manifests from {}! Not a valid KeqingLauncher repository?", p.display()); }
+    if !manifets_path.exists() {
+        fs::create_dir_all(&manifets_path).unwrap();
+    } else {
+        for d in fs::read_dir(&manifets_path).unwrap() {
+            let p = d.unwrap().path();
+
+            if p.is_dir() {
+                for pp in fs::read_dir(p).unwrap() {
+                    let p = pp.unwrap().path();
+
+                    if p.join("repository.json").exists() {
+                        // Loaders are keyed by repository, clones without a database entry can not be referenced anyway
+                        let dbr = get_origin_url(&p).and_then(|o| get_repository_info_by_remote(app, o.remote).ok().flatten());
+                        if dbr.is_none() {
+                            loaded.errors.push(ManifestError { repository: p.display().to_string(), file: "repository.json".to_string(), path: "$".to_string(), reason: "Repository is cloned but not registered, add it again or delete the directory".to_string() });
+                            continue;
                         }
+                        load_repository_manifests(app, &p, &dbr.unwrap(), &mut loaded);
+                    } else {
+                        #[cfg(debug_assertions)]
+                        { println!("Failed to load manifests from {}! Not a valid KeqingLauncher repository?", p.display()); }
                     }
                 }
             }
         }
     }
 
-fn update_manifest_table(app: &AppHandle, filename: String, display_name: &str, path: PathBuf) {
-    let dbm = get_manifest_info_by_filename(&app, filename.clone());
-    if dbm.is_none() {
-        let user = path.parent().unwrap().components().last().unwrap().as_os_str().to_str().unwrap();
-        let repo_name = path.components().last().unwrap().as_os_str().to_str().unwrap();
+    // Local repositories are read straight from wherever they live, bundles from where their last download was extracted to
+    let mut local = HashSet::new();
+    for r in get_repositories(app).unwrap_or_default().into_iter().filter(|r| r.transport != RepositoryTransport::Git) {
+        let p = get_repository_dir(app, &r).unwrap();
+        if r.is_local() { local.insert(p.clone()); }
+        if p.join("repository.json").exists() {
+            load_repository_manifests(app, &p, &r, &mut loaded);
+        } else {
+            let reason = if r.is_local() { format!("Local repository {} no longer contains a repository.json", p.display()) } else { format!("Bundle of {} has not been downloaded yet", r.remote) };
+            loaded.errors.push(ManifestError { repository: r.github_id.clone(), file: "repository.json".to_string(), path: "$".to_string(), reason });
+        }
+    }
+
+    let LoadedManifests { games, runners, extras, overridden, mut errors, listed, listed_repositories } = loaded;
+
+    // Swap everything while holding all write locks so readers never see one loader updated and another not
+    let mut reloaded = ManifestsReloaded::default();
+    {
+        let mut tmp = ml.game.0.write().unwrap();
+        let mut tmp1 = ml.runner.0.write().unwrap();
+        let mut tmp2 = ml.extra.0.write().unwrap();
+
+        diff_loader(&tmp, &games, &mut reloaded);
+        diff_loader(&tmp1, &runners, &mut reloaded);
+        diff_loader(&tmp2, &extras, &mut reloaded);
+
+        *tmp = games;
+        *tmp1 = runners;
+        *tmp2 = extras;
+        *ml.overridden.write().unwrap() = overridden;
+    }
+
+    for key in manifests_to_prune(&reloaded.removed, &listed, &listed_repositories) { prune_manifest_row(app, key); }
+    watch_local_repositories(app, local);
 
-        let dbr = get_repository_info_by_github_id(&app, format!("{user}/{repo_name}"));
-        if dbr.is_some() {
-            let dbrr = dbr.unwrap();
-            let cuid = generate_cuid();
-            create_manifest(&app, cuid, dbrr.id, display_name, filename.as_str(), true).unwrap();
+    #[cfg(target_os = "linux")]
+    errors.extend(index_runner_versions(app));
+    *ml.errors.write().unwrap() = errors;
+
+    if !reloaded.is_empty() {
+        if let Err(_e) = app.emit("manifests_reloaded", reloaded.clone()) {
+            #[cfg(debug_assertions)]
+            { println!("Failed to emit manifests_reloaded: {}", _e); }
+        }
+    }
+    reloaded
+}
+
+fn load_repository_manifests(app: &AppHandle, p: &PathBuf, repository: &LauncherRepository, loaded: &mut LoadedManifests) {
+    #[cfg(debug_assertions)]
+    { println!("Loading manifests from: {}", p.display()); }
+    let repo_id = repository.id.clone();
+
+    let rma = match fs::read_to_string(p.join("repository.json")).map_err(|e| e.to_string()).and_then(|c| serde_json::from_str::<RepositoryManifest>(c.as_str()).map_err(|e| e.to_string())) {
+        Ok(rma) => rma,
+        Err(e) => {
+            loaded.errors.push(ManifestError { repository: repository.github_id.clone(), file: "repository.json".to_string(), path: "$".to_string(), reason: e });
+            return;
+        }
+    };
+
+    if let Err(msg) = check_launcher_version(app, &rma) {
+        loaded.errors.push(ManifestError { repository: repository.github_id.clone(), file: "repository.json".to_string(), path: "$.min_launcher_version".to_string(), reason: msg });
+        return;
+    }
+
+    loaded.listed_repositories.insert(repo_id.clone());
+    for (kind, m) in rma.manifests.entries() {
+        let key = manifest_key(&repo_id, &m);
+        loaded.listed.insert(key.clone());
+
+        // A broken override is reported but the manifest still loads as the repository ships it
+        let parsed = parse_manifest(&repository.github_id, &m, kind, &p.join(m.as_str())).map(|data| match read_override(app, &repository.github_id, &key) {
+            Ok(Some(patch)) => match apply_override(&repository.github_id, &m, &data, &patch) {
+                Ok(patched) => {
+                    loaded.overridden.insert(key.clone(), patch_fields(&patch));
+                    patched
+                }
+                Err(e) => {
+                    loaded.errors.push(e);
+                    data
+                }
+            },
+            Ok(None) => data,
+            Err(e) => {
+                loaded.errors.push(e);
+                data
+            }
+        });
+
+        match parsed {
+            Ok(ManifestData::Game(mi)) => {
+                update_manifest_table(&app, repo_id.clone(), m.clone(), mi.display_name.clone().as_str());
+                loaded.games.insert(key, mi);
+                #[cfg(debug_assertions)]
+                { println!("Loaded game manifest {}", m.as_str()); }
+            }
+            #[cfg(target_os = "linux")]
+            Ok(ManifestData::Runner(ri)) => {
+                update_manifest_table(&app, repo_id.clone(), m.clone(), ri.display_name.clone().as_str());
+                loaded.runners.insert(key, ri);
+                #[cfg(debug_assertions)]
+                { println!("Loaded compatibility manifest {}", m.as_str()); }
+            }
+            #[cfg(target_os = "windows")]
+            Ok(ManifestData::Runner(_)) => {}
+            Ok(ManifestData::Extra(ei)) => {
+                update_manifest_table(&app, repo_id.clone(), m.clone(), ei.display_name.clone().as_str());
+                loaded.extras.insert(key, ei);
+                #[cfg(debug_assertions)]
+                { println!("Loaded extra manifest {}", m.as_str()); }
+            }
+            Err(e) => {
+                #[cfg(debug_assertions)]
+                { println!("Skipping invalid manifest {} ({}): {}", m.as_str(), e.path, e.reason); }
+                loaded.errors.push(e);
+            }
+        }
+    }
+}
+
+fn diff_loader<T: Serialize>(old: &LinkedHashMap<String, T>, new: &LinkedHashMap<String, T>, reloaded: &mut ManifestsReloaded) {
+    for (key, value) in new.iter() {
+        match old.get(key) {
+            None => reloaded.added.push(key.clone()),
+            Some(o) if serde_json::to_value(o).ok() != serde_json::to_value(value).ok() => reloaded.changed.push(key.clone()),
+            Some(_) => {}
+        }
+    }
+    for key in old.keys() {
+        if !new.contains_key(key) { reloaded.removed.push(key.clone()); }
+    }
+}
+
+/// Removed loader keys whose manifest is gone from its `repository.json`.
+/// Manifests that merely failed to parse this time keep their row and id, so do all of a repository whose `repository.json` is broken.
+fn manifests_to_prune<'a>(removed: &'a [String], listed: &HashSet<String>, listed_repositories: &HashSet<String>) -> Vec<&'a String> {
+    removed.iter().filter(|k| k.split_once('/').is_some_and(|(r, _)| listed_repositories.contains(r)) && !listed.contains(*k)).collect()
+}
+
+/// Drops the database row of a manifest that disappeared from its repository, unless an install still points at it.
+fn prune_manifest_row(app: &AppHandle, key: &str) {
+    let (repository_id, filename) = key.split_once('/').unwrap();
+    if let Ok(Some(m)) = get_manifest_info_by_repository_and_filename(app, repository_id.to_string(), filename.to_string()) {
+        if get_installs_by_manifest_id(app, m.id.clone()).is_ok_and(|i| i.is_empty()) { delete_manifest_by_id(app, m.id).ok(); }
+    }
+}
+
+/// Watches the `manifests` directory and reloads whenever a `repository.json` or manifest file is added, edited or deleted.
+/// Local repositories are followed as well, [`load_manifests`] hands them to [`watch_local_repositories`] on every reload.
+pub fn start_manifest_watcher(app: &AppHandle) {
+    let root = app.path().app_data_dir().unwrap().join("manifests");
+    if let Err(_e) = fs::create_dir_all(&root) {
+        #[cfg(debug_assertions)]
+        { println!("Failed to create {}, manifest watcher not started: {}", root.display(), _e); }
+        return;
+    }
+
+    let h = app.clone();
+    // Wait for changes to settle so editors and git checkouts writing several files end up in a single reload
+    let debouncer = new_debouncer(Duration::from_secs(2), move |rslt: DebounceEventResult| match rslt {
+        Ok(events) => {
+            if events.iter().any(|e| is_watched_file(&e.path)) {
+                #[cfg(debug_assertions)]
+                { println!("Manifest files changed on disk, reloading"); }
+                load_manifests(&h);
+            }
+        },
+        Err(_e) => {
+            #[cfg(debug_assertions)]
+            { println!("Manifest watcher error: {}", _e); }
+        }
+    });
+    let mut debouncer = match debouncer {
+        Ok(d) => d,
+        Err(_e) => {
+            #[cfg(debug_assertions)]
+            { println!("Failed to start manifest watcher: {}", _e); }
+            return;
+        }
+    };
+
+    if let Err(_e) = debouncer.watcher().watch(&root, RecursiveMode::Recursive) {
+        #[cfg(debug_assertions)]
+        { println!("Failed to watch {}: {}", root.display(), _e); }
+        return;
+    }
+    *app.state::<ManifestWatcher>().0.lock().unwrap() = Some(WatchedPaths { debouncer, local: HashSet::new() });
+}
+
+/// Points the manifest watcher at exactly `dirs`, local repositories that were removed stop being watched.
+fn watch_local_repositories(app: &AppHandle, dirs: HashSet<PathBuf>) {
+    let state = app.state::<ManifestWatcher>();
+    let mut guard = state.0.lock().unwrap();
+    let Some(watched) = guard.as_mut() else { return; };
+
+    for d in watched.local.difference(&dirs) { watched.debouncer.watcher().unwatch(d).ok(); }
+    watched.local.retain(|d| dirs.contains(d));
+    for d in dirs {
+        if watched.local.contains(&d) { continue; }
+        // Missing directories are retried on the next reload
+        match watched.debouncer.watcher().watch(&d, RecursiveMode::Recursive) {
+            Ok(()) => { watched.local.insert(d); },
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                { println!("Failed to watch {}: {}", d.display(), _e); }
+            }
         }
     }
 }
 
+fn is_watched_file(path: &Path) -> bool {
+    path.extension().map(|x| x == "json").unwrap_or(false) && !path.components().any(|c| c.as_os_str() == ".git")
+}
+
+/// Reads and validates a single manifest file, errors carry the JSON path of the offending field.
+/// `expected` is the kind `repository.json` groups the file under, if it groups its entries at all.
+pub fn parse_manifest(repository: &str, filename: &str, expected: Option<ManifestKind>, file: &PathBuf) -> Result<ManifestData, ManifestError> {
+    let err = |path: String, reason: String| ManifestError { repository: repository.to_string(), file: filename.to_string(), path, reason };
+
+    let content = fs::read_to_string(file).map_err(|e| err("$".to_string(), format!("Failed to read manifest: {}", e)))?;
+    let value: serde_json::Value = serde_json::from_str(content.as_str()).map_err(|e| err("$".to_string(), format!("Invalid JSON: {}", e)))?;
+
+    match value.get("version").and_then(|v| v.as_i64()) {
+        Some(v) if v >= 1 && v <= MANIFEST_SCHEMA_VERSION as i64 => {}
+        Some(v) => return Err(err("$.version".to_string(), format!("Unsupported schema version {}, this launcher supports up to {}", v, MANIFEST_SCHEMA_VERSION))),
+        None => return Err(err("$.version".to_string(), "Missing schema version".to_string()))
+    }
+
+    let declared = match value.get("kind") {
+        Some(k) => Some(serde_json::from_value::<ManifestKind>(k.clone()).map_err(|_| err("$.kind".to_string(), format!("Unknown manifest kind {}", k)))?),
+        None => None
+    };
+    if let (Some(d), Some(e)) = (declared, expected) {
+        if d != e { return Err(err("$.kind".to_string(), format!("Manifest is a {} manifest but repository.json lists it under {}", d.as_str(), e.as_str()))); }
+    }
+
+    // Older manifests have no kind at all, guess it from their content like we always did
+    let kind = declared.or(expected).or_else(|| ManifestKind::detect(&value)).ok_or(err("$.kind".to_string(), "Missing manifest kind and it can not be guessed from the content".to_string()))?;
+    let data = match kind {
+        ManifestKind::Game => serde_path_to_error::deserialize(value).map(ManifestData::Game),
+        ManifestKind::Runner => serde_path_to_error::deserialize(value).map(ManifestData::Runner),
+        ManifestKind::Extra => serde_path_to_error::deserialize(value).map(ManifestData::Extra),
+    };
+
+    data.map_err(|e| {
+        let path = e.path().to_string();
+        err(if path == "." { "$".to_string() } else { format!("$.{}", path) }, e.inner().to_string())
+    })
+}
+
+pub fn get_overridden_fields(app: &AppHandle, key: &str) -> Vec<String> {
+    app.state::<ManifestLoaders>().overridden.read().unwrap().get(key).cloned().unwrap_or_default()
+}
+
+/// Directory the files of a repository are read from, the clone, the extracted bundle or the local directory.
+pub fn get_repository_dir(app: &AppHandle, repository: &LauncherRepository) -> Option<PathBuf> {
+    if repository.is_local() { repository.local_path.as_ref().map(PathBuf::from) } else { get_repository_path(app, repository) }
+}
+
+pub fn get_manifest_errors(app: &AppHandle) -> Vec<ManifestError> {
+    app.state::<ManifestLoaders>().errors.read().unwrap().clone()
+}
+
+/// Creates the database row of a loaded manifest or refreshes its display name, reloads never add a second row.
+fn update_manifest_table(app: &AppHandle, repository_id: String, filename: String, display_name: &str) {
+    if let Err(_e) = upsert_manifest(&app, generate_cuid(), repository_id, display_name, filename.as_str()) {
+        #[cfg(debug_assertions)]
+        { println!("Failed to save manifest {}: {}", filename, _e); }
+    }
+}
+
+/// Loader key of a manifest, file names alone are only unique within a single repository.
+pub fn manifest_key(repository_id: &str, filename: &str) -> String {
+    format!("{}/{}", repository_id, filename)
+}
+
 pub fn get_manifests(app: &AppHandle) -> LinkedHashMap<String, GameManifest> {
     app.state::<ManifestLoaders>().game.0.read().unwrap().clone()
 }
 
-pub fn get_manifest(app: &AppHandle, filename: String) -> Option<GameManifest> {
+pub fn get_manifest(app: &AppHandle, key: String) -> Option<GameManifest> {
     let loader = app.state::<ManifestLoaders>().game.0.read().unwrap().clone();
 
-    if loader.contains_key(&filename) {
-        let content = loader.get(&filename).unwrap();
+    if loader.contains_key(&key) {
+        let content = loader.get(&key).unwrap();
         Some(content.clone())
     } else {
         None
@@ -265,49 +992,389 @@ pub fn get_compatibilities(app: &AppHandle) -> LinkedHashMap<String, RunnerManif
 }
 
 #[cfg(target_os = "linux")]
-pub fn get_compatibility(app: &AppHandle, filename: &String) -> Option<RunnerManifest> {
+pub fn get_compatibility(app: &AppHandle, key: &String) -> Option<RunnerManifest> {
     let loader = app.state::<ManifestLoaders>().runner.0.read().unwrap().clone();
 
-    if loader.contains_key(filename) {
-        let content = loader.get(filename).unwrap();
+    if loader.contains_key(key) {
+        let content = loader.get(key).unwrap();
         Some(content.clone())
     } else {
         None
     }
 }
 
+pub fn get_extras(app: &AppHandle) -> LinkedHashMap<String, ExtraManifest> {
+    app.state::<ManifestLoaders>().extra.0.read().unwrap().clone()
+}
+
+/// Looks up an extra by its `id`, tools from the official repository win over third party ones publishing the same id.
+pub fn get_extra_by_id(app: &AppHandle, id: &str) -> Result<Option<(String, ExtraManifest)>, DbError> {
+    let official = match RepositoryUrl::parse(OFFICIAL_GAME_REPOSITORY) {
+        Ok(u) => get_repository_info_by_remote(app, u.remote)?.map(|r| r.id),
+        Err(_) => None
+    };
+    let enabled = get_manifests_info(app)?.into_iter().filter(|m| m.enabled).map(|m| manifest_key(&m.repository_id, &m.filename)).collect::<HashSet<String>>();
+    let mut found = get_extras(app).into_iter().filter(|(key, e)| e.id == id && enabled.contains(key)).collect::<Vec<_>>();
+    found.sort_by_key(|(key, _)| official.as_ref().map(|o| !key.starts_with(format!("{}/", o).as_str())).unwrap_or(true));
+    Ok(found.into_iter().next())
+}
+
+/// Resolves a runner or DXVK version to the manifest declaring it through the index built by [`load_manifests`].
+#[cfg(target_os = "linux")]
+pub fn get_compatibility_by_version(app: &AppHandle, version: &str) -> Result<(RunnerManifest, RunnerVersion), String> {
+    if version.is_empty() { return Err("No runner version selected".to_string()); }
+
+    let key = app.state::<ManifestLoaders>().runner_versions.0.read().unwrap().get(version).cloned().ok_or(format!("Unknown runner version {}, no loaded runner manifest provides it", version))?;
+    let rm = get_compatibility(app, &key).ok_or(format!("Runner manifest {} providing {} is no longer loaded", key, version))?;
+    let rv = rm.versions.iter().find(|v| v.version == version).cloned().ok_or(format!("Runner manifest {} no longer lists version {}", key, version))?;
+    Ok((rm, rv))
+}
+
+/// Rebuilds the version index from the runner loader, the official compatibility repository wins when versions collide.
+#[cfg(target_os = "linux")]
+fn index_runner_versions(app: &AppHandle) -> Vec<ManifestError> {
+    let official = RepositoryUrl::parse(OFFICIAL_RUNNER_REPOSITORY).ok().and_then(|u| get_repository_info_by_remote(app, u.remote).ok().flatten()).map(|r| format!("{}/", r.id));
+    let mut runners = get_compatibilities(app).into_iter().collect::<Vec<_>>();
+    runners.sort_by_key(|(key, _)| official.as_ref().map(|o| !key.starts_with(o.as_str())).unwrap_or(true));
+
+    let mut index: HashMap<String, String> = HashMap::new();
+    let mut conflicts = Vec::new();
+    for (key, rm) in runners {
+        for (i, v) in rm.versions.iter().enumerate() {
+            match index.get(&v.version) {
+                Some(existing) => {
+                    let (repository, file) = key.split_once('/').unwrap();
+                    conflicts.push(ManifestError { repository: repository.to_string(), file: file.to_string(), path: format!("$.versions[{}].version", i), reason: format!("{} is already provided by {}, ignoring this one", v.version, existing) });
+                }
+                None => { index.insert(v.version.clone(), key.clone()); }
+            }
+        }
+    }
+
+    *app.state::<ManifestLoaders>().runner_versions.0.write().unwrap() = index;
+    conflicts
+}
+
+/// Location of a repository clone inside the `manifests` directory, or of an extracted bundle inside `bundles`.
+/// Local repositories are not owned by the launcher and have none.
+pub fn get_repository_path(app: &AppHandle, repository: &LauncherRepository) -> Option<PathBuf> {
+    if repository.is_local() { return None; }
+    if repository.transport == RepositoryTransport::Bundle { return Some(bundle_dir(app, &repository.id)); }
+    let parsed = RepositoryUrl::parse(repository.remote.as_str()).ok()?;
+    Some(app.path().app_data_dir().unwrap().join("manifests").join(parsed.local_path()))
+}
+
+/// Drops manifests from the in-memory loaders so they are no longer offered to the frontend.
+pub fn unload_manifests(app: &AppHandle, keys: &Vec<String>) {
+    let ml = app.state::<ManifestLoaders>();
+
+    let mut tmp = ml.game.0.write().unwrap();
+    let mut tmp1 = ml.runner.0.write().unwrap();
+    let mut tmp2 = ml.extra.0.write().unwrap();
+
+    for f in keys {
+        tmp.remove(f);
+        tmp1.remove(f);
+        tmp2.remove(f);
+        #[cfg(debug_assertions)]
+        { println!("Unloaded manifest {}", f.as_str()); }
+    }
+
+    drop(tmp);
+    drop(tmp1);
+    drop(tmp2);
+    #[cfg(target_os = "linux")]
+    index_runner_versions(app);
+}
+
 // === STRUCTS ===
 
+/// Result of a single [`load_manifests`] pass before it gets swapped into the loaders.
+#[derive(Default)]
+struct LoadedManifests {
+    games: LinkedHashMap<String, GameManifest>,
+    runners: LinkedHashMap<String, RunnerManifest>,
+    extras: LinkedHashMap<String, ExtraManifest>,
+    overridden: HashMap<String, Vec<String>>,
+    errors: Vec<ManifestError>,
+    /// Keys of every manifest listed in a readable `repository.json`, whether it parsed or not
+    listed: HashSet<String>,
+    /// Repositories whose `repository.json` could be read, nothing of the others gets pruned
+    listed_repositories: HashSet<String>
+}
+
 #[derive(Default)]
 pub struct RunnerLoader(pub RwLock<LinkedHashMap<String, RunnerManifest>>);
 
 #[derive(Default)]
 pub struct ManifestLoader(pub RwLock<LinkedHashMap<String, GameManifest>>);
 
+/// Runner and DXVK version to the loader key of the runner manifest declaring it.
+#[derive(Default)]
+pub struct RunnerVersionIndex(pub RwLock<HashMap<String, String>>);
+
+#[derive(Default)]
+pub struct ExtraLoader(pub RwLock<LinkedHashMap<String, ExtraManifest>>);
+
+#[derive(Default)]
+pub struct RepositoryPreviews(pub Mutex<HashMap<String, PendingRepository>>);
+
+/// Debounced watcher of the `manifests` directory and local repositories, `None` until [`start_manifest_watcher`] ran.
+#[derive(Default)]
+pub struct ManifestWatcher(pub Mutex<Option<WatchedPaths>>);
+
+pub struct WatchedPaths {
+    debouncer: Debouncer<RecommendedWatcher>,
+    /// Local repository directories currently watched
+    local: HashSet<PathBuf>
+}
+
+/// One lock per repository id, held by everything that syncs, re-pins or deletes a repository so the scheduler never works on a half removed clone.
+#[derive(Default)]
+pub struct RepositoryLocks(pub Mutex<HashMap<String, Arc<Mutex<()>>>>);
+
+pub struct PendingRepository {
+    pub url: String,
+    pub path: PathBuf,
+    pub branch: String,
+    pub valid: bool,
+    pub credentials: Option<RepositoryCredentials>,
+    pub created_at: Instant
+}
+
 pub struct ManifestLoaders {
     pub game: ManifestLoader,
     pub runner: RunnerLoader,
+    pub extra: ExtraLoader,
+    pub runner_versions: RunnerVersionIndex,
+    /// Fields replaced by user overrides, keyed like the loaders
+    pub overridden: RwLock<HashMap<String, Vec<String>>>,
+    /// Held for the whole of [`load_manifests`]
+    pub reloading: Mutex<()>,
+    /// Diagnostics for manifests skipped during the last load
+    pub errors: RwLock<Vec<ManifestError>>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
-#[serde(untagged)]
-enum ManifestData {
+/// Value of the `kind` field in manifests and the group names in `repository.json`.
+/// Adding a kind means adding a variant here, to [`ManifestData`] and a deserialize arm in [`parse_manifest`].
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ManifestKind {
+    Game,
+    Runner,
+    Extra
+}
+
+impl ManifestKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ManifestKind::Game => "game",
+            ManifestKind::Runner => "runner",
+            ManifestKind::Extra => "extra"
+        }
+    }
+
+    /// Heuristic for manifests written before the `kind` field existed, new kinds must always declare themselves.
+    fn detect(value: &serde_json::Value) -> Option<ManifestKind> {
+        if value.get("game_versions").is_some() {
+            Some(ManifestKind::Game)
+        } else if value.get("versions").is_some() {
+            Some(ManifestKind::Runner)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ManifestData {
     Game(GameManifest),
-    Runner(RunnerManifest)
+    Runner(RunnerManifest),
+    Extra(ExtraManifest)
+}
+
+impl ManifestData {
+    pub fn display_name(&self) -> &str {
+        match self {
+            ManifestData::Game(g) => g.display_name.as_str(),
+            ManifestData::Runner(r) => r.display_name.as_str(),
+            ManifestData::Extra(e) => e.display_name.as_str()
+        }
+    }
+}
+
+/// Group name in `repository.json`, kinds this launcher does not know yet are kept so they can be skipped instead of failing the whole file.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(untagged)]
+pub enum ManifestGroup {
+    Known(ManifestKind),
+    Unknown(String)
+}
+
+/// Either the old flat list of manifest files or files grouped by their kind.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+pub enum RepositoryManifestList {
+    Grouped(BTreeMap<ManifestGroup, Vec<String>>),
+    Flat(Vec<String>)
+}
+
+impl RepositoryManifestList {
+    /// Every manifest file this launcher can load, files in unknown groups are left out.
+    pub fn entries(&self) -> Vec<(Option<ManifestKind>, String)> {
+        self.located_entries().into_iter().map(|(_, k, f)| (k, f)).collect()
+    }
+
+    /// Like [`entries`](Self::entries) but with the JSON path of every file in `repository.json`.
+    pub fn located_entries(&self) -> Vec<(String, Option<ManifestKind>, String)> {
+        match self {
+            RepositoryManifestList::Grouped(g) => g.iter().flat_map(|(group, files)| match group {
+                ManifestGroup::Known(k) => files.iter().enumerate().map(|(i, f)| (format!("$.manifests.{}[{}]", k.as_str(), i), Some(*k), f.clone())).collect(),
+                ManifestGroup::Unknown(_) => Vec::new()
+            }).collect(),
+            RepositoryManifestList::Flat(files) => files.iter().enumerate().map(|(i, f)| (format!("$.manifests[{}]", i), None, f.clone())).collect()
+        }
+    }
+
+    /// Group names written for a newer launcher.
+    pub fn unknown_groups(&self) -> Vec<&str> {
+        match self {
+            RepositoryManifestList::Grouped(g) => g.keys().filter_map(|k| match k { ManifestGroup::Unknown(name) => Some(name.as_str()), _ => None }).collect(),
+            RepositoryManifestList::Flat(_) => Vec::new()
+        }
+    }
+}
+
+/// Secrets for private repositories, stored in their own `repository_credentials` table.
+/// `kind` is one of `token` (personal access token in `secret`), `ssh_key` (`ssh_key_path` with optional passphrase in `secret`)
+/// or `credential_helper` (whatever git's configured credential helper returns).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RepositoryCredentials {
+    pub kind: String,
+    pub username: Option<String>,
+    pub secret: Option<String>,
+    pub ssh_key_path: Option<String>
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct RepositoryManifest {
-    name: String,
-    description: String,
-    maintainers: Vec<String>,
-    manifests: Vec<String>
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum RepositoryError {
+    InvalidUrl(String),
+    AuthFailed(String),
+    Network(String),
+    /// Cloned fine but there is no `repository.json` in it
+    NotLauncherRepository,
+    AlreadyAdded,
+    LauncherTooOld(String),
+    Other(String),
+}
+
+impl std::fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepositoryError::InvalidUrl(m) => write!(f, "Invalid repository url: {}", m),
+            RepositoryError::AuthFailed(m) => write!(f, "Authentication failed: {}", m),
+            RepositoryError::Network(m) => write!(f, "Network error: {}", m),
+            RepositoryError::NotLauncherRepository => write!(f, "Not a launcher repository, repository.json is missing"),
+            RepositoryError::AlreadyAdded => write!(f, "Repository is already added"),
+            RepositoryError::LauncherTooOld(m) | RepositoryError::Other(m) => write!(f, "{}", m),
+        }
+    }
+}
+
+impl From<DbError> for RepositoryError {
+    fn from(e: DbError) -> Self {
+        RepositoryError::Other(e.to_string())
+    }
+}
+
+impl From<Error> for RepositoryError {
+    fn from(e: Error) -> Self {
+        let msg = e.message().to_string();
+        match (e.code(), e.class()) {
+            // libgit2 reports rejected http credentials and failed ssh handshakes as GIT_EAUTH
+            (ErrorCode::Auth, _) | (_, ErrorClass::Ssh) => RepositoryError::AuthFailed(msg),
+            (ErrorCode::Certificate, _) | (_, ErrorClass::Net) | (_, ErrorClass::Http) | (_, ErrorClass::Ssl) | (_, ErrorClass::Os) => RepositoryError::Network(msg),
+            _ => RepositoryError::Other(msg)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct RepositoryManifest {
+    pub name: String,
+    pub description: String,
+    pub maintainers: Vec<String>,
+    pub manifests: RepositoryManifestList,
+    pub homepage: Option<String>,
+    pub icon: Option<String>,
+    /// Oldest launcher version able to read this repository, e.g. `1.2.0`
+    pub min_launcher_version: Option<String>
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct LauncherRepository {
     pub id: String,
-    pub github_id: String
+    pub github_id: String,
+    pub remote: String,
+    pub host: String,
+    pub ref_kind: String,
+    pub ref_name: String,
+    pub previous_commit: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub maintainers: Vec<String>,
+    pub homepage: Option<String>,
+    pub icon: Option<String>,
+    pub min_launcher_version: Option<String>,
+    /// Directory a local repository is read from, `None` for git clones
+    pub local_path: Option<String>,
+    pub transport: RepositoryTransport,
+    /// Validators of the last fetched bundle index, only set for bundle repositories
+    pub etag: Option<String>,
+    pub last_modified: Option<String>
+}
+
+/// Where a repository gets its files from, stored as TEXT in the `transport` column.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RepositoryTransport {
+    /// Cloned and synced with git
+    Git,
+    /// Read in place from a directory on this machine
+    Local,
+    /// `index.json` polled over http(s) or `file://` pointing at a single archive
+    Bundle
+}
+
+impl RepositoryTransport {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RepositoryTransport::Git => "git",
+            RepositoryTransport::Local => "local",
+            RepositoryTransport::Bundle => "bundle"
+        }
+    }
+}
+
+impl std::str::FromStr for RepositoryTransport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "git" => Ok(RepositoryTransport::Git),
+            "local" => Ok(RepositoryTransport::Local),
+            "bundle" => Ok(RepositoryTransport::Bundle),
+            _ => Err(format!("Unknown repository transport {}", s))
+        }
+    }
+}
+
+impl LauncherRepository {
+    /// Local repositories are read in place and never synced or deleted by the launcher.
+    pub fn is_local(&self) -> bool {
+        self.transport == RepositoryTransport::Local
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -338,31 +1405,107 @@ pub struct LauncherInstall {
     pub use_jadeite: bool,
     pub use_xxmi: bool,
     pub use_fps_unlock: bool,
-    pub env_vars: String,
+    pub env_vars: Vec<InstallEnvVar>,
     pub pre_launch_command: String,
     pub launch_command: String,
     pub fps_value: String,
     pub runner_prefix: String,
-    pub launch_args: String
+    /// Passed to the game as separate arguments, never through a shell
+    pub launch_args: Vec<String>
 }
 
-// === MANIFESTS ===
+/// Row of the `install_env_var` table, disabled entries are kept but not passed to the game.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstallEnvVar {
+    pub key: String,
+    pub value: String,
+    pub enabled: bool
+}
 
+/// Named set of overrides applied on top of an install when launching, `None` keeps whatever the install itself uses.
+/// Every install has exactly one default profile which is used when no profile is asked for.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LaunchProfile {
+    pub id: String,
+    pub install_id: String,
+    pub name: String,
+    pub is_default: bool,
+    pub use_xxmi: Option<bool>,
+    pub use_fps_unlock: Option<bool>,
+    pub fps_value: Option<String>,
+    /// Layered over the install variables by key, a disabled entry switches an install variable off
+    pub env_vars: Option<Vec<InstallEnvVar>>,
+    /// Replaces the install launch arguments entirely
+    pub launch_args: Option<Vec<String>>,
+    pub runner_version: Option<String>
+}
+
+impl LaunchProfile {
+    pub fn apply(&self, mut install: LauncherInstall) -> LauncherInstall {
+        if let Some(v) = self.use_xxmi { install.use_xxmi = v; }
+        if let Some(v) = self.use_fps_unlock { install.use_fps_unlock = v; }
+        if let Some(v) = &self.fps_value { install.fps_value = v.clone(); }
+        if let Some(vars) = &self.env_vars {
+            for var in vars {
+                match install.env_vars.iter_mut().find(|e| e.key == var.key) {
+                    Some(e) => *e = var.clone(),
+                    None => install.env_vars.push(var.clone())
+                }
+            }
+        }
+        if let Some(args) = &self.launch_args { install.launch_args = args.clone(); }
+        // Runners live in directories named after their version, same as when switching the install runner
+        if let Some(v) = &self.runner_version {
+            if *v != install.runner_version {
+                install.runner_path = install.runner_path.replace(install.runner_version.as_str(), v.as_str());
+                install.runner_version = v.clone();
+            }
+        }
+        install
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LauncherInstalledExtra {
+    pub id: String,
+    pub manifest_id: String,
+    pub version: String,
+    pub path: String,
+    pub installed_at: i64
+}
+
+// === MANIFESTS ===
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct RunnerManifest {
     pub version: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ManifestKind>,
     pub display_name: String,
     pub versions: Vec<RunnerVersion>,
     pub paths: RunnerPaths
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct RunnerVersion {
     pub version: String,
     pub url: String,
+    /// Lowercase hex sha256 of the archive at `url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Archive size in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Release date as `YYYY-MM-DD`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_date: Option<String>,
+    #[serde(default)]
+    pub recommended: bool,
+    #[serde(default)]
+    pub deprecated: bool
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct RunnerPaths {
     pub wine32: String,
     pub wine64: String,
@@ -370,9 +1513,78 @@ pub struct RunnerPaths {
     pub wine_boot: String
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Third party tool (mod loaders, fps unlockers...) installed into one of the launcher wide tool directories.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct ExtraManifest {
+    pub version: i32,
+    pub kind: ManifestKind,
+    /// Stable identifier the launcher refers to the tool by, e.g. `xxmi`
+    pub id: String,
+    pub display_name: String,
+    pub target: ExtraTarget,
+    pub latest_version: String,
+    pub versions: Vec<ExtraVersion>
+}
+
+/// Settings directory an extra gets installed into.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtraTarget {
+    Xxmi,
+    Jadeite,
+    FpsUnlock
+}
+
+impl ExtraTarget {
+    pub fn path(&self, settings: &GlobalSettings) -> PathBuf {
+        match self {
+            ExtraTarget::Xxmi => Path::new(&settings.xxmi_path).to_path_buf(),
+            ExtraTarget::Jadeite => Path::new(&settings.jadeite_path).to_path_buf(),
+            ExtraTarget::FpsUnlock => Path::new(&settings.fps_unlock_path).to_path_buf()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct ExtraVersion {
+    pub version: String,
+    pub files: Vec<ExtraFile>,
+    #[serde(default)]
+    pub post_install: Vec<ExtraStep>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct ExtraFile {
+    pub url: String,
+    /// Name the download is saved as, only kept around when `extract` is false
+    pub file_name: String,
+    /// Lowercase hex sha256 of the download
+    pub sha256: Option<String>,
+    #[serde(default = "default_true")]
+    pub extract: bool,
+    /// Directory relative to the target the file is placed or extracted into
+    pub subdir: Option<String>,
+    /// Drop the single top level directory most release archives wrap their contents in
+    #[serde(default)]
+    pub strip_root: bool
+}
+
+/// Steps run after every file of a version is in place, paths are relative to the target directory.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ExtraStep {
+    Symlink { source: String, destination: String },
+    Copy { source: String, destination: String },
+    Remove { path: String }
+}
+
+fn default_true() -> bool { true }
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct GameManifest {
     pub version: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ManifestKind>,
     pub display_name: String,
     pub biz: String,
     pub latest_version: String,
@@ -383,7 +1595,7 @@ pub struct GameManifest {
     pub extra: GameExtras
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct GameVersion {
     pub metadata: VersionMetadata,
     pub assets: VersionAssets,
@@ -391,7 +1603,7 @@ pub struct GameVersion {
     pub audio: VersionAudioFiles
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct GamePaths {
     pub audio_pkg_res_dir: String,
     pub exe_filename: String,
@@ -400,7 +1612,7 @@ pub struct GamePaths {
     pub screenshot_dir_relative_to: String
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct VersionMetadata {
     pub versioned_name: String,
     pub version: String,
@@ -411,7 +1623,7 @@ pub struct VersionMetadata {
     pub diff_list_url: DiffUrls
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct DiffUrls {
     pub game: String,
     pub en_us: String,
@@ -420,19 +1632,19 @@ pub struct DiffUrls {
     pub ko_kr: String
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct VersionAssets {
     pub game_icon: String,
     pub game_background: String
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct VersionGameFiles {
     pub full: Vec<FullGameFile>,
     pub diff: Vec<DiffGameFile>
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct FullGameFile {
     pub file_url: String,
     pub compressed_size: String,
@@ -441,7 +1653,7 @@ pub struct FullGameFile {
     pub file_path: String
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct DiffGameFile {
     pub file_url: String,
     pub compressed_size: String,
@@ -452,13 +1664,13 @@ pub struct DiffGameFile {
     pub delete_files: Vec<String>
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct VersionAudioFiles {
     pub full: Vec<FullAudioFile>,
     pub diff: Vec<DiffAudioFile>
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct FullAudioFile {
     pub file_url: String,
     pub compressed_size: String,
@@ -467,7 +1679,7 @@ pub struct FullAudioFile {
     pub language: String
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct DiffAudioFile {
     pub file_url: String,
     pub compressed_size: String,
@@ -478,7 +1690,7 @@ pub struct DiffAudioFile {
     pub language: String
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct GamePreload {
     pub metadata: Option<VersionMetadata>,
     pub index_file: Option<String>,
@@ -487,16 +1699,75 @@ pub struct GamePreload {
     pub audio: Option<VersionAudioFiles>
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct GameTweakSwitches {
     pub fps_unlocker: bool,
     pub jadeite: bool,
     pub xxmi: bool
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct GameExtras {
     pub preload: Option<GamePreload>,
     pub switches: GameTweakSwitches,
     pub fps_unlock_options: Vec<String>,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn launcher_versions_are_parsed_strictly() {
+        assert_eq!(parse_launcher_version("1.2.3"), Some((1, 2, 3)));
+        assert_eq!(parse_launcher_version("v1.2"), Some((1, 2, 0)));
+        assert_eq!(parse_launcher_version("2"), Some((2, 0, 0)));
+        assert_eq!(parse_launcher_version("1.3.0-beta.1"), Some((1, 3, 0)));
+        assert_eq!(parse_launcher_version("1.0.0+build5"), Some((1, 0, 0)));
+
+        for invalid in ["", "1.x", "1..2", "1.2.3.4", "latest", "1.2.-1"] {
+            assert_eq!(parse_launcher_version(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn only_manifests_gone_from_repository_json_are_pruned() {
+        let removed = vec!["repo1/gone.json".to_string(), "repo1/broken.json".to_string(), "repo2/game.json".to_string()];
+        let listed = HashSet::from(["repo1/game.json".to_string(), "repo1/broken.json".to_string()]);
+        // repo2 has a broken repository.json so nothing of it is listed
+        let listed_repositories = HashSet::from(["repo1".to_string()]);
+
+        assert_eq!(manifests_to_prune(&removed, &listed, &listed_repositories), vec!["repo1/gone.json"]);
+    }
+
+    #[test]
+    fn transports_round_trip_through_the_database_text() {
+        for t in [RepositoryTransport::Git, RepositoryTransport::Local, RepositoryTransport::Bundle] {
+            assert_eq!(t.as_str().parse::<RepositoryTransport>(), Ok(t));
+            assert_eq!(serde_json::to_value(t).unwrap(), serde_json::Value::String(t.as_str().to_string()));
+        }
+        assert!("svn".parse::<RepositoryTransport>().is_err());
+    }
+
+    #[test]
+    fn watcher_ignores_git_internals_and_other_files() {
+        assert!(is_watched_file(Path::new("/data/manifests/github.com/owner/repo/repository.json")));
+        assert!(is_watched_file(Path::new("/home/user/my-repo/games/hk4e.json")));
+        assert!(!is_watched_file(Path::new("/data/manifests/github.com/owner/repo/.git/index")));
+        assert!(!is_watched_file(Path::new("/data/manifests/github.com/owner/repo/.git/stash.json")));
+        assert!(!is_watched_file(Path::new("/home/user/my-repo/README.md")));
+    }
+
+    #[test]
+    fn git_errors_are_classified() {
+        let auth = RepositoryError::from(Error::new(ErrorCode::Auth, ErrorClass::Http, "too many redirects or authentication replays"));
+        assert!(matches!(auth, RepositoryError::AuthFailed(_)));
+        let ssh = RepositoryError::from(Error::new(ErrorCode::GenericError, ErrorClass::Ssh, "failed to authenticate SSH session"));
+        assert!(matches!(ssh, RepositoryError::AuthFailed(_)));
+
+        // A 403 in the message alone is not an authentication failure
+        let http = RepositoryError::from(Error::new(ErrorCode::GenericError, ErrorClass::Http, "unexpected http status code: 403"));
+        assert!(matches!(http, RepositoryError::Network(_)));
+        let other = RepositoryError::from(Error::new(ErrorCode::NotFound, ErrorClass::Reference, "reference not found"));
+        assert!(matches!(other, RepositoryError::Other(_)));
+    }
+}
@@ -1,25 +1,38 @@
-use tauri::{tray::{TrayIconBuilder}, Manager, AppHandle, Runtime};
-use tauri::menu::{Menu, MenuItemBuilder};
+use tauri::{tray::{TrayIconBuilder}, Manager, AppHandle, Wry};
+use tauri::menu::{Menu, MenuBuilder, MenuItemBuilder, SubmenuBuilder};
 use tauri::tray::{TrayIconEvent};
+use tauri_plugin_notification::NotificationExt;
+use crate::commands::install::launch_install;
+use crate::utils::db_manager::{get_installs, get_launch_profiles_by_install_id};
 
-pub fn init_tray<R: Runtime>(app: &AppHandle<R>) -> Result<(), tauri::Error> {
-    let toggle_i = MenuItemBuilder::with_id("toggle", "Hide").build(app)?;
-    let menu1 = Menu::with_items(app, &[&toggle_i])?;
+pub fn init_tray(app: &AppHandle) -> Result<(), tauri::Error> {
+    let menu1 = build_tray_menu(app)?;
 
     let _ = TrayIconBuilder::with_id("tray_1").tooltip(&app.config().product_name.clone().unwrap()).show_menu_on_left_click(true).menu(&menu1)
         .icon(app.default_window_icon().unwrap().clone())
         .on_menu_event(move |app, event| match event.id().as_ref() {
             "toggle" => {
                 if let Some(window) = app.get_window("main") {
-                    let new_title = if window.is_visible().unwrap_or_default() {
+                    if window.is_visible().unwrap_or_default() {
                         let _ = window.hide();
-                        "Show"
                     } else {
                         let _ = window.show();
                         let _ = window.set_focus();
-                        "Hide"
-                    };
-                    toggle_i.set_text(new_title).unwrap();
+                    }
+                    refresh_tray_menu(app);
+                }
+            }
+            // launch|<install id>|<profile id>
+            id if id.starts_with("launch|") => {
+                let mut parts = id.splitn(3, '|').skip(1);
+                if let (Some(install), Some(profile)) = (parts.next(), parts.next()) {
+                    let (app, install, profile) = (app.clone(), install.to_string(), profile.to_string());
+                    std::thread::spawn(move || {
+                        // Anything but a database error is already reported by launch_install
+                        if let Err(e) = launch_install(&app, install, Some(profile)) {
+                            app.notification().builder().icon("dialog-error").title("TwintailLauncher").body(format!("Failed to launch game! {}", e)).show().unwrap();
+                        }
+                    });
                 }
             }
             _ => ()
@@ -38,4 +51,37 @@ pub fn init_tray<R: Runtime>(app: &AppHandle<R>) -> Result<(), tauri::Error> {
             _ => {}
         }).build(app)?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Rebuilds the tray menu, called whenever installs or their launch profiles change.
+pub fn refresh_tray_menu(app: &AppHandle) {
+    if let Some(tray) = app.tray_by_id("tray_1") {
+        match build_tray_menu(app) {
+            Ok(menu) => { tray.set_menu(Some(menu)).ok(); }
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                { println!("Failed to rebuild tray menu: {}", _e); }
+            }
+        }
+    }
+}
+
+fn build_tray_menu(app: &AppHandle) -> Result<Menu<Wry>, tauri::Error> {
+    let visible = app.get_window("main").map(|w| w.is_visible().unwrap_or_default()).unwrap_or(true);
+    let toggle_i = MenuItemBuilder::with_id("toggle", if visible { "Hide" } else { "Show" }).build(app)?;
+
+    // Every install gets a submenu listing its profiles, the default one first
+    let mut launch = SubmenuBuilder::new(app, "Launch");
+    let installs = get_installs(app).unwrap_or_default();
+    for i in &installs {
+        let mut install = SubmenuBuilder::new(app, i.name.clone());
+        for p in get_launch_profiles_by_install_id(app, i.id.clone()).unwrap_or_default() {
+            let label = if p.is_default { format!("{} (default)", p.name) } else { p.name.clone() };
+            install = install.item(&MenuItemBuilder::with_id(format!("launch|{}|{}", i.id, p.id), label).build(app)?);
+        }
+        launch = launch.item(&install.build()?);
+    }
+    let launch = launch.enabled(!installs.is_empty()).build()?;
+
+    MenuBuilder::new(app).item(&toggle_i).separator().item(&launch).build()
+}
@@ -5,6 +5,7 @@ import {POPUPS} from "./components/popups/POPUPS.ts";
 import AddRepo from "./components/popups/AddRepo.tsx";
 import SidebarIconManifest from "./components/SidebarIconManifest.tsx";
 import {invoke} from "@tauri-apps/api/core";
+import {listen} from "@tauri-apps/api/event";
 import SidebarRepos from "./components/SidebarRepos.tsx";
 import {ChevronDown, DownloadIcon, Settings} from "lucide-react";
 import SidebarSettings from "./components/SidebarSettings.tsx";
@@ -82,9 +83,9 @@ export default class App extends React.Component<any, any> {
                             }))
                         }} className={`h-5 w-14 align-middle border-transparent transition cursor-pointer duration-500 pb-0 mb-0 ${this.state.globalSettings.hide_manifests ? "rotate-00" : "rotate-180"}`}/>}/>
                         <div className={"w-full transition-all duration-500 overflow-scroll scrollbar-none gap-3 flex flex-col flex-shrink items-center"} style={{maxHeight: this.state.globalSettings.hide_manifests ? "0px" : (this.state.gamesinfo.length * 120) + "px"}}>
-                            {this.state.currentGame != "" && this.state.gamesinfo.map((game: { manifest_enabled: boolean; assets: any; filename: string; icon: string; display_name: string; biz: string; }) => {
+                            {this.state.currentGame != "" && this.state.gamesinfo.map((game: { manifest_enabled: boolean; assets: any; filename: string; icon: string; display_name: string; biz: string; key: string; }) => {
                                 return (
-                                    <SidebarIconManifest key={game.biz} popup={this.state.openPopup} icon={game.assets.game_icon} background={game.assets.game_background} name={game.display_name} enabled={game.manifest_enabled} id={game.biz} setCurrentGame={this.setCurrentGame} setOpenPopup={this.setOpenPopup} setDisplayName={this.setDisplayName} setBackground={this.setBackground} setCurrentInstall={this.setCurrentInstall} setGameIcon={this.setGameIcon} />
+                                    <SidebarIconManifest key={game.key} popup={this.state.openPopup} icon={game.assets.game_icon} background={game.assets.game_background} name={game.display_name} enabled={game.manifest_enabled} id={game.key} setCurrentGame={this.setCurrentGame} setOpenPopup={this.setOpenPopup} setDisplayName={this.setDisplayName} setBackground={this.setBackground} setCurrentInstall={this.setCurrentInstall} setGameIcon={this.setGameIcon} />
                                 )
                             })}
                         </div>
@@ -123,7 +124,7 @@ export default class App extends React.Component<any, any> {
                     {this.state.openPopup == POPUPS.REPOMANAGER && <RepoManager repos={this.state.reposList} setOpenPopup={this.setOpenPopup} fetchRepositories={this.fetchRepositories}/>}
                     {this.state.openPopup == POPUPS.ADDREPO && <AddRepo setOpenPopup={this.setOpenPopup}/>}
                     {this.state.openPopup == POPUPS.SETTINGS && <SettingsGlobal fetchSettings={this.fetchSettings} settings={this.state.globalSettings} setOpenPopup={this.setOpenPopup} />}
-                    {this.state.openPopup == POPUPS.DOWNLOADGAME && <DownloadGame fetchDownloadSizes={this.fetchDownloadSizes} disk={this.state.downloadSizes} runnerVersions={this.state.runnerVersions} dxvkVersions={this.state.dxvkVersions} versions={this.state.gameVersions} icon={this.state.gameIcon} background={this.state.gameBackground} biz={this.state.currentGame} displayName={this.state.displayName} settings={this.state.globalSettings} setOpenPopup={this.setOpenPopup} pushInstalls={this.pushInstalls} setBackground={this.setBackground} setCurrentInstall={this.setCurrentInstall}/>}
+                    {this.state.openPopup == POPUPS.DOWNLOADGAME && <DownloadGame fetchDownloadSizes={this.fetchDownloadSizes} disk={this.state.downloadSizes} runnerVersions={this.state.runnerVersions} dxvkVersions={this.state.dxvkVersions} versions={this.state.gameVersions} icon={this.state.gameIcon} background={this.state.gameBackground} biz={this.getCurrentGameBiz()} manifestId={this.getCurrentManifestId()} displayName={this.state.displayName} settings={this.state.globalSettings} setOpenPopup={this.setOpenPopup} pushInstalls={this.pushInstalls} setBackground={this.setBackground} setCurrentInstall={this.setCurrentInstall}/>}
                     {this.state.openPopup == POPUPS.INSTALLSETTINGS && <SettingsInstall games={this.state.gamesinfo} runnerVersions={this.state.runnerVersions} dxvkVersions={this.state.dxvkVersions} installSettings={this.state.installSettings} setOpenPopup={this.setOpenPopup} pushInstalls={this.pushInstalls} setCurrentInstall={this.setCurrentInstall} setCurrentGame={this.setCurrentGame} setBackground={this.setBackground} fetchInstallSettings={this.fetchInstallSettings}/>}
                     {this.state.openPopup == POPUPS.INSTALLDELETECONFIRMATION && <InstallDeleteConfirm games={this.state.gamesinfo} install={this.state.installSettings} setOpenPopup={this.setOpenPopup} pushInstalls={this.pushInstalls} setCurrentInstall={this.setCurrentInstall} setCurrentGame={this.setCurrentGame} setBackground={this.setBackground}/>}
                 </div>
@@ -134,6 +135,8 @@ export default class App extends React.Component<any, any> {
     componentDidMount() {
         this.fetchSettings();
         this.fetchRepositories();
+        // Manifests edited on disk or pulled by a sync get picked up without a restart
+        listen("manifests_reloaded", () => { this.fetchRepositories(); }).then(() => {});
     }
 
     componentDidUpdate(_prevProps: any, prevState: any) {
@@ -183,31 +186,21 @@ export default class App extends React.Component<any, any> {
                 console.error("GameManifest repository fetch issue, some serious fuck up happened!")
             } else {
                 let gi = JSON.parse(m as string);
-                // Hacky way to pass some values from DB manifest data onto the list of games we use to render SideBarIcon components
-                gi.forEach((e: any) => {
-                  let g = games.find(g => g.filename.replace(".json", "") === e.biz);
-                  // @ts-ignore
-                    e["manifest_id"] = g.id;
-                  // @ts-ignore
-                    e["manifest_enabled"] = g.enabled;
-                  // @ts-ignore
-                    e["manifest_file"] = g.filename;
-                });
 
                 this.setState(() => ({gamesinfo: gi}), () => {
                     if (this.state.installs.length === 0) {
                         if (games.length > 0 && this.state.currentGame == "") {
-                            this.setCurrentGame(games[0].filename.replace(".json", ""));
+                            this.setCurrentGame(gi[0].key);
                             this.setDisplayName(games[0].display_name);
                             this.setBackground(gi[0].assets.game_background);
                             this.setGameIcon(gi[0].assets.game_icon);
                             setTimeout(() => {
                                 // @ts-ignore
-                                document.getElementById(gi[0].biz).focus();
+                                document.getElementById(gi[0].key).focus();
                             }, 20);
                         }
                     } else {
-                        this.setCurrentGame(games[0].filename.replace(".json", ""));
+                        this.setCurrentGame(gi[0].key);
                         this.setDisplayName(this.state.installs[0].name);
                         this.setBackground(this.state.installs[0].game_background);
                         this.setGameIcon(this.state.installs[0].game_icon);
@@ -263,8 +256,8 @@ export default class App extends React.Component<any, any> {
         });
     }
 
-    fetchGameVersions(biz: string) {
-        let game = this.state.gamesinfo.filter((g: any) => g.biz == biz)[0];
+    fetchGameVersions(key: string) {
+        let game = this.state.gamesinfo.filter((g: any) => g.key == key)[0];
         let tmp: { value: any; name: any; }[] = [];
         game.game_versions.forEach((g: any) => {
             tmp.push({value: g.metadata.version, name: (game.latest_version === g.metadata.version) ? `Latest (${g.metadata.version})` : g.metadata.version});
@@ -282,18 +275,34 @@ export default class App extends React.Component<any, any> {
                 let wines: any[] = [];
                 // Bad but will work for now... DO NOT EVER FILTER LIKE THIS...
                 r.filter((e: any) => e.display_name.toLowerCase().includes("dxvk")).forEach((e: any) => {
-                    e.versions.forEach((v: any) => dxvks.push({value: v.version, name: v.version}));
+                    e.versions.forEach((v: any) => dxvks.push(v));
                 });
                 r.filter((e: any) => !e.display_name.toLowerCase().includes("dxvk")).forEach((e: any) => {
-                    e.versions.forEach((v: any) => wines.push({value: v.version, name: v.version}));
+                    e.versions.forEach((v: any) => wines.push(v));
                 });
-                this.setState({runnerVersions: wines, dxvkVersions: dxvks});
+                // Recommended versions come first so they are preselected, deprecated ones sink to the bottom
+                let rank = (v: any) => v.recommended ? 0 : (v.deprecated ? 2 : 1);
+                let options = (versions: any[]) => versions.sort((a, b) => rank(a) - rank(b)).map((v: any) => ({value: v.version, name: v.recommended ? `${v.version} (recommended)` : (v.deprecated ? `${v.version} (deprecated)` : v.version)}));
+                this.setState({runnerVersions: options(wines), dxvkVersions: options(dxvks)});
             }
         })
     }
 
-    fetchDownloadSizes(biz: any, version: any, lang: any, path: any, callback: (data: any) => void) {
-        invoke("get_download_sizes", {biz: biz, version: version, path: path, lang: lang}).then(data => {
+    getCurrentManifestId() {
+        let game = this.state.gamesinfo.filter((g: any) => g.key == this.state.currentGame)[0];
+        // @ts-ignore
+        return (game !== undefined) ? game.manifest_id : "";
+    }
+
+    // Games are keyed by "<repository id>/<file name>", biz is only used for naming directories
+    getCurrentGameBiz() {
+        let game = this.state.gamesinfo.filter((g: any) => g.key == this.state.currentGame)[0];
+        // @ts-ignore
+        return (game !== undefined) ? game.biz : "";
+    }
+
+    fetchDownloadSizes(manifestId: any, version: any, lang: any, path: any, callback: (data: any) => void) {
+        invoke("get_download_sizes", {manifestId: manifestId, version: version, path: path, lang: lang}).then(data => {
             if (data === null) {
                 console.error("Could not get download sizes!");
             } else {
@@ -319,7 +328,7 @@ export default class App extends React.Component<any, any> {
         this.fetchGameVersions(this.state.currentGame);
         this.fetchCompatibilityVersions();
         setTimeout(() => {
-            this.fetchDownloadSizes(this.state.currentGame, this.state.gameVersions[0].value, "en-us", `${this.state.globalSettings.default_game_path}/${this.state.currentGame}`, () => {});
+            this.fetchDownloadSizes(this.getCurrentManifestId(), this.state.gameVersions[0].value, "en-us", `${this.state.globalSettings.default_game_path}/${this.getCurrentGameBiz()}`, () => {});
             this.setState({openPopup: POPUPS.DOWNLOADGAME});
         }, 20);
     }
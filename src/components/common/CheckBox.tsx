@@ -57,6 +57,8 @@ export default function CheckBox({ id, name, enabled, install, fetchSettings, fe
                                 invoke("update_install_use_jadeite", {enabled: !isEnabled, id: install}).then(() => {
                                     setIsEnabled(!isEnabled);
                                     fetchInstallSettings(install as string)
+                                }).catch(e => {
+                                    console.error("Failed to install jadeite: " + e.message)
                                 });
                             }
                         }
@@ -66,6 +68,8 @@ export default function CheckBox({ id, name, enabled, install, fetchSettings, fe
                                 invoke("update_install_use_xxmi", {enabled: !isEnabled, id: install}).then(() => {
                                     setIsEnabled(!isEnabled);
                                     fetchInstallSettings(install as string)
+                                }).catch(e => {
+                                    console.error("Failed to install XXMI: " + e.message)
                                 });
                             }
                         }
@@ -75,6 +79,8 @@ export default function CheckBox({ id, name, enabled, install, fetchSettings, fe
                                 invoke("update_install_use_fps_unlock", {enabled: !isEnabled, id: install}).then(() => {
                                     setIsEnabled(!isEnabled);
                                     fetchInstallSettings(install as string)
+                                }).catch(e => {
+                                    console.error("Failed to install fps unlock: " + e.message)
                                 });
                             }
                         }
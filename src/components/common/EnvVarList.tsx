@@ -0,0 +1,41 @@
+import {useState} from "react";
+import {invoke} from "@tauri-apps/api/core";
+import {Plus, X} from "lucide-react";
+import HelpTooltip from "./HelpTooltip.tsx";
+
+export default function EnvVarList({ name, envVars, install, fetchInstallSettings, helpText}: { name: string, envVars: { key: string, value: string, enabled: boolean }[], install: string, fetchInstallSettings: (id: string) => void, helpText: string }) {
+    const [key, setKey] = useState<string>("");
+    const [value, setValue] = useState<string>("");
+
+    const run = (cmd: string, args: any) => {
+        invoke(cmd, {id: install, ...args}).then(() => {
+            fetchInstallSettings(install);
+        }).catch(e => console.error(`Failed to update environment variables: ${e.message}`));
+    };
+
+    return (
+        <div className="flex flex-col w-full gap-2">
+            <span className="text-white text-sm flex items-center gap-1">{name}
+                <HelpTooltip text={helpText}/>
+            </span>
+            {envVars.map((e) => (
+                <div key={e.key} className="flex flex-row items-center gap-2 w-full h-8">
+                    <input type={"checkbox"} className="cursor-pointer" checked={e.enabled} onChange={() => run("toggle_install_env_var", {key: e.key, enabled: !e.enabled})}/>
+                    <span className={`text-sm w-1/3 truncate ${e.enabled ? "text-white" : "text-white/50 line-through"}`}>{e.key}</span>
+                    <span className={`text-sm flex-1 truncate ${e.enabled ? "text-white" : "text-white/50"}`}>{e.value}</span>
+                    <X className="text-white cursor-pointer shrink-0" onClick={() => run("remove_install_env_var", {key: e.key})}/>
+                </div>
+            ))}
+            <div className="flex flex-row items-center gap-2 w-full">
+                <input className="w-full focus:outline-none h-8 rounded-lg bg-white/20 text-white px-2 placeholder-white/50" value={key} placeholder={"DXVK_HUD"} onChange={(e) => setKey(e.target.value)}/>
+                <input className="w-full focus:outline-none h-8 rounded-lg bg-white/20 text-white px-2 placeholder-white/50" value={value} placeholder={"fps"} onChange={(e) => setValue(e.target.value)}/>
+                <Plus className="text-white cursor-pointer shrink-0" onClick={() => {
+                    if (key.trim() === "" || key.includes("=")) return;
+                    run("add_install_env_var", {key: key.trim(), value: value});
+                    setKey("");
+                    setValue("");
+                }}/>
+            </div>
+        </div>
+    )
+}
@@ -0,0 +1,37 @@
+import {useState} from "react";
+import {invoke} from "@tauri-apps/api/core";
+import {Plus, X} from "lucide-react";
+import HelpTooltip from "./HelpTooltip.tsx";
+
+export default function LaunchArgList({ name, args, install, fetchInstallSettings, helpText}: { name: string, args: string[], install: string, fetchInstallSettings: (id: string) => void, helpText: string }) {
+    const [arg, setArg] = useState<string>("");
+
+    // Arguments are passed to the game as they are, spaces and quotes included
+    const save = (argv: string[]) => {
+        invoke("update_install_launch_argv", {id: install, argv: argv}).then(() => {
+            fetchInstallSettings(install);
+        }).catch(e => console.error(`Failed to update launch arguments: ${e.message}`));
+    };
+
+    return (
+        <div className="flex flex-col w-full gap-2">
+            <span className="text-white text-sm flex items-center gap-1">{name}
+                <HelpTooltip text={helpText}/>
+            </span>
+            {args.map((a, i) => (
+                <div key={`${i}-${a}`} className="flex flex-row items-center gap-2 w-full h-8">
+                    <span className="text-white text-sm flex-1 truncate font-mono">{a}</span>
+                    <X className="text-white cursor-pointer shrink-0" onClick={() => save(args.filter((_, j) => j !== i))}/>
+                </div>
+            ))}
+            <div className="flex flex-row items-center gap-2 w-full">
+                <input className="w-full focus:outline-none h-8 rounded-lg bg-white/20 text-white px-2 placeholder-white/50" value={arg} placeholder={"-dx11"} onChange={(e) => setArg(e.target.value)}/>
+                <Plus className="text-white cursor-pointer shrink-0" onClick={() => {
+                    if (arg === "") return;
+                    save([...args, arg]);
+                    setArg("");
+                }}/>
+            </div>
+        </div>
+    )
+}
@@ -85,6 +85,8 @@ export default function SelectMenu({ id, name, options, selected, multiple, inst
                             if (fetchInstallSettings !== undefined) {
                                 invoke("update_install_fps_value", {fps: `${e.target.value}`, id: install}).then(() => {
                                     fetchInstallSettings(install as string)
+                                }).catch(e => {
+                                    console.error("Failed to install fps unlock: " + e.message)
                                 });
                             }
                         }
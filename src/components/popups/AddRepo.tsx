@@ -1,9 +1,12 @@
 import {POPUPS} from "./POPUPS.ts";
-import {ArrowLeft, ChevronDown, X} from "lucide-react";
+import {ArrowLeft, ChevronDown, FolderOpen, X} from "lucide-react";
 import React, {useState} from "react";
+import {invoke} from "@tauri-apps/api/core";
+import {open} from "@tauri-apps/plugin-dialog";
 
 export default function AddRepo({setOpenPopup}: {setOpenPopup: (popup: POPUPS) => void}) {
     const [advanncedOptionsOpen, setAdvanncedOptionsOpen] = useState<boolean>(false);
+    const [error, setError] = useState<string>("");
 
     return (
         <div className="rounded-lg h-full w-3/4 flex flex-col p-4 bg-neutral-700 gap-4 overflow-scroll">
@@ -37,13 +40,27 @@ export default function AddRepo({setOpenPopup}: {setOpenPopup: (popup: POPUPS) =
 					   placeholder="Override Name"/>
 			</React.Fragment>}
             <div className="flex-grow">{/* Spacer */}</div>
-            <div className="flex flex-row-reverse">
+            {error !== "" && <span className="text-red-400 text-sm">{error}</span>}
+            <div className="flex flex-row-reverse gap-2">
                 <button className="flex flex-row gap-1 items-center p-2 bg-blue-600 rounded-lg" onClick={() => {
                     // TODO(ipc): Communicate to Rust
                     setOpenPopup(POPUPS.REPOMANAGER);
                 }}>
                     <span className="font-semibold translate-y-px">Add Repository</span>
                 </button>
+                {/* Local repositories are read in place, handy while writing manifests */}
+                <button className="flex flex-row gap-1 items-center p-2 bg-white/20 rounded-lg" onClick={async () => {
+                    let path = await open({directory: true});
+                    if (path === null) return;
+                    invoke("add_local_repository", {path: path}).then(() => {
+                        setOpenPopup(POPUPS.REPOMANAGER);
+                    }).catch(e => {
+                        setError(e.message !== undefined ? e.message : e.kind);
+                    });
+                }}>
+                    <FolderOpen className="text-white w-5 h-5"/>
+                    <span className="font-semibold translate-y-px text-white">Add Local Folder</span>
+                </button>
             </div>
         </div>
     )
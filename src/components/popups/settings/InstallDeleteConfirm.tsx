@@ -38,10 +38,10 @@ export default function InstallDeleteConfirm({setOpenPopup, install, games, push
                             if (r) {
                                 pushInstalls();
                                 setCurrentInstall("");
-                                setCurrentGame(games[0].biz);
+                                setCurrentGame(games[0].key);
                                 setBackground(games[0].assets.game_background);
                                 // @ts-ignore
-                                document.getElementById(games[0].biz).focus();
+                                document.getElementById(games[0].key).focus();
                             } else {
                                 console.error("Uninstall error!");
                             }
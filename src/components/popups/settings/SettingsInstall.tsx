@@ -3,6 +3,8 @@ import {POPUPS} from "../POPUPS.ts";
 import FolderInput from "../../common/FolderInput.tsx";
 import CheckBox from "../../common/CheckBox.tsx";
 import TextInput from "../../common/TextInput.tsx";
+import EnvVarList from "../../common/EnvVarList.tsx";
+import LaunchArgList from "../../common/LaunchArgList.tsx";
 import SelectMenu from "../../common/SelectMenu.tsx";
 import {invoke} from "@tauri-apps/api/core";
 import React from "react";
@@ -81,10 +83,10 @@ export default class SettingsInstall extends React.Component<IProps, IState> {
                     {(this.state.gameSwitches.xxmi) ? <CheckBox enabled={this.props.installSettings.use_xxmi} name={"Inject XXMI"} id={"tweak_xxmi"} fetchInstallSettings={this.props.fetchInstallSettings} install={this.props.installSettings.id} helpText={"Enable and inject XXMI modding tool."}/> : null}
                     {(this.state.gameSwitches.fps_unlocker) ? <CheckBox enabled={this.props.installSettings.use_fps_unlock} name={"Inject FPS Unlocker"} id={"tweak_fps_unlock"} fetchInstallSettings={this.props.fetchInstallSettings} install={this.props.installSettings.id} helpText={"Load and inject fps unlocking into the game. Pick FPS in the menu bellow."}/> : null}
                     {(this.state.gameSwitches.fps_unlocker) ? <SelectMenu id={"install_fps_value"} name={"FPS value"} multiple={false} options={this.state.gameFps} selected={`${this.props.installSettings.fps_value}`} fetchInstallSettings={this.props.fetchInstallSettings} install={this.props.installSettings.id} helpText={"Target FPS to unlock game to."}/> : null}
-                    <TextInput name={"Environment variables"} value={this.props.installSettings.env_vars} readOnly={false} id={"install_env_vars"} placeholder={"DXVK_HUD=fps;DXVK_LOG=none;"} fetchInstallSettings={this.props.fetchInstallSettings} install={this.props.installSettings.id} helpText={`Pass extra variables to Wine/Proton. Each entry is divided and list must end with ";"`}/>
+                    <EnvVarList name={"Environment variables"} envVars={this.props.installSettings.env_vars ?? []} install={this.props.installSettings.id} fetchInstallSettings={this.props.fetchInstallSettings} helpText={"Pass extra variables to Wine/Proton. Disabled entries are kept but not passed to the game."}/>
                     <TextInput name={"Pre launch command"} value={this.props.installSettings.pre_launch_command} readOnly={false} id={"install_pre_launch_cmd"} placeholder={"%command%"} fetchInstallSettings={this.props.fetchInstallSettings} install={this.props.installSettings.id} helpText={"Command that will be ran before game launches. Running stuff under Wine/Proton requires you to call runner binary."}/>
                     <TextInput name={"Launch command"} value={this.props.installSettings.launch_command} readOnly={false} id={"install_launch_cmd"} placeholder={"%command%"} fetchInstallSettings={this.props.fetchInstallSettings} install={this.props.installSettings.id} helpText={"Custom command to launch the game. On linux this will run whatever you enter here inside Wine/Proton."}/>
-                    <TextInput name={"Launch arguments"} value={this.props.installSettings.launch_args} readOnly={false} id={"install_launch_args"} placeholder={"-dx11 -whatever -thisonetoo"} fetchInstallSettings={this.props.fetchInstallSettings} install={this.props.installSettings.id} helpText={"Additional arguments to pass to the game. Each entry is separated with space."}/>
+                    <LaunchArgList name={"Launch arguments"} args={this.props.installSettings.launch_args ?? []} install={this.props.installSettings.id} fetchInstallSettings={this.props.fetchInstallSettings} helpText={"Additional arguments to pass to the game. Each entry is passed as a single argument, spaces included."}/>
                     {(window.navigator.platform.includes("Linux")) ? <SelectMenu id={"install_runner_version"} name={"Runner version"} multiple={false} options={this.props.runnerVersions} selected={(this.props.installSettings.runner_version === "none" || this.props.installSettings.runner_version === "") ? this.props.runnerVersions[0].value : this.props.installSettings.runner_version} install={this.props.installSettings.id} fetchInstallSettings={this.props.fetchInstallSettings} helpText={"Wine/Proton version used by this installation."}/> : null}
                     {(window.navigator.platform.includes("Linux")) ? <FolderInput name={"Runner path"} clearable={true} value={`${this.props.installSettings.runner_path}`} folder={true} id={"install_runner_path"} fetchInstallSettings={this.props.fetchInstallSettings} install={this.props.installSettings.id} setOpenPopup={this.props.setOpenPopup} helpText={`Location of the Wine/Proton runner. Usually points to directory containing "bin" or "files" directory.`}/> : null}
                     {(window.navigator.platform.includes("Linux")) ? <FolderInput name={"Runner prefix path"} clearable={true} value={`${this.props.installSettings.runner_prefix}`} folder={true} id={"install_prefix_path2"} fetchInstallSettings={this.props.fetchInstallSettings} install={this.props.installSettings.id} setOpenPopup={this.props.setOpenPopup} helpText={`Location where Wine/Proton prefix is stored. Should point to directory where "system.reg" is stored.`}/> : null}